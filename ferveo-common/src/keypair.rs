@@ -1,5 +1,6 @@
 use ark_ec::PairingEngine;
 use ark_ec::{AffineCurve, ProjectiveCurve};
+use ark_ff::{PrimeField, Zero};
 use ark_serialize::{
     CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write,
 };
@@ -42,6 +43,74 @@ impl<E: PairingEngine> Default for PublicKey<E> {
     }
 }
 
+/// Domain separation tag for the challenge of a proof of possession
+const POP_DOMAIN: &[u8] = b"FERVEO-SESSION-KEY-POP";
+
+/// A proof that the owner of a session public key knows the matching
+/// decryption key, bound to the address of the validator registering it
+///
+/// This is a Schnorr proof of knowledge of `dk` such that `ek = h^dk`
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Eq,
+    PartialEq,
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct ProofOfPossession<E: PairingEngine> {
+    /// The commitment R = h^k to the nonce k
+    #[serde(with = "crate::ark_serde")]
+    pub commitment: E::G2Affine,
+    /// The response s = k + c dk to the challenge c
+    #[serde(with = "crate::ark_serde")]
+    pub response: E::Fr,
+}
+
+/// The challenge of a proof of possession of `public_key` by `address`
+/// with commitment `commitment`
+fn pop_challenge<E: PairingEngine>(
+    public_key: &PublicKey<E>,
+    address: &str,
+    commitment: &E::G2Affine,
+) -> E::Fr {
+    let mut bytes = vec![];
+    bytes.extend_from_slice(&(address.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(address.as_bytes());
+    CanonicalSerialize::serialize(public_key, &mut bytes)
+        .expect("serializing to a vector can not fail");
+    CanonicalSerialize::serialize(commitment, &mut bytes)
+        .expect("serializing to a vector can not fail");
+    let mut state = blake2b_simd::Params::new().hash_length(64).to_state();
+    state.update(&(POP_DOMAIN.len() as u64).to_le_bytes());
+    state.update(POP_DOMAIN);
+    state.update(&bytes);
+    E::Fr::from_le_bytes_mod_order(state.finalize().as_bytes())
+}
+
+impl<E: PairingEngine> PublicKey<E> {
+    /// Verify that the validator with address `address` knows the
+    /// decryption key of this session public key
+    ///
+    /// The identity is never accepted as a session public key
+    pub fn verify_possession(
+        &self,
+        address: &str,
+        proof: &ProofOfPossession<E>,
+    ) -> bool {
+        if self.encryption_key.is_zero() {
+            return false;
+        }
+        let c = pop_challenge(self, address, &proof.commitment);
+        // h^s = R ek^c
+        E::G2Affine::prime_subgroup_generator().mul(proof.response)
+            == self.encryption_key.mul(c) + proof.commitment.into_projective()
+    }
+}
+
 #[derive(
     Clone,
     Copy,
@@ -75,4 +144,45 @@ impl<E: PairingEngine> Keypair<E> {
             decryption_key: E::Fr::rand(rng),
         }
     }
+
+    /// Prove that the validator with address `address` knows the
+    /// decryption key of this session keypair
+    /// `rng` is a cryptographic random number generator
+    pub fn prove_possession<R: crate::Rng>(
+        &self,
+        address: &str,
+        rng: &mut R,
+    ) -> ProofOfPossession<E> {
+        use ark_std::UniformRand;
+        let k = E::Fr::rand(rng);
+        let commitment =
+            E::G2Affine::prime_subgroup_generator().mul(k).into_affine();
+        let c = pop_challenge(&self.public(), address, &commitment);
+        ProofOfPossession {
+            commitment,
+            response: k + c * self.decryption_key,
+        }
+    }
+}
+
+#[test]
+fn test_proof_of_possession() {
+    use ark_bls12_381::Bls12_381 as E;
+    let rng = &mut ark_std::test_rng();
+    let keypair = Keypair::<E>::new(rng);
+    let public_key = keypair.public();
+    let proof = keypair.prove_possession("validator_0", rng);
+    assert!(public_key.verify_possession("validator_0", &proof));
+    // the proof is bound to the address and to the key
+    assert!(!public_key.verify_possession("validator_1", &proof));
+    assert!(!Keypair::<E>::new(rng)
+        .public()
+        .verify_possession("validator_0", &proof));
+
+    // the identity is rejected even though its key is known
+    let identity = Keypair::<E> {
+        decryption_key: Zero::zero(),
+    };
+    let proof = identity.prove_possession("validator_0", rng);
+    assert!(!identity.public().verify_possession("validator_0", &proof));
 }
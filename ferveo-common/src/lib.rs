@@ -3,32 +3,62 @@ use ark_ec::PairingEngine;
 use ark_serialize::{
     CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write,
 };
+use serde::{Deserialize, Serialize};
 
 pub mod keypair;
 pub use keypair::*;
 
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize, PartialEq)]
+#[derive(
+    Clone,
+    Debug,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+    PartialEq,
+    Serialize,
+    Deserialize,
+)]
+#[serde(bound = "")]
 /// Represents an external validator
 pub struct ExternalValidator<E: PairingEngine> {
     /// The established address of the validator
     pub address: String,
     /// The Public key
     pub public_key: PublicKey<E>,
+    /// The voting power of the validator, used to weight its shares
+    pub power: u64,
 }
 
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(
+    Clone,
+    Debug,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+    Serialize,
+    Deserialize,
+)]
+#[serde(bound = "")]
+/// A validator together with the contiguous range of shares it holds
 pub struct Validator<E: PairingEngine> {
     pub validator: ExternalValidator<E>,
-    pub share_index: usize,
+    /// The number of shares allocated to this validator
+    pub weight: u32,
+    /// The index of the first share held by this validator
+    pub share_start: usize,
+    /// One past the index of the last share held by this validator
+    pub share_end: usize,
 }
 
 impl Rng for ark_std::rand::prelude::StdRng {}
 
+impl Rng for rand_chacha::ChaCha20Rng {}
+
 pub trait Rng: ark_std::rand::CryptoRng + ark_std::rand::RngCore {}
 
+/// Serde for ark types, as hex strings in human-readable formats such
+/// as JSON and as bytes in binary formats such as bincode
 pub mod ark_serde {
     use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
-    use serde_bytes::{Deserialize, Serialize};
+    use serde::{Deserialize, Serialize};
 
     /// Serialize an ark type with serde
     pub fn serialize<S, T>(data: &T, serializer: S) -> Result<S::Ok, S::Error>
@@ -38,8 +68,13 @@ pub mod ark_serde {
     {
         use serde::ser::Error;
         let mut bytes = vec![];
-        data.serialize(&mut bytes).map_err(Error::custom)?;
-        serde_bytes::Bytes::new(&bytes).serialize(serializer)
+        CanonicalSerialize::serialize(data, &mut bytes)
+            .map_err(Error::custom)?;
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&hex::encode(&bytes))
+        } else {
+            serde_bytes::serialize(&bytes, serializer)
+        }
     }
     /// Deserialize an ark type with serde
     pub fn deserialize<'d, D, T>(deserializer: D) -> Result<T, D::Error>
@@ -48,25 +83,148 @@ pub mod ark_serde {
         T: CanonicalDeserialize,
     {
         use serde::de::Error;
-        let bytes = <serde_bytes::ByteBuf>::deserialize(deserializer)?;
-        T::deserialize(bytes.as_slice()).map_err(Error::custom)
+        let bytes = if deserializer.is_human_readable() {
+            let hex = <String as Deserialize>::deserialize(deserializer)?;
+            hex::decode(hex).map_err(Error::custom)?
+        } else {
+            serde_bytes::deserialize::<Vec<u8>, _>(deserializer)?
+        };
+        CanonicalDeserialize::deserialize(bytes.as_slice())
+            .map_err(Error::custom)
+    }
+
+    /// An ark type wrapped to be serialized with [`serialize`]
+    struct Ark<T>(T);
+
+    impl<T: CanonicalSerialize + std::fmt::Debug> Serialize for Ark<&T> {
+        fn serialize<S: serde::Serializer>(
+            &self,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serialize(self.0, serializer)
+        }
+    }
+
+    impl<'d, T: CanonicalDeserialize> Deserialize<'d> for Ark<T> {
+        fn deserialize<D: serde::Deserializer<'d>>(
+            deserializer: D,
+        ) -> Result<Self, D::Error> {
+            deserialize(deserializer).map(Ark)
+        }
+    }
+
+    /// Serde for vectors of ark types, as a list with one hex string per
+    /// element in human-readable formats and as bytes in binary formats
+    pub mod seq {
+        use super::*;
+
+        /// Serialize a vector of ark types with serde
+        pub fn serialize<S, T>(
+            data: &Vec<T>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+            T: CanonicalSerialize + std::fmt::Debug,
+        {
+            if serializer.is_human_readable() {
+                serializer.collect_seq(data.iter().map(Ark))
+            } else {
+                super::serialize(data, serializer)
+            }
+        }
+        /// Deserialize a vector of ark types with serde
+        pub fn deserialize<'d, D, T>(
+            deserializer: D,
+        ) -> Result<Vec<T>, D::Error>
+        where
+            D: serde::Deserializer<'d>,
+            T: CanonicalSerialize + CanonicalDeserialize,
+        {
+            if deserializer.is_human_readable() {
+                let data = Vec::<Ark<T>>::deserialize(deserializer)?;
+                Ok(data.into_iter().map(|Ark(t)| t).collect())
+            } else {
+                super::deserialize(deserializer)
+            }
+        }
+    }
+
+    /// Serde for vectors of vectors of ark types, as nested lists of hex
+    /// strings in human-readable formats and as bytes in binary formats
+    pub mod seq2 {
+        use super::*;
+
+        /// Serialize a vector of vectors of ark types with serde
+        pub fn serialize<S, T>(
+            data: &Vec<Vec<T>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+            T: CanonicalSerialize + std::fmt::Debug,
+        {
+            if serializer.is_human_readable() {
+                serializer.collect_seq(
+                    data.iter()
+                        .map(|inner| inner.iter().map(Ark).collect::<Vec<_>>()),
+                )
+            } else {
+                super::serialize(data, serializer)
+            }
+        }
+        /// Deserialize a vector of vectors of ark types with serde
+        pub fn deserialize<'d, D, T>(
+            deserializer: D,
+        ) -> Result<Vec<Vec<T>>, D::Error>
+        where
+            D: serde::Deserializer<'d>,
+            T: CanonicalSerialize + CanonicalDeserialize,
+        {
+            if deserializer.is_human_readable() {
+                let data = Vec::<Vec<Ark<T>>>::deserialize(deserializer)?;
+                Ok(data
+                    .into_iter()
+                    .map(|inner| inner.into_iter().map(|Ark(t)| t).collect())
+                    .collect())
+            } else {
+                super::deserialize(deserializer)
+            }
+        }
     }
 }
 
 #[test]
 fn test_ark_serde() {
-    use ark_bls12_381::G1Affine;
+    use ark_bls12_381::{G1Affine, G2Affine};
     use serde::{Deserialize, Serialize};
     #[derive(Serialize, Deserialize)]
     struct Test {
         #[serde(with = "ark_serde")]
         pub p: G1Affine,
+        #[serde(with = "ark_serde::seq")]
+        pub ps: Vec<G1Affine>,
+        #[serde(with = "ark_serde::seq2")]
+        pub qs: Vec<Vec<G2Affine>>,
     }
     use ark_ec::AffineCurve;
     let p = G1Affine::prime_subgroup_generator();
-    let t = Test { p };
-    let m = serde_json::to_string(&t).unwrap();
-    let _t2: Test = serde_json::from_str(&m).unwrap();
+    let q = G2Affine::prime_subgroup_generator();
+    let t = Test {
+        p,
+        ps: vec![p, p],
+        qs: vec![vec![q], vec![], vec![q, q]],
+    };
+    let mut bytes = vec![];
+    p.serialize(&mut bytes).unwrap();
+    let m = serde_json::to_value(&t).unwrap();
+    // group elements are hex strings in JSON
+    assert_eq!(m["p"], serde_json::json!(hex::encode(&bytes)));
+    assert_eq!(m["ps"][1], m["p"]);
+    assert_eq!(m["qs"][2].as_array().unwrap().len(), 2);
+    let t2: Test = serde_json::from_value(m).unwrap();
+    assert_eq!((t2.p, t2.ps, t2.qs), (t.p, t.ps.clone(), t.qs.clone()));
     let m = bincode::serialize(&t).unwrap();
-    let _t2: Test = bincode::deserialize(&m).unwrap();
+    let t2: Test = bincode::deserialize(&m).unwrap();
+    assert_eq!((t2.p, t2.ps, t2.qs), (t.p, t.ps, t.qs));
 }
@@ -26,6 +26,22 @@ pub fn gen_validators(
         .map(|i| ExternalValidator {
             address: format!("validator_{}", i),
             public_key: keypairs[i].public(),
+            power: 1,
+        })
+        .collect()
+}
+
+/// Generate the proofs of possession of the session keys of `validators`
+pub fn gen_proofs(
+    keypairs: &[ferveo_common::Keypair<EllipticCurve>],
+    validators: &[ExternalValidator<EllipticCurve>],
+) -> Vec<ferveo_common::ProofOfPossession<EllipticCurve>> {
+    let rng = &mut ark_std::test_rng();
+    validators
+        .iter()
+        .zip(keypairs.iter())
+        .map(|(validator, keypair)| {
+            keypair.prove_possession(&validator.address, rng)
         })
         .collect()
 }
@@ -38,14 +54,16 @@ pub fn setup_dkg(
 ) -> PubliclyVerifiableDkg<EllipticCurve> {
     let keypairs = gen_keypairs(num);
     let validators = gen_validators(&keypairs);
+    let proofs = gen_proofs(&keypairs, &validators);
     let me = validators[validator].clone();
     PubliclyVerifiableDkg::new(
         validators,
+        &proofs,
         Params {
             tau: 0,
             security_threshold: shares_num / 3,
             shares_num,
-            retry_after: 1,
+            dealings_per_block: 1,
         },
         &me,
         keypairs[validator],
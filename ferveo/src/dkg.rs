@@ -3,7 +3,6 @@
 #![allow(unused_imports)]
 
 use crate::*;
-use anyhow::anyhow;
 use ark_ec::{AffineCurve, PairingEngine, ProjectiveCurve};
 use ark_ff::Zero;
 use ark_ff::{Field, One};
@@ -15,20 +14,33 @@ use ark_serialize::*;
 use bincode::Options;
 use ed25519_dalek as ed25519;
 
+pub mod audit;
 pub mod common;
+pub mod complaint;
+pub mod decryption;
+pub mod equivocation;
+pub mod params;
 pub mod pv;
+pub mod recovery;
+pub mod refresh;
+pub mod reshare;
+pub mod schedule;
+pub mod snapshot;
+pub mod timeout;
 
+pub use audit::*;
 pub use common::*;
+pub use complaint::*;
+pub use decryption::*;
+pub use equivocation::*;
+pub use params::*;
 pub use pv::*;
-
-// DKG parameters
-#[derive(Copy, Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
-pub struct Params {
-    pub tau: u64,
-    pub security_threshold: u32,
-    pub shares_num: u32,
-    pub retry_after: u32, // TODO: Remove. Not relevant in our scheme.
-}
+pub use recovery::*;
+pub use refresh::*;
+pub use reshare::*;
+pub use schedule::*;
+pub use snapshot::*;
+pub use timeout::*;
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum PvssScheduler {
@@ -36,12 +48,46 @@ pub enum PvssScheduler {
     Issue,
 }
 
-#[derive(Debug, Clone)]
+/// The reason a DKG session failed
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FailureReason {
+    /// Not enough PVSS transcripts were dealt before the dealing deadline
+    DealingTimeout,
+    /// The transcripts were not aggregated before the aggregation deadline
+    AggregationTimeout,
+}
+
+impl FailureReason {
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::DealingTimeout => 0,
+            Self::AggregationTimeout => 1,
+        }
+    }
+
+    fn from_u8(reason: u8) -> Option<Self> {
+        match reason {
+            0 => Some(Self::DealingTimeout),
+            1 => Some(Self::AggregationTimeout),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(bound = "")]
 pub enum DkgState<E: PairingEngine> {
-    Sharing { accumulated_shares: u32, block: u32 },
+    Sharing {
+        accumulated_shares: u32,
+    },
     Dealt,
-    Success { final_key: E::G1Affine },
-    Invalid,
+    Success {
+        #[serde(with = "ferveo_common::ark_serde")]
+        final_key: E::G1Affine,
+    },
+    Invalid {
+        reason: FailureReason,
+    },
 }
 
 impl<E: PairingEngine> CanonicalSerialize for DkgState<E> {
@@ -51,40 +97,35 @@ impl<E: PairingEngine> CanonicalSerialize for DkgState<E> {
         mut writer: W,
     ) -> Result<(), SerializationError> {
         match self {
-            Self::Sharing {
-                accumulated_shares,
-                block,
-            } => {
+            Self::Sharing { accumulated_shares } => {
                 CanonicalSerialize::serialize(&0u8, &mut writer)?;
-                CanonicalSerialize::serialize(
-                    &(*accumulated_shares, *block),
-                    &mut writer,
-                )
+                CanonicalSerialize::serialize(accumulated_shares, &mut writer)
             }
             Self::Dealt => CanonicalSerialize::serialize(&1u8, &mut writer),
             Self::Success { final_key } => {
                 CanonicalSerialize::serialize(&2u8, &mut writer)?;
                 final_key.serialize(&mut writer)
             }
-            Self::Invalid => CanonicalSerialize::serialize(&3u8, &mut writer),
+            Self::Invalid { reason } => {
+                CanonicalSerialize::serialize(&3u8, &mut writer)?;
+                CanonicalSerialize::serialize(&reason.to_u8(), &mut writer)
+            }
         }
     }
 
     #[inline]
     fn serialized_size(&self) -> usize {
         match self {
-            Self::Sharing {
-                accumulated_shares,
-                block,
-            } => {
-                0u8.serialized_size()
-                    + (*accumulated_shares, *block).serialized_size()
+            Self::Sharing { accumulated_shares } => {
+                0u8.serialized_size() + accumulated_shares.serialized_size()
             }
             Self::Dealt => 1u8.serialized_size(),
             Self::Success { final_key } => {
                 2u8.serialized_size() + final_key.serialized_size()
             }
-            Self::Invalid => 3u8.serialized_size(),
+            Self::Invalid { reason } => {
+                3u8.serialized_size() + reason.to_u8().serialized_size()
+            }
         }
     }
 }
@@ -95,14 +136,9 @@ impl<E: PairingEngine> CanonicalDeserialize for DkgState<E> {
         let variant = <u8 as CanonicalDeserialize>::deserialize(&mut reader)?;
         match variant {
             0 => {
-                let (accumulated_shares, block) =
-                    <(u32, u32) as CanonicalDeserialize>::deserialize(
-                        &mut reader,
-                    )?;
-                Ok(Self::Sharing {
-                    accumulated_shares,
-                    block,
-                })
+                let accumulated_shares =
+                    <u32 as CanonicalDeserialize>::deserialize(&mut reader)?;
+                Ok(Self::Sharing { accumulated_shares })
             }
             1 => Ok(Self::Dealt),
             2 => Ok(Self::Success {
@@ -110,7 +146,14 @@ impl<E: PairingEngine> CanonicalDeserialize for DkgState<E> {
                     &mut reader,
                 )?,
             }),
-            3 => Ok(Self::Invalid),
+            3 => {
+                let reason =
+                    <u8 as CanonicalDeserialize>::deserialize(&mut reader)?;
+                Ok(Self::Invalid {
+                    reason: FailureReason::from_u8(reason)
+                        .ok_or(SerializationError::InvalidData)?,
+                })
+            }
             _ => Err(SerializationError::InvalidData),
         }
     }
@@ -0,0 +1,436 @@
+use crate::*;
+use ark_serialize::*;
+use ferveo_common::ExternalValidator;
+use serde::{Deserialize, Serialize};
+
+/// The public key shares A_j = g^{f(\omega_j)} of the first `shares_num`
+/// shares of the polynomial f committed to by `coeffs`, computed with an
+/// FFT over `domain`
+pub fn commitment_key_shares<E: PairingEngine>(
+    domain: &ark_poly::Radix2EvaluationDomain<E::Fr>,
+    coeffs: &[E::G1Affine],
+    shares_num: u32,
+) -> Vec<E::G1Affine> {
+    let mut commitment = batch_to_projective(coeffs);
+    domain.fft_in_place(&mut commitment);
+    commitment.truncate(shares_num as usize);
+    E::G1Projective::batch_normalization_into_affine(&commitment)
+}
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// The public key shares A_j = g^{f(\omega_j)} of the aggregated
+    /// transcript, indexed by share. The shares of a validator are those
+    /// in its range `share_start..share_end`
+    pub fn public_key_shares(&self) -> Result<Vec<E::G1Affine>> {
+        match self.state {
+            DkgState::Success { .. } => Ok(commitment_key_shares::<E>(
+                &self.domain,
+                &aggregate(self).coeffs,
+                self.params.shares_num,
+            )),
+            _ => Err(DkgError::WrongState(DkgOperation::PublicKeyShares)),
+        }
+    }
+
+    /// Export the audit transcript of this DKG, which a third party can
+    /// verify with [`AuditTranscript::verify`] without following the DKG
+    pub fn audit_transcript(&self) -> Result<AuditTranscript<E>> {
+        match self.state {
+            DkgState::Success { .. } => Ok(AuditTranscript {
+                params: self.params,
+                validators: self
+                    .validators
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| ExternalValidator {
+                        public_key: self.session_key(i),
+                        ..v.validator.clone()
+                    })
+                    .collect(),
+                dealers: DealerBitmap::new(self.vss.keys().copied()),
+                dealings: self
+                    .vss
+                    .values()
+                    .map(TranscriptCommitment::of)
+                    .collect(),
+                refresh: self.refresh.as_ref().map(TranscriptCommitment::of),
+                aggregate: aggregate(self),
+                final_key: self.final_key(),
+            }),
+            _ => Err(DkgError::WrongState(DkgOperation::ExportAudit)),
+        }
+    }
+}
+
+/// The commitment of a PVSS transcript with its proof of knowledge,
+/// without the encrypted shares
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+#[serde(bound = "")]
+pub struct TranscriptCommitment<E: PairingEngine> {
+    /// The commitment to the coefficients of the dealt polynomial
+    #[serde(with = "ferveo_common::ark_serde::seq")]
+    pub coeffs: Vec<E::G1Affine>,
+    /// The proof of knowledge of the dealt secret
+    #[serde(with = "ferveo_common::ark_serde")]
+    pub sigma: E::G2Affine,
+}
+
+impl<E: PairingEngine> TranscriptCommitment<E> {
+    fn of(pvss: &Pvss<E>) -> Self {
+        Self {
+            coeffs: pvss.coeffs.clone(),
+            sigma: pvss.sigma,
+        }
+    }
+}
+
+/// A self-contained record of a finished DKG: its parameters, validator
+/// set, the commitments of the dealers of the aggregated PVSS transcript
+/// and the final key
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+#[serde(bound = "")]
+pub struct AuditTranscript<E: PairingEngine> {
+    pub params: Params,
+    /// The validator set, with the session keys that the shares of
+    /// `aggregate` are encrypted to
+    pub validators: Vec<ExternalValidator<E>>,
+    /// The dealers whose transcripts are aggregated
+    pub dealers: DealerBitmap,
+    /// The commitment of the transcript of each dealer, in the order of
+    /// `dealers`
+    pub dealings: Vec<TranscriptCommitment<E>>,
+    /// The sum of the refresh transcripts applied after the DKG
+    pub refresh: Option<TranscriptCommitment<E>>,
+    /// The aggregated PVSS transcript, with any refresh applied
+    pub aggregate: AggregatedPvss<E>,
+    #[serde(with = "ferveo_common::ark_serde")]
+    pub final_key: E::G1Affine,
+}
+
+impl<E: PairingEngine> AuditTranscript<E> {
+    /// Verify that the aggregated transcript deals the final key to the
+    /// validators with the share partition given by the parameters, and
+    /// that it is the sum of the transcripts of its dealers, which prove
+    /// knowledge of their secrets and meet the security threshold, and of
+    /// the refresh transcripts
+    /// `rng` is a cryptographic random number generator
+    pub fn verify<R: Rng>(&self, rng: &mut R) -> Result<()> {
+        let dkg = PubliclyVerifiableDkg::<E>::init_observer(
+            self.validators.clone(),
+            self.params,
+        )?;
+        let minimum_shares = self.params.degree();
+        if self.aggregate.coeffs.len() != minimum_shares as usize + 1 {
+            return Err(DkgError::MalformedAggregation);
+        }
+        if self.aggregate.coeffs[0] != self.final_key {
+            return Err(DkgError::FinalKeyMismatch);
+        }
+        if self.dealers.dealers().count() != self.dealings.len() {
+            return Err(DkgError::AuditDealingCount {
+                dealers: self.dealers.dealers().count(),
+                dealings: self.dealings.len(),
+            });
+        }
+        let mut dealt_shares = 0u32;
+        let mut coeffs =
+            vec![E::G1Projective::zero(); minimum_shares as usize + 1];
+        let mut sigma = E::G2Projective::zero();
+        for (dealer, dealing) in self.dealers.dealers().zip(&self.dealings) {
+            let validator =
+                dkg.validators.get(dealer as usize).ok_or_else(|| {
+                    DkgError::UnknownValidator(dealer.to_string())
+                })?;
+            if dealing.coeffs.len() != coeffs.len()
+                || !verify_pok(
+                    &validator.validator,
+                    self.params.tau,
+                    &dealing.coeffs,
+                    dealing.sigma,
+                )
+            {
+                return Err(DkgError::InvalidTranscript(dealer));
+            }
+            dealt_shares += validator.weight;
+            for (a, b) in coeffs.iter_mut().zip(&dealing.coeffs) {
+                *a += b.into_projective();
+            }
+            sigma += dealing.sigma.into_projective();
+        }
+        if dealt_shares < minimum_shares {
+            return Err(DkgError::InsufficientShares {
+                received: dealt_shares,
+                required: minimum_shares,
+            });
+        }
+        if let Some(refresh) = &self.refresh {
+            if refresh.coeffs.len() != coeffs.len()
+                || !refresh.coeffs[0].is_zero()
+            {
+                return Err(DkgError::InvalidAuditRefresh);
+            }
+            for (a, b) in coeffs.iter_mut().zip(&refresh.coeffs) {
+                *a += b.into_projective();
+            }
+            sigma += refresh.sigma.into_projective();
+        }
+        if E::G1Projective::batch_normalization_into_affine(&coeffs)
+            != self.aggregate.coeffs
+            || sigma.into_affine() != self.aggregate.sigma
+        {
+            return Err(DkgError::AggregationMismatch);
+        }
+        if !batch_verify_full(&dkg, &[&self.aggregate], rng) {
+            return Err(DkgError::AuditSharesMismatch);
+        }
+        Ok(())
+    }
+
+    /// The public key shares A_j = g^{f(\omega_j)} of the aggregated
+    /// transcript, indexed by share
+    pub fn public_key_shares(&self) -> Result<Vec<E::G1Affine>> {
+        let domain = ark_poly::Radix2EvaluationDomain::<E::Fr>::new(
+            self.params.shares_num as usize,
+        )
+        .ok_or(DkgError::InvalidDomain(self.params.shares_num))?;
+        Ok(commitment_key_shares::<E>(
+            &domain,
+            &self.aggregate.coeffs,
+            self.params.shares_num,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test_audit {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+
+    type E = EllipticCurve;
+    type G2 = <E as PairingEngine>::G2Affine;
+
+    /// Test that the public key shares match the private key shares of
+    /// every validator, e(A_j, h) = e(g, Z_j), with weighted validators
+    #[test]
+    fn test_public_key_shares() {
+        let dkgs = setup_finished_dkgs(2, 6, &[1, 2, 3]);
+        let keypairs = gen_n_keypairs(3);
+        let public_key_shares =
+            dkgs[0].public_key_shares().expect("Test failed");
+        assert_eq!(public_key_shares.len(), 6);
+        let g = dkgs[0].pvss_params.g.into_affine();
+        let h = dkgs[0].pvss_params.h.into_affine();
+        for (dkg, keypair) in dkgs.iter().zip(keypairs.iter()) {
+            assert_eq!(
+                dkg.public_key_shares().expect("Test failed"),
+                public_key_shares
+            );
+            let validator = &dkg.validators[dkg.me];
+            let dk_inv = keypair.decryption_key.inverse().unwrap();
+            for (share_index, y) in (validator.share_start..validator.share_end)
+                .zip(aggregate_for_decryption(dkg)[dkg.me].iter())
+            {
+                let z = y.mul(dk_inv).into_affine();
+                assert_eq!(
+                    E::pairing(public_key_shares[share_index], h),
+                    E::pairing(g, z)
+                );
+            }
+        }
+        // the same as evaluating the commitment at every point
+        let coeffs = aggregate(&dkgs[0]).coeffs;
+        for (j, a_j) in public_key_shares.iter().enumerate() {
+            let x = dkgs[0].domain.element(j);
+            let powers = (0..coeffs.len())
+                .map(|k| x.pow([k as u64]).into_repr())
+                .collect::<Vec<_>>();
+            let expected = ark_ec::msm::VariableBaseMSM::multi_scalar_mul(
+                &coeffs, &powers,
+            );
+            assert_eq!(*a_j, expected.into_affine());
+        }
+
+        // the key is not known before the DKG succeeds
+        assert!(matches!(
+            setup_dkg(0).public_key_shares(),
+            Err(DkgError::WrongState(_))
+        ));
+        assert!(matches!(
+            setup_dealt_dkg().public_key_shares(),
+            Err(DkgError::WrongState(_))
+        ));
+    }
+
+    /// Test that the audit transcript of a finished DKG verifies after
+    /// a round trip through its serializations
+    #[test]
+    fn test_audit_transcript() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 6, &[1, 2, 3]);
+        let audit = dkgs[0].audit_transcript().expect("Test failed");
+        audit.verify(rng).expect("Test failed");
+        assert_eq!(audit.final_key, dkgs[0].final_key());
+        assert_eq!(
+            audit.public_key_shares().expect("Test failed"),
+            dkgs[0].public_key_shares().expect("Test failed")
+        );
+
+        let bytes = bincode::serialize(&audit).expect("Test failed");
+        let restored: AuditTranscript<E> =
+            bincode::deserialize(&bytes).expect("Test failed");
+        restored.verify(rng).expect("Test failed");
+        let mut bytes = vec![];
+        CanonicalSerialize::serialize(&audit, &mut bytes).expect("Test failed");
+        let restored =
+            <AuditTranscript<E> as CanonicalDeserialize>::deserialize(
+                &bytes[..],
+            )
+            .expect("Test failed");
+        restored.verify(rng).expect("Test failed");
+
+        assert!(matches!(
+            setup_dealt_dkg().audit_transcript(),
+            Err(DkgError::WrongState(_))
+        ));
+    }
+
+    /// Test that tampered audit transcripts are rejected
+    #[test]
+    fn test_audit_transcript_tampered() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 6, &[1, 2, 3]);
+        let audit = dkgs[0].audit_transcript().expect("Test failed");
+
+        let mut tampered = audit.clone();
+        tampered.final_key = dkgs[0].pvss_params.g.into_affine();
+        assert!(matches!(
+            tampered.verify(rng),
+            Err(DkgError::FinalKeyMismatch)
+        ));
+
+        let mut tampered = audit.clone();
+        tampered.aggregate.shares[1][0] = G2::zero();
+        assert!(matches!(
+            tampered.verify(rng),
+            Err(DkgError::AuditSharesMismatch)
+        ));
+
+        // the share partition follows the voting power
+        let mut tampered = audit.clone();
+        tampered.validators[0].power = 3;
+        assert!(tampered.verify(rng).is_err());
+
+        let mut tampered = audit.clone();
+        tampered.dealers = DealerBitmap::new([0]);
+        tampered.dealings.truncate(1);
+        assert!(matches!(
+            tampered.verify(rng),
+            Err(DkgError::InsufficientShares { .. })
+        ));
+
+        let mut tampered = audit.clone();
+        tampered.aggregate.sigma = G2::zero();
+        assert!(matches!(
+            tampered.verify(rng),
+            Err(DkgError::AggregationMismatch)
+        ));
+
+        let mut tampered = audit.clone();
+        tampered.dealings.pop();
+        assert!(matches!(
+            tampered.verify(rng),
+            Err(DkgError::AuditDealingCount { .. })
+        ));
+
+        let mut tampered = audit;
+        tampered.aggregate.coeffs.pop();
+        assert!(matches!(
+            tampered.verify(rng),
+            Err(DkgError::MalformedAggregation)
+        ));
+    }
+
+    /// Test that the dealers named by an audit transcript must have dealt
+    /// the aggregated transcript: each commitment is bound to its dealer
+    /// by the proof of knowledge, and the commitments add up to the
+    /// aggregate
+    #[test]
+    fn test_audit_transcript_forged_dealers() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 6, &[1, 2, 3]);
+        let audit = dkgs[0].audit_transcript().expect("Test failed");
+        assert_eq!(audit.dealers.dealers().collect::<Vec<_>>(), vec![0, 1, 2]);
+
+        // dealer 2 claims the transcript of dealer 0
+        let mut forged = audit.clone();
+        forged.dealings[2] = forged.dealings[0].clone();
+        assert!(matches!(
+            forged.verify(rng),
+            Err(DkgError::InvalidTranscript(2))
+        ));
+
+        // the named dealers must account for the whole aggregate
+        let mut forged = audit.clone();
+        forged.dealers = DealerBitmap::new([1, 2]);
+        forged.dealings.remove(0);
+        assert!(matches!(
+            forged.verify(rng),
+            Err(DkgError::AggregationMismatch)
+        ));
+
+        // a commitment with a forged proof of knowledge
+        let mut forged = audit;
+        forged.dealings[1].sigma = forged.dealings[0].sigma;
+        assert!(matches!(
+            forged.verify(rng),
+            Err(DkgError::InvalidTranscript(1))
+        ));
+    }
+
+    /// Test that the audit transcript of a refreshed DKG accounts for the
+    /// refresh in the aggregated transcript
+    #[test]
+    fn test_audit_transcript_refreshed() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_finished_dkgs(2, 6, &[1, 2, 3]).remove(0);
+        let refresh = dkg.refresh(rng).expect("Test failed");
+        let sender = dkg.validators[dkg.me].validator.clone();
+        dkg.verify_message(&sender, &refresh).expect("Test failed");
+        dkg.apply_message(sender, refresh).expect("Test failed");
+
+        let audit = dkg.audit_transcript().expect("Test failed");
+        assert!(audit.refresh.is_some());
+        audit.verify(rng).expect("Test failed");
+
+        let mut tampered = audit.clone();
+        tampered.refresh = None;
+        assert!(matches!(
+            tampered.verify(rng),
+            Err(DkgError::AggregationMismatch)
+        ));
+
+        let mut tampered = audit;
+        if let Some(refresh) = tampered.refresh.as_mut() {
+            refresh.coeffs[0] = dkg.pvss_params.g.into_affine();
+        }
+        assert!(matches!(
+            tampered.verify(rng),
+            Err(DkgError::InvalidAuditRefresh)
+        ));
+    }
+}
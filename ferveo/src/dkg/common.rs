@@ -1,16 +1,90 @@
 use crate::*;
-use ferveo_common::ExternalValidator;
+use ferveo_common::{ExternalValidator, ProofOfPossession};
 use itertools::izip;
 
+/// Check that every validator of `validators` knows the decryption key of
+/// its session public key, given the proofs of possession `proofs` in the
+/// same order as `validators`
+///
+/// This rules out session keys that are the identity or copied from
+/// another validator, which would not be bound to a secret of their owner
+pub fn verify_proofs_of_possession<E: PairingEngine>(
+    validators: &[ExternalValidator<E>],
+    proofs: &[ProofOfPossession<E>],
+) -> Result<()> {
+    if validators.len() != proofs.len() {
+        return Err(DkgError::Params(ParamsError::ProofCountMismatch {
+            validators: validators.len(),
+            proofs: proofs.len(),
+        }));
+    }
+    match validators
+        .iter()
+        .zip(proofs.iter())
+        .find(|(validator, proof)| {
+            !validator
+                .public_key
+                .verify_possession(&validator.address, proof)
+        }) {
+        Some((validator, _)) => Err(DkgError::InvalidProofOfPossession(
+            validator.address.clone(),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Partition the `params.shares_num` shares of the DKG between the
+/// validators proportionally to their voting power.
+///
+/// Each validator is allocated a contiguous range of share indices, in the
+/// same order as `validators`. Shares left over after rounding down are
+/// handed out to the validators with the largest remainders, ties broken
+/// by position in the validator set.
 pub fn make_validators<E: PairingEngine>(
     validators: Vec<ExternalValidator<E>>,
-) -> Vec<ferveo_common::Validator<E>> {
-    validators
+    params: &Params,
+) -> Result<Vec<ferveo_common::Validator<E>>> {
+    let total_power = validators
+        .iter()
+        .try_fold(0u128, |acc, v| acc.checked_add(v.power as u128))
+        .ok_or(DkgError::VotingPowerOverflow)?;
+    if total_power == 0 {
+        return Err(DkgError::ZeroVotingPower);
+    }
+    let shares_num = params.shares_num as u128;
+
+    // Compute the weight of each validator rounded down, along with the remainder
+    let mut weights = validators
         .iter()
-        .enumerate()
-        .map(|(index, validator)| ferveo_common::Validator::<E> {
-            validator: validator.clone(),
-            share_index: index,
+        .map(|v| {
+            let scaled = v.power as u128 * shares_num;
+            ((scaled / total_power) as u32, scaled % total_power)
+        })
+        .collect::<Vec<_>>();
+
+    // Hand out the excess shares to the validators with the largest remainders
+    let allocated = weights.iter().map(|(w, _)| *w).sum::<u32>();
+    let mut by_remainder = (0..weights.len()).collect::<Vec<_>>();
+    by_remainder.sort_by(|a, b| weights[*b].1.cmp(&weights[*a].1));
+    for ix in by_remainder
+        .into_iter()
+        .take((params.shares_num - allocated) as usize)
+    {
+        weights[ix].0 += 1;
+    }
+
+    let mut share_start = 0usize;
+    Ok(izip!(validators.into_iter(), weights.into_iter())
+        .map(|(validator, (weight, _))| {
+            let share_end = share_start + weight as usize;
+            let validator = ferveo_common::Validator::<E> {
+                validator,
+                weight,
+                share_start,
+                share_end,
+            };
+            share_start = share_end;
+            validator
         })
-        .collect()
+        .collect())
 }
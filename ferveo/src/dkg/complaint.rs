@@ -0,0 +1,462 @@
+use crate::*;
+use ark_ec::msm::VariableBaseMSM;
+use ark_serialize::*;
+use ferveo_common::ExternalValidator;
+
+/// Domain separation tag for the Fiat-Shamir challenge of [`DleqProof`]
+const DLEQ_DOMAIN: &[u8] = b"FERVEO-COMPLAINT-DLEQ";
+
+/// A Chaum-Pedersen proof that two pairs of G2 elements share the same
+/// discrete log, i.e. that `h = ek^x` and `z = y^x` for some `x`.
+///
+/// Used to prove that a share was decrypted with the secret `x = dk^{-1}`
+/// matching the session public key `ek = h^{dk}`
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+#[serde(bound = "")]
+pub struct DleqProof<E: PairingEngine> {
+    #[serde(with = "ferveo_common::ark_serde")]
+    challenge: E::Fr,
+    #[serde(with = "ferveo_common::ark_serde")]
+    response: E::Fr,
+}
+
+impl<E: PairingEngine> DleqProof<E> {
+    /// Prove that `log_{ek}(h) = log_{y}(z) = x`
+    fn new<R: Rng>(
+        x: E::Fr,
+        ek: E::G2Affine,
+        y: E::G2Affine,
+        z: E::G2Affine,
+        rng: &mut R,
+    ) -> Self {
+        use ark_std::UniformRand;
+        let r = E::Fr::rand(rng);
+        let t_1 = ek.mul(r).into_affine();
+        let t_2 = y.mul(r).into_affine();
+        let challenge = Self::challenge(&[ek, y, z, t_1, t_2]);
+        Self {
+            challenge,
+            response: r - challenge * x,
+        }
+    }
+
+    /// Check the proof that `log_{ek}(h) = log_{y}(z)`
+    fn verify(&self, ek: E::G2Affine, y: E::G2Affine, z: E::G2Affine) -> bool {
+        let h = E::G2Affine::prime_subgroup_generator();
+        // t_1 = ek^s * h^c, t_2 = y^s * z^c
+        let t_1 = (ek.mul(self.response) + h.mul(self.challenge)).into_affine();
+        let t_2 = (y.mul(self.response) + z.mul(self.challenge)).into_affine();
+        self.challenge == Self::challenge(&[ek, y, z, t_1, t_2])
+    }
+
+    fn challenge(points: &[E::G2Affine]) -> E::Fr {
+        let mut bytes = vec![];
+        points
+            .serialize(&mut bytes)
+            .expect("serializing group elements to a vector cannot fail");
+        hash_to_scalar(DLEQ_DOMAIN, &bytes)
+    }
+}
+
+/// A publicly verifiable complaint against a dealer whose PVSS transcript
+/// contains a share for the complainer that does not match the dealer's
+/// Feldman commitment.
+///
+/// The complainer reveals the decryption of the disputed share together
+/// with a [`DleqProof`] that the decryption was done with its session key
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+#[serde(bound = "")]
+pub struct Complaint<E: PairingEngine> {
+    /// The index of the dealer accused of dealing a bad share
+    pub dealer: u32,
+    /// The index of the disputed share in the domain
+    pub share_index: usize,
+    /// The decrypted share, h^{\phi(\omega_j)}
+    #[serde(with = "ferveo_common::ark_serde")]
+    pub decrypted_share: E::G2Affine,
+    /// Proof that `decrypted_share` was correctly decrypted
+    pub proof: DleqProof<E>,
+}
+
+impl<E: PairingEngine> Complaint<E> {
+    /// Create a complaint against share `share_index` dealt by `dealer` in
+    /// `pvss` to the owner of the DKG context `dkg`
+    pub fn new<R: Rng>(
+        dkg: &PubliclyVerifiableDkg<E>,
+        dealer: u32,
+        pvss: &Pvss<E>,
+        share_index: usize,
+        rng: &mut R,
+    ) -> Result<Self> {
+        dkg.check_participant(DkgOperation::Complain)?;
+        let validator = &dkg.validators[dkg.me];
+        if !(validator.share_start..validator.share_end).contains(&share_index)
+        {
+            return Err(DkgError::ComplaintShareNotHeld {
+                complainer: dkg.me as u32,
+                share_index,
+            });
+        }
+        let encrypted_share =
+            match encrypted_share(pvss, validator, dkg.me, share_index) {
+                Some(share) => share,
+                // The transcript is malformed and is missing the share,
+                // so there is nothing to decrypt
+                None => {
+                    return Ok(Self {
+                        dealer,
+                        share_index,
+                        decrypted_share: E::G2Affine::zero(),
+                        proof: DleqProof {
+                            challenge: E::Fr::zero(),
+                            response: E::Fr::zero(),
+                        },
+                    })
+                }
+            };
+        let x = dkg
+            .session_keypair
+            .decryption_key
+            .inverse()
+            .ok_or(DkgError::ZeroSessionKey)?;
+        let decrypted_share = encrypted_share.mul(x).into_affine();
+        let proof = DleqProof::new(
+            x,
+            validator.validator.public_key.encryption_key,
+            encrypted_share,
+            decrypted_share,
+            rng,
+        );
+        Ok(Self {
+            dealer,
+            share_index,
+            decrypted_share,
+            proof,
+        })
+    }
+
+    /// Verify a complaint issued by the validator at index `complainer`
+    /// against the transcript `pvss` of the accused dealer.
+    ///
+    /// The complaint is upheld if the disputed share is missing from the
+    /// transcript, or if it was correctly decrypted and does not match the
+    /// dealer's commitment
+    pub fn verify(
+        &self,
+        dkg: &PubliclyVerifiableDkg<E>,
+        complainer: usize,
+        pvss: &Pvss<E>,
+    ) -> Result<()> {
+        let validator = &dkg.validators[complainer];
+        if !(validator.share_start..validator.share_end)
+            .contains(&self.share_index)
+        {
+            return Err(DkgError::ComplaintShareNotHeld {
+                complainer: complainer as u32,
+                share_index: self.share_index,
+            });
+        }
+        let encrypted_share = match encrypted_share(
+            pvss,
+            validator,
+            complainer,
+            self.share_index,
+        ) {
+            Some(share) => share,
+            // The transcript is malformed and is missing the share
+            None => return Ok(()),
+        };
+        if !self.proof.verify(
+            validator.validator.public_key.encryption_key,
+            encrypted_share,
+            self.decrypted_share,
+        ) {
+            return Err(DkgError::InvalidDecryptionProof(complainer as u32));
+        }
+        if share_matches_commitment(
+            dkg,
+            pvss,
+            self.share_index,
+            self.decrypted_share,
+        ) {
+            Err(DkgError::UnfoundedComplaint {
+                dealer: self.dealer,
+                share_index: self.share_index,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Look up the encryption of share `share_index` dealt to `validator`
+fn encrypted_share<E: PairingEngine>(
+    pvss: &Pvss<E>,
+    validator: &ferveo_common::Validator<E>,
+    validator_index: usize,
+    share_index: usize,
+) -> Option<E::G2Affine> {
+    let offset = share_index.checked_sub(validator.share_start)?;
+    pvss.shares
+        .get(validator_index)?
+        .get(offset)
+        .filter(|_| share_index < validator.share_end)
+        .copied()
+}
+
+/// Check the decrypted share h^{\phi(\omega_j)} against the Feldman
+/// commitment of `pvss`, evaluated in the exponent at \omega_j
+fn share_matches_commitment<E: PairingEngine>(
+    dkg: &PubliclyVerifiableDkg<E>,
+    pvss: &Pvss<E>,
+    share_index: usize,
+    decrypted_share: E::G2Affine,
+) -> bool {
+    let omega = dkg.domain.element(share_index);
+    let mut power = E::Fr::one();
+    let powers = pvss
+        .coeffs
+        .iter()
+        .map(|_| {
+            let repr = power.into_repr();
+            power *= omega;
+            repr
+        })
+        .collect::<Vec<_>>();
+    // A_j = g^{\phi(\omega_j)}
+    let a = VariableBaseMSM::multi_scalar_mul(&pvss.coeffs, &powers);
+    // e(A_j, h) = e(g, h^{\phi(\omega_j)})
+    E::pairing(a, E::G2Affine::prime_subgroup_generator())
+        == E::pairing(dkg.pvss_params.g, decrypted_share)
+}
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// Decrypt the shares dealt to us by `dealer` in `pvss` and check them
+    /// against the dealer's commitment.
+    ///
+    /// Returns a [`Message::Complaint`] to post on-chain if any of our
+    /// shares is invalid, or `None` if all of them are correct
+    pub fn check_dealt_shares<R: Rng>(
+        &self,
+        dealer: &ExternalValidator<E>,
+        pvss: &Pvss<E>,
+        rng: &mut R,
+    ) -> Result<Option<Message<E>>> {
+        let dealer = self
+            .validators
+            .iter()
+            .position(|probe| dealer == &probe.validator)
+            .ok_or_else(|| {
+                DkgError::UnknownValidator(dealer.address.clone())
+            })?;
+        self.check_participant(DkgOperation::CheckDealtShares)?;
+        let x = self
+            .session_keypair
+            .decryption_key
+            .inverse()
+            .ok_or(DkgError::ZeroSessionKey)?;
+        let validator = &self.validators[self.me];
+        for share_index in validator.share_start..validator.share_end {
+            let valid = encrypted_share(pvss, validator, self.me, share_index)
+                .map(|share| {
+                    share_matches_commitment(
+                        self,
+                        pvss,
+                        share_index,
+                        share.mul(x).into_affine(),
+                    )
+                })
+                .unwrap_or(false);
+            if !valid {
+                let complaint = Complaint::new(
+                    self,
+                    dealer as u32,
+                    pvss,
+                    share_index,
+                    rng,
+                )?;
+                return Ok(Some(Message::Complaint(complaint)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Test complaints against dealers of bad shares
+#[cfg(test)]
+mod test_complaints {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+
+    type G2 = <EllipticCurve as PairingEngine>::G2Affine;
+    type Fr = <EllipticCurve as PairingEngine>::Fr;
+
+    /// Have validator 0 deal a transcript in which
+    /// the share of validator 1 is corrupted
+    fn deal_bad_share() -> Message<EllipticCurve> {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dkg(0);
+        match dkg.share(rng).expect("Test failed") {
+            Message::Deal(mut pvss) => {
+                pvss.shares[1][0] = G2::prime_subgroup_generator()
+                    .mul(Fr::rand(rng))
+                    .into_affine();
+                Message::Deal(pvss)
+            }
+            _ => panic!("Test failed"),
+        }
+    }
+
+    /// Test that a validator receiving correct shares does not complain
+    #[test]
+    fn test_no_complaint_for_valid_shares() {
+        let rng = &mut ark_std::test_rng();
+        let mut dealer = setup_dkg(0);
+        let pvss = match dealer.share(rng).expect("Test failed") {
+            Message::Deal(pvss) => pvss,
+            _ => panic!("Test failed"),
+        };
+        for i in 0..4 {
+            let dkg = setup_dkg(i);
+            let complaint = dkg
+                .check_dealt_shares(&dkg.validators[0].validator, &pvss, rng)
+                .expect("Test failed");
+            assert!(complaint.is_none());
+        }
+    }
+
+    /// Test that a validator receiving a bad share complains, that the
+    /// complaint verifies and that the dealer's transcript is dropped
+    #[test]
+    fn test_complaint_drops_bad_dealer() {
+        let rng = &mut ark_std::test_rng();
+        let deal = deal_bad_share();
+        let pvss = match &deal {
+            Message::Deal(pvss) => pvss.clone(),
+            _ => panic!("Test failed"),
+        };
+        let dealer = setup_dkg(0).validators[0].validator.clone();
+
+        // only the validator holding the bad share complains
+        let complaint = setup_dkg(1)
+            .check_dealt_shares(&dealer, &pvss, rng)
+            .expect("Test failed")
+            .expect("Test failed");
+        assert!(setup_dkg(2)
+            .check_dealt_shares(&dealer, &pvss, rng)
+            .expect("Test failed")
+            .is_none());
+
+        let mut dkg = setup_dkg(2);
+        assert!(dkg.apply_message(dealer.clone(), deal.clone()).is_ok());
+        let complainer = dkg.validators[1].validator.clone();
+        assert!(dkg.verify_message(&complainer, &complaint).is_ok());
+        // the complaint must come from the holder of the share
+        assert!(dkg
+            .verify_message(&dkg.validators[2].validator, &complaint)
+            .is_err());
+        assert!(dkg.apply_message(complainer, complaint).is_ok());
+        assert!(dkg.vss.is_empty());
+        assert!(dkg.faulty_dealers.contains(&0));
+        assert!(matches!(
+            dkg.state,
+            DkgState::Sharing {
+                accumulated_shares: 0,
+                ..
+            }
+        ));
+
+        // the faulty dealer may not deal again
+        assert!(dkg.verify_message(&dealer, &deal).is_err());
+    }
+
+    /// Test that a complaint against a correctly dealt share is rejected
+    #[test]
+    fn test_false_complaint_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dkg(1);
+        let deal = dkg.share(rng).expect("Test failed");
+        let pvss = match &deal {
+            Message::Deal(pvss) => pvss.clone(),
+            _ => panic!("Test failed"),
+        };
+        let dealer = dkg.validators[0].validator.clone();
+        assert!(dkg.apply_message(dealer, deal).is_ok());
+
+        let complaint = Complaint::new(&dkg, 0, &pvss, 1, rng)
+            .map(Message::Complaint)
+            .expect("Test failed");
+        let complainer = dkg.validators[1].validator.clone();
+        assert!(dkg.verify_message(&complainer, &complaint).is_err());
+    }
+
+    /// Test that a complaint revealing an incorrect decryption is rejected
+    #[test]
+    fn test_complaint_with_bad_decryption_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let deal = deal_bad_share();
+        let mut dkg = setup_dkg(1);
+        let dealer = dkg.validators[0].validator.clone();
+        let mut complaint = match &deal {
+            Message::Deal(pvss) => dkg
+                .check_dealt_shares(&dealer, pvss, rng)
+                .expect("Test failed")
+                .expect("Test failed"),
+            _ => panic!("Test failed"),
+        };
+        if let Message::Complaint(complaint) = &mut complaint {
+            complaint.decrypted_share = G2::prime_subgroup_generator();
+        }
+        assert!(dkg.apply_message(dealer, deal).is_ok());
+        let complainer = dkg.validators[1].validator.clone();
+        assert!(dkg.verify_message(&complainer, &complaint).is_err());
+    }
+
+    /// Test that dropping a transcript can move the DKG back
+    /// from [`DkgState::Dealt`] to [`DkgState::Sharing`]
+    #[test]
+    fn test_complaint_reverts_dealt_state() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dkg(1);
+        let dealer = dkg.validators[0].validator.clone();
+        let deal = deal_bad_share();
+        let complaint = match &deal {
+            Message::Deal(pvss) => dkg
+                .check_dealt_shares(&dealer, pvss, rng)
+                .expect("Test failed")
+                .expect("Test failed"),
+            _ => panic!("Test failed"),
+        };
+        assert!(dkg.apply_message(dealer, deal).is_ok());
+        let pvss = setup_dkg(2).share(rng).expect("Test failed");
+        let sender = dkg.validators[2].validator.clone();
+        assert!(dkg.apply_message(sender, pvss).is_ok());
+        assert!(matches!(dkg.state, DkgState::Dealt));
+
+        let complainer = dkg.validators[1].validator.clone();
+        assert!(dkg.verify_message(&complainer, &complaint).is_ok());
+        assert!(dkg.apply_message(complainer, complaint).is_ok());
+        assert!(matches!(
+            dkg.state,
+            DkgState::Sharing {
+                accumulated_shares: 1,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,294 @@
+use crate::*;
+use ferveo_common::Keypair;
+use group_threshold_cryptography::{
+    BlindedKeyShare, PrivateDecryptionContextFast,
+    PrivateDecryptionContextSimple, PrivateKeyShare,
+    PublicDecryptionContextFast, PublicDecryptionContextSimple, PublicKeyShare,
+    SetupParams,
+};
+
+/// The private decryption contexts of the shares held by a validator,
+/// with the public decryption contexts of every share
+pub type DecryptionContextsSimple<E> = (
+    Vec<PrivateDecryptionContextSimple<E>>,
+    Vec<PublicDecryptionContextSimple<E>>,
+);
+
+/// The private decryption contexts of the shares held by a validator,
+/// with the public decryption contexts of every share, for the fast
+/// variant of threshold decryption
+pub type DecryptionContextsFast<E> = (
+    Vec<PrivateDecryptionContextFast<E>>,
+    Vec<PublicDecryptionContextFast<E>>,
+);
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// Build the threshold decryption contexts of a finished DKG
+    /// `keypair` is the session keypair of this validator
+    ///
+    /// Returns the private contexts of the shares held by this validator,
+    /// and the public contexts of every share of the DKG
+    ///
+    /// The public key shares A_j = g^{f(\omega_j)} are computed from the
+    /// aggregated commitment. The encrypted shares Y_j = ek_i^{f(\omega_j)}
+    /// are the key shares Z_j = h^{f(\omega_j)} blinded by the session key
+    /// of their holder, so every public context is built from the
+    /// transcripts on-chain, without a trusted dealer
+    pub fn decryption_contexts_simple(
+        &self,
+        keypair: &Keypair<E>,
+    ) -> Result<DecryptionContextsSimple<E>> {
+        self.check_participant(DkgOperation::DecryptionContexts)?;
+        if !matches!(self.state, DkgState::Success { .. }) {
+            return Err(DkgError::WrongState(DkgOperation::DecryptionContexts));
+        }
+        if keypair.public() != self.session_key(self.me) {
+            return Err(DkgError::KeypairMismatch);
+        }
+        let b = keypair.decryption_key;
+        let b_inv = b.inverse().ok_or(DkgError::ZeroSessionKey)?;
+        let public_key_shares = &self.public_key_shares()?;
+        let encrypted_shares = aggregate_for_decryption(self);
+
+        let public_contexts = self
+            .validators
+            .iter()
+            .zip(encrypted_shares.iter())
+            .enumerate()
+            .flat_map(|(i, (validator, shares))| {
+                let blinding_key = self.session_key(i).encryption_key;
+                (validator.share_start..validator.share_end)
+                    .zip(shares)
+                    .map(move |(share_index, y)| {
+                        PublicDecryptionContextSimple {
+                            domain: self.domain.element(share_index),
+                            public_key_share: PublicKeyShare {
+                                public_key_share: public_key_shares
+                                    [share_index],
+                            },
+                            blinded_key_share: BlindedKeyShare {
+                                blinding_key,
+                                blinded_key_share: *y,
+                                blinding_key_prepared: E::G2Prepared::from(
+                                    blinding_key,
+                                ),
+                            },
+                        }
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        let g = self.pvss_params.g.into_affine();
+        let h = self.pvss_params.h.into_affine();
+        let validator = &self.validators[self.me];
+        let private_contexts = (validator.share_start..validator.share_end)
+            .zip(encrypted_shares[self.me].iter())
+            .map(|(index, y)| PrivateDecryptionContextSimple {
+                index,
+                setup_params: SetupParams {
+                    b,
+                    b_inv,
+                    g,
+                    g_inv: E::G1Prepared::from(-g),
+                    h_inv: E::G2Prepared::from(-h),
+                    h,
+                },
+                // Z_j = Y_j^{dk^{-1}}
+                private_key_share: PrivateKeyShare {
+                    private_key_share: y.mul(b_inv).into_affine(),
+                },
+                public_decryption_contexts: public_contexts.clone(),
+            })
+            .collect();
+        Ok((private_contexts, public_contexts))
+    }
+
+    /// Build the threshold decryption contexts of a finished DKG for the
+    /// fast variant of threshold decryption, where the session keypair
+    /// of each validator is the blinding factor `b` of its key shares
+    /// `keypair` is the session keypair of this validator
+    ///
+    /// Returns the private contexts of the shares held by this validator,
+    /// and the public contexts of every share of the DKG
+    ///
+    /// The blinded key shares [b] Z_j of the public contexts are the
+    /// encrypted shares Y_j of the aggregated transcript, so decryption
+    /// shares can be verified and combined from the transcripts on-chain
+    pub fn decryption_contexts_fast(
+        &self,
+        keypair: &Keypair<E>,
+    ) -> Result<DecryptionContextsFast<E>> {
+        let (private_contexts, public_contexts) =
+            self.decryption_contexts_simple(keypair)?;
+        let public_contexts = public_contexts
+            .into_iter()
+            .map(|context| {
+                let mut blinded_key_share = context.blinded_key_share;
+                let domain_inv = context
+                    .domain
+                    .inverse()
+                    .expect("domain points are not zero");
+                // [-b \omega_j^{-1}] Z_j, prepared for `prepare_combine_fast`
+                blinded_key_share.multiply_by_omega_inv(&domain_inv);
+                PublicDecryptionContextFast {
+                    domain: context.domain,
+                    public_key_share: context.public_key_share,
+                    blinded_key_share,
+                    lagrange_n_0: context.domain,
+                }
+            })
+            .collect::<Vec<_>>();
+        let scalar_bits = E::Fr::size_in_bits();
+        let private_contexts = private_contexts
+            .into_iter()
+            .map(|context| PrivateDecryptionContextFast {
+                index: context.index,
+                setup_params: context.setup_params,
+                private_key_share: context.private_key_share,
+                public_decryption_contexts: public_contexts.clone(),
+                scalar_bits,
+            })
+            .collect();
+        Ok((private_contexts, public_contexts))
+    }
+}
+
+#[cfg(test)]
+mod test_decryption {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+    use group_threshold_cryptography as tpke;
+
+    type E = EllipticCurve;
+
+    /// Test that the contexts built by every validator decrypt
+    /// a ciphertext to the final key, with weighted validators
+    #[test]
+    fn test_decryption_contexts_simple() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 6, &[1, 2, 3]);
+        let keypairs = gen_n_keypairs(3);
+        let final_key = dkgs[0].final_key();
+
+        let msg: &[u8] = "abc".as_bytes();
+        let aad: &[u8] = "my-aad".as_bytes();
+        let ciphertext = tpke::encrypt::<_, E>(msg, aad, &final_key, rng);
+
+        let mut decryption_shares = vec![];
+        let mut public_contexts = vec![];
+        for (dkg, keypair) in dkgs.iter().zip(keypairs.iter()) {
+            let (private, public) = dkg
+                .decryption_contexts_simple(keypair)
+                .expect("Test failed");
+            let validator = &dkg.validators[dkg.me];
+            assert_eq!(private.len(), validator.weight as usize);
+            assert_eq!(public.len(), dkg.params.shares_num as usize);
+            decryption_shares
+                .extend(private.iter().map(|c| c.create_share(&ciphertext)));
+            public_contexts = public;
+        }
+
+        // the public contexts match the final key and the key shares
+        assert!(public_contexts.iter().all(|c| c
+            .blinded_key_share
+            .verify_blinding(&c.public_key_share, rng)));
+
+        let shares_x =
+            public_contexts.iter().map(|c| c.domain).collect::<Vec<_>>();
+        let lagrange_coeffs = tpke::prepare_combine_simple::<E>(&shares_x);
+        let shared_secret = tpke::share_combine_simple::<E>(
+            &decryption_shares,
+            &lagrange_coeffs,
+        );
+        let plaintext = tpke::checked_decrypt_with_shared_secret(
+            &ciphertext,
+            aad,
+            &shared_secret,
+        )
+        .expect("Test failed");
+        assert_eq!(plaintext, msg);
+    }
+
+    /// Test that the fast contexts built by every validator produce
+    /// decryption shares that verify in a batch and decrypt a ciphertext
+    /// to the final key, with weighted validators
+    #[test]
+    fn test_decryption_contexts_fast() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 6, &[1, 2, 3]);
+        let keypairs = gen_n_keypairs(3);
+        let final_key = dkgs[0].final_key();
+
+        let msg: &[u8] = "abc".as_bytes();
+        let aad: &[u8] = "my-aad".as_bytes();
+        let ciphertexts = (0..2)
+            .map(|_| tpke::encrypt::<_, E>(msg, aad, &final_key, rng))
+            .collect::<Vec<_>>();
+
+        let mut contexts = vec![];
+        for (dkg, keypair) in dkgs.iter().zip(keypairs.iter()) {
+            let (private, public) =
+                dkg.decryption_contexts_fast(keypair).expect("Test failed");
+            assert_eq!(private.len(), dkg.validators[dkg.me].weight as usize);
+            assert_eq!(public.len(), dkg.params.shares_num as usize);
+            contexts.extend(private);
+        }
+        let shares = ciphertexts
+            .iter()
+            .map(|ciphertext| {
+                contexts
+                    .iter()
+                    .map(|c| c.create_share(ciphertext))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        assert!(contexts[0].batch_verify_decryption_shares(
+            &ciphertexts,
+            &shares,
+            rng
+        ));
+
+        let public_contexts = &contexts[0].public_decryption_contexts;
+        for (ciphertext, shares) in ciphertexts.iter().zip(shares.iter()) {
+            let prepared_key_shares =
+                tpke::prepare_combine_fast(public_contexts, shares);
+            let shared_secret =
+                tpke::share_combine_fast(shares, &prepared_key_shares);
+            let plaintext = tpke::checked_decrypt_with_shared_secret(
+                ciphertext,
+                aad,
+                &shared_secret,
+            )
+            .expect("Test failed");
+            assert_eq!(plaintext, msg);
+        }
+
+        // a decryption share from another session key fails the batch
+        let mut forged = shares.clone();
+        forged[0][1] = contexts[0].create_share(&ciphertexts[0]);
+        forged[0][1].decrypter_index = 1;
+        assert!(!contexts[0].batch_verify_decryption_shares(
+            &ciphertexts,
+            &forged,
+            rng
+        ));
+    }
+
+    /// Test that the contexts can only be built once the DKG
+    /// has succeeded, and with the session keypair of this validator
+    #[test]
+    fn test_decryption_contexts_simple_rejected() {
+        let dkg = setup_dealt_dkg();
+        let keypairs = gen_keypairs();
+        assert!(matches!(
+            dkg.decryption_contexts_simple(&keypairs[0]),
+            Err(DkgError::WrongState(_))
+        ));
+        let dkg = &setup_finished_dkgs(2, 4, &[1, 1, 1, 1])[0];
+        assert!(dkg.decryption_contexts_simple(&keypairs[0]).is_ok());
+        assert!(matches!(
+            dkg.decryption_contexts_simple(&keypairs[1]),
+            Err(DkgError::KeypairMismatch)
+        ));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::*;
+use ark_serialize::*;
+use ferveo_common::ExternalValidator;
+
+/// Evidence that a dealer signed two different PVSS transcripts in the
+/// same DKG session, which any third party can verify with
+/// [`EquivocationEvidence::verify`]
+///
+/// The two messages are ordered by their canonical serialization, so the
+/// same equivocation always has the same evidence
+#[derive(
+    Clone, Debug, PartialEq, Eq, CanonicalSerialize, CanonicalDeserialize,
+)]
+pub struct EquivocationEvidence<S: SignatureScheme = Ed25519> {
+    pub first: SignedMessage<S>,
+    pub second: SignedMessage<S>,
+}
+
+/// The canonical serialization of `t`
+fn canonical_bytes<T: CanonicalSerialize>(t: &T) -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    CanonicalSerialize::serialize(t, &mut bytes)?;
+    Ok(bytes)
+}
+
+impl<S: SignatureScheme> EquivocationEvidence<S> {
+    /// Collect the evidence of two conflicting signed dealings, in
+    /// canonical order
+    pub fn new(a: SignedMessage<S>, b: SignedMessage<S>) -> Result<Self> {
+        let (first, second) = if canonical_bytes(&a)? <= canonical_bytes(&b)? {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        Ok(Self { first, second })
+    }
+
+    /// Verify that this evidence proves that a dealer signed two different
+    /// PVSS transcripts in the session given by `params` and `validators`
+    ///
+    /// `signing_keys` are the message signing keys of the validators, in
+    /// the order of the validator set. Returns the position of the dealer
+    /// that equivocated
+    pub fn verify<E: PairingEngine>(
+        &self,
+        params: &Params,
+        validators: &[ExternalValidator<E>],
+        signing_keys: &[S::PublicKey],
+    ) -> Result<u32> {
+        if signing_keys.len() != validators.len() {
+            return Err(DkgError::Params(
+                ParamsError::SigningKeyCountMismatch {
+                    validators: validators.len(),
+                    signing_keys: signing_keys.len(),
+                },
+            ));
+        }
+        if canonical_bytes(&self.first)? >= canonical_bytes(&self.second)? {
+            return Err(DkgError::EvidenceNotCanonical);
+        }
+        let session = session_hash(params, validators)?;
+        let dealer = self.first.check_sender(
+            params,
+            &session,
+            validators,
+            signing_keys,
+        )?;
+        let second = self.second.check_sender(
+            params,
+            &session,
+            validators,
+            signing_keys,
+        )?;
+        if second != dealer {
+            return Err(DkgError::EvidenceSenderMismatch {
+                first: dealer as u32,
+                second: second as u32,
+            });
+        }
+        let first = match self.first.decode::<E>()? {
+            Message::Deal(pvss) => pvss,
+            msg => return Err(DkgError::EvidenceNotDealing(msg.kind())),
+        };
+        let second = match self.second.decode::<E>()? {
+            Message::Deal(pvss) => pvss,
+            msg => return Err(DkgError::EvidenceNotDealing(msg.kind())),
+        };
+        if canonical_bytes(&first)? == canonical_bytes(&second)? {
+            return Err(DkgError::EvidenceSameTranscript(dealer as u32));
+        }
+        SignedMessage::verify_batch(&[
+            self.first.clone(),
+            self.second.clone(),
+        ])?;
+        Ok(dealer as u32)
+    }
+}
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// Collect the evidence that the signed dealings `a` and `b` equivocate
+    /// in this DKG session, to post on chain
+    ///
+    /// `signing_keys` are the message signing keys of the validators, in
+    /// the order of the validator set
+    pub fn equivocation_evidence<S: SignatureScheme>(
+        &self,
+        a: SignedMessage<S>,
+        b: SignedMessage<S>,
+        signing_keys: &[S::PublicKey],
+    ) -> Result<EquivocationEvidence<S>> {
+        let evidence = EquivocationEvidence::new(a, b)?;
+        evidence.verify(
+            &self.params,
+            &self.external_validators(),
+            signing_keys,
+        )?;
+        Ok(evidence)
+    }
+}
+
+#[cfg(test)]
+mod test_equivocation {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+    use ed25519_dalek as ed25519;
+
+    /// Test that two different dealings signed by the same dealer are
+    /// evidence that any third party can verify
+    #[test]
+    fn test_equivocation_evidence() {
+        let rng = &mut ark_std::test_rng();
+        let keypairs = gen_signing_keypairs(4);
+        let signing_keys =
+            keypairs.iter().map(|k| k.public).collect::<Vec<_>>();
+        let mut dealer = setup_dkg(1);
+        let mut sign_deal = || {
+            let pvss = dealer.share(rng).expect("Test failed");
+            dealer
+                .sign_message(&pvss, &keypairs[1])
+                .expect("Test failed")
+        };
+        let (a, b) = (sign_deal(), sign_deal());
+
+        let dkg = setup_dkg(0);
+        let evidence = dkg
+            .equivocation_evidence(a.clone(), b.clone(), &signing_keys)
+            .expect("Test failed");
+        assert_eq!(
+            dkg.equivocation_evidence(b, a, &signing_keys)
+                .expect("Test failed"),
+            evidence
+        );
+
+        // a third party only needs the session and the signing keys
+        let mut bytes = vec![];
+        CanonicalSerialize::serialize(&evidence, &mut bytes)
+            .expect("Test failed");
+        let restored =
+            <EquivocationEvidence as CanonicalDeserialize>::deserialize(
+                &bytes[..],
+            )
+            .expect("Test failed");
+        assert_eq!(restored, evidence);
+        let validators = dkg.external_validators();
+        assert_eq!(
+            restored
+                .verify(&dkg.params, &validators, &signing_keys)
+                .expect("Test failed"),
+            1
+        );
+        assert!(matches!(
+            restored.verify(
+                &Params {
+                    tau: 1,
+                    ..dkg.params
+                },
+                &validators,
+                &signing_keys
+            ),
+            Err(DkgError::SessionMismatch(_))
+        ));
+    }
+
+    /// Test that evidence of messages that do not conflict is rejected
+    #[test]
+    fn test_equivocation_evidence_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let keypairs = gen_signing_keypairs(4);
+        let signing_keys =
+            keypairs.iter().map(|k| k.public).collect::<Vec<_>>();
+        let dkg = setup_dkg(0);
+
+        // re-issuing the same transcript is not an equivocation
+        let mut dealer = setup_dkg(1);
+        let pvss = dealer.share_deterministic().expect("Test failed");
+        let signed = dealer
+            .sign_message(&pvss, &keypairs[1])
+            .expect("Test failed");
+        assert!(matches!(
+            dkg.equivocation_evidence(
+                signed.clone(),
+                signed.clone(),
+                &signing_keys
+            ),
+            Err(DkgError::EvidenceNotCanonical)
+        ));
+
+        // dealings of two different dealers
+        let mut other = setup_dkg(2);
+        let pvss = other.share(rng).expect("Test failed");
+        let other_signed = other
+            .sign_message(&pvss, &keypairs[2])
+            .expect("Test failed");
+        assert!(matches!(
+            dkg.equivocation_evidence(
+                signed.clone(),
+                other_signed,
+                &signing_keys
+            ),
+            Err(DkgError::EvidenceSenderMismatch { .. })
+        ));
+
+        // messages that are not dealings
+        let mut refresh =
+            |dealer: &mut PubliclyVerifiableDkg<EllipticCurve>| {
+                let pvss = match dealer.share(rng).expect("Test failed") {
+                    Message::Deal(pvss) => Message::Refresh(pvss),
+                    _ => unreachable!(),
+                };
+                dealer
+                    .sign_message(&pvss, &keypairs[1])
+                    .expect("Test failed")
+            };
+        let (a, b) = (refresh(&mut dealer), refresh(&mut dealer));
+        assert!(matches!(
+            dkg.equivocation_evidence(a, b, &signing_keys),
+            Err(DkgError::EvidenceNotDealing(MessageKind::Refresh))
+        ));
+
+        // a dealing signed with the key of another validator
+        let pvss = dealer.share(rng).expect("Test failed");
+        let mut forged = dealer
+            .sign_message(&pvss, &keypairs[2])
+            .expect("Test failed");
+        forged.signer = signing_keys[1];
+        assert!(matches!(
+            dkg.equivocation_evidence(signed, forged, &signing_keys),
+            Err(DkgError::InvalidSignature)
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::*;
+use ark_serialize::*;
+
+/// The parameters of a DKG session
+///
+/// The key is shared with a polynomial of degree
+/// `shares_num - security_threshold`, so that `shares_num -
+/// security_threshold + 1` shares are needed to decrypt, and the DKG
+/// can aggregate once dealers holding `shares_num - security_threshold`
+/// shares have dealt. Use [`Params::builder`] to set the parameters in
+/// terms of the number of shares needed to decrypt, with validation.
+#[derive(
+    Serialize,
+    Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct Params {
+    /// The session id, which binds the transcripts to this session
+    pub tau: u64,
+    /// The number of shares beyond the degree of the sharing polynomial
+    pub security_threshold: u32,
+    /// The total number of shares, split between the validators
+    pub shares_num: u32,
+    /// The maximum number of PVSS transcripts scheduled to be dealt in a block
+    pub dealings_per_block: u32,
+}
+
+impl Params {
+    /// Start building the parameters of the session `tau`, with
+    /// `shares_num` shares of which `decryption_threshold` are needed
+    /// to decrypt
+    pub fn builder(
+        tau: u64,
+        shares_num: u32,
+        decryption_threshold: u32,
+    ) -> ParamsBuilder {
+        ParamsBuilder {
+            tau,
+            shares_num,
+            decryption_threshold,
+            dealings_per_block: 1,
+        }
+    }
+
+    /// The degree of the sharing polynomial. It is also the weight of the
+    /// dealers whose transcripts are needed to aggregate
+    pub fn degree(&self) -> u32 {
+        self.shares_num - self.security_threshold
+    }
+
+    /// The number of shares needed to decrypt, or to interpolate a share
+    pub fn decryption_threshold(&self) -> u32 {
+        self.degree() + 1
+    }
+
+    /// Check that the parameters are consistent and that decrypting needs
+    /// a strict majority of the shares, so that no two disjoint sets of
+    /// validators can both decrypt
+    pub fn validate(&self) -> Result<(), ParamsError> {
+        if self.shares_num == 0 {
+            return Err(ParamsError::ZeroShares);
+        }
+        if self.security_threshold == 0
+            || self.security_threshold > self.shares_num
+        {
+            return Err(ParamsError::SecurityThresholdOutOfRange {
+                security_threshold: self.security_threshold,
+                shares_num: self.shares_num,
+            });
+        }
+        if 2 * (self.decryption_threshold() as u64) <= self.shares_num as u64 {
+            return Err(ParamsError::UnsafeThreshold {
+                decryption_threshold: self.decryption_threshold(),
+                shares_num: self.shares_num,
+            });
+        }
+        if self.dealings_per_block == 0 {
+            return Err(ParamsError::ZeroDealingBudget);
+        }
+        Ok(())
+    }
+}
+
+/// A builder of validated [`Params`]
+#[derive(Copy, Clone, Debug)]
+pub struct ParamsBuilder {
+    tau: u64,
+    shares_num: u32,
+    decryption_threshold: u32,
+    dealings_per_block: u32,
+}
+
+impl ParamsBuilder {
+    /// Set the maximum number of PVSS transcripts scheduled to be dealt
+    /// in a block, one by default
+    pub fn dealings_per_block(mut self, dealings_per_block: u32) -> Self {
+        self.dealings_per_block = dealings_per_block;
+        self
+    }
+
+    /// Build the parameters, checking them with [`Params::validate`]
+    pub fn build(self) -> Result<Params, ParamsError> {
+        if self.decryption_threshold == 0
+            || self.decryption_threshold > self.shares_num
+        {
+            return Err(ParamsError::DecryptionThresholdOutOfRange {
+                decryption_threshold: self.decryption_threshold,
+                shares_num: self.shares_num,
+            });
+        }
+        let params = Params {
+            tau: self.tau,
+            security_threshold: self.shares_num - self.decryption_threshold + 1,
+            shares_num: self.shares_num,
+            dealings_per_block: self.dealings_per_block,
+        };
+        params.validate()?;
+        Ok(params)
+    }
+}
+
+#[cfg(test)]
+mod test_params {
+    use super::*;
+
+    /// Test that the builder sets the threshold as the number of
+    /// shares needed to decrypt
+    #[test]
+    fn test_params_builder() {
+        let params = Params::builder(1, 8, 5)
+            .dealings_per_block(2)
+            .build()
+            .expect("Test failed");
+        assert_eq!(params.tau, 1);
+        assert_eq!(params.shares_num, 8);
+        assert_eq!(params.security_threshold, 4);
+        assert_eq!(params.degree(), 4);
+        assert_eq!(params.decryption_threshold(), 5);
+        assert_eq!(params.dealings_per_block, 2);
+
+        // all the shares may be needed
+        let params = Params::builder(0, 4, 4).build().expect("Test failed");
+        assert_eq!(params.security_threshold, 1);
+        assert_eq!(params.dealings_per_block, 1);
+    }
+
+    /// Test that inconsistent and unsafe parameters are rejected
+    #[test]
+    fn test_params_rejected() {
+        assert_eq!(
+            Params::builder(0, 0, 0).build().unwrap_err(),
+            ParamsError::DecryptionThresholdOutOfRange {
+                decryption_threshold: 0,
+                shares_num: 0
+            }
+        );
+        assert_eq!(
+            Params::builder(0, 4, 5).build().unwrap_err(),
+            ParamsError::DecryptionThresholdOutOfRange {
+                decryption_threshold: 5,
+                shares_num: 4
+            }
+        );
+        // half of the shares must not be enough
+        assert!(Params::builder(0, 8, 5).build().is_ok());
+        for decryption_threshold in [3, 4] {
+            assert_eq!(
+                Params::builder(0, 8, decryption_threshold)
+                    .build()
+                    .unwrap_err(),
+                ParamsError::UnsafeThreshold {
+                    decryption_threshold,
+                    shares_num: 8
+                }
+            );
+        }
+        assert_eq!(
+            Params::builder(0, 8, 5)
+                .dealings_per_block(0)
+                .build()
+                .unwrap_err(),
+            ParamsError::ZeroDealingBudget
+        );
+
+        let params = |security_threshold, shares_num| Params {
+            tau: 0,
+            security_threshold,
+            shares_num,
+            dealings_per_block: 1,
+        };
+        assert_eq!(params(0, 0).validate(), Err(ParamsError::ZeroShares));
+        for (security_threshold, shares_num) in [(0, 4), (5, 4)] {
+            assert_eq!(
+                params(security_threshold, shares_num).validate(),
+                Err(ParamsError::SecurityThresholdOutOfRange {
+                    security_threshold,
+                    shares_num
+                })
+            );
+        }
+        assert!(params(2, 4).validate().is_ok());
+        assert!(params(3, 4).validate().is_err());
+    }
+
+    /// Test that a security threshold of 3 out of 4 shares, accepted
+    /// before the decryption threshold had to be a strict majority of
+    /// the shares, is now rejected both by the parameters and the DKG
+    #[test]
+    fn test_params_minority_decryption_threshold_rejected() {
+        use crate::dkg::pv::test_common::*;
+
+        let params = Params {
+            tau: 0,
+            security_threshold: 3,
+            shares_num: 4,
+            dealings_per_block: 1,
+        };
+        let unsafe_threshold = ParamsError::UnsafeThreshold {
+            decryption_threshold: 2,
+            shares_num: 4,
+        };
+        assert_eq!(params.validate(), Err(unsafe_threshold));
+
+        let keypairs = gen_keypairs();
+        let validators = gen_validators_with_powers(&keypairs, &[1; 4]);
+        let proofs = gen_proofs(&keypairs, &validators);
+        let me = validators[0].clone();
+        let err = PubliclyVerifiableDkg::<EllipticCurve>::new(
+            validators,
+            &proofs,
+            params,
+            &me,
+            keypairs[0],
+        )
+        .expect_err("Test failed");
+        assert!(matches!(err, DkgError::Params(e) if e == unsafe_threshold));
+    }
+}
@@ -1,56 +1,136 @@
 use crate::*;
-use anyhow::Context;
 use ark_ec::bn::TwistType::D;
 use ark_ec::PairingEngine;
 use ark_ff::Field;
 use ark_serialize::*;
 use ark_std::{end_timer, start_timer};
-use ferveo_common::{ExternalValidator, PublicKey};
-use std::collections::BTreeMap;
+use ferveo_common::{ExternalValidator, ProofOfPossession, PublicKey};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Personalization of the keyed hash deriving the seed of a dealer's
+/// deterministic secret polynomial
+const DEALING_SEED_PERSONAL: &[u8] = b"FERVEO-PVSS-SEED";
 
 /// The DKG context that holds all of the local state for participating in the DKG
-#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
+///
+/// The serde form of the DKG omits the session keypair, and is restored
+/// with [`RestoreState`]
+#[derive(Serialize, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[serde(bound = "")]
 pub struct PubliclyVerifiableDkg<E: PairingEngine> {
     pub params: Params,
     pub pvss_params: PubliclyVerifiableParams<E>,
+    /// The session keypair of this validator, kept out of the serde form
+    /// so that exported state never holds the decryption key
+    #[serde(skip)]
     pub session_keypair: ferveo_common::Keypair<E>,
     pub validators: Vec<ferveo_common::Validator<E>>,
     pub vss: BTreeMap<u32, PubliclyVerifiableSS<E>>,
+    /// Dealers whose transcripts were dropped after a valid complaint
+    pub faulty_dealers: BTreeSet<u32>,
+    #[serde(with = "ferveo_common::ark_serde")]
     pub domain: ark_poly::Radix2EvaluationDomain<E::Fr>,
     pub state: DkgState<E>,
     pub me: usize,
-    pub window: (u32, u32),
+    /// The sum of the refresh transcripts applied after the DKG succeeded
+    pub refresh: Option<PubliclyVerifiableSS<E>>,
+    /// The recovery of the key shares of a validator, if any
+    pub recovery: Option<Recovery<E>>,
+    /// If this DKG follows the session without a local identity, in which
+    /// case `me` and `session_keypair` are not used
+    pub observer: bool,
+    /// The deadlines after which the DKG fails if it has not progressed
+    pub deadlines: Deadlines,
+    /// The current refresh round, which refresh transcripts are bound to
+    pub refresh_round: u64,
+    /// Validators whose refresh transcript was applied in the current
+    /// refresh round
+    pub refreshed: BTreeSet<u32>,
+    /// The validators that registered a new session key by recovering
+    /// their key shares, see [`PubliclyVerifiableDkg::session_key`]
+    pub rekeyed: BTreeMap<u32, Rekeyed<E>>,
+    /// Our new session keypair while we complete the recovery of our key
+    /// shares, which replaces `session_keypair` once the completion is
+    /// applied. It is kept out of the serde form like the session keypair
+    #[serde(skip)]
+    pub recovery_keypair: Option<ferveo_common::Keypair<E>>,
 }
 
 impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
     /// Create a new DKG context to participate in the DKG
     /// Every identity in the DKG is linked to an ed25519 public key;
     /// `validatorst`: List of validators
+    /// `proofs`: the proofs of possession of the session keys of `validators`
     /// `params` contains the parameters of the DKG such as number of shares
     /// `me` the validator creating this instance
     /// `session_keypair` the keypair for `me`
     pub fn new(
         validators: Vec<ExternalValidator<E>>,
+        proofs: &[ProofOfPossession<E>],
         params: Params,
         me: &ExternalValidator<E>,
         session_keypair: ferveo_common::Keypair<E>,
     ) -> Result<Self> {
-        use ark_std::UniformRand;
+        // keep track of the owner of this instance in the validator set
+        let me = validators
+            .iter()
+            .position(|probe| me == probe)
+            .ok_or(DkgError::NotInValidatorSet)?;
+        verify_proofs_of_possession(&validators, proofs)?;
+        Self::init(validators, params, me, session_keypair, false)
+    }
+
+    /// Create a new DKG context to follow the DKG without a local identity,
+    /// for nodes that verify and apply messages to learn the final key
+    /// `validators`: List of validators
+    /// `proofs`: the proofs of possession of the session keys of `validators`
+    /// `params` contains the parameters of the DKG such as number of shares
+    ///
+    /// The APIs that deal, complain or decrypt fail on an observer
+    pub fn new_observer(
+        validators: Vec<ExternalValidator<E>>,
+        proofs: &[ProofOfPossession<E>],
+        params: Params,
+    ) -> Result<Self> {
+        verify_proofs_of_possession(&validators, proofs)?;
+        Self::init_observer(validators, params)
+    }
+
+    /// Create an observer of the DKG among `validators`, whose proofs of
+    /// possession of their session keys have been verified
+    pub(crate) fn init_observer(
+        validators: Vec<ExternalValidator<E>>,
+        params: Params,
+    ) -> Result<Self> {
+        // an observer is not in the validator set
+        let me = validators.len();
+        let session_keypair = ferveo_common::Keypair {
+            decryption_key: E::Fr::zero(),
+        };
+        Self::init(validators, params, me, session_keypair, true)
+    }
+
+    /// Create a DKG context for `validators`, whose proofs of possession
+    /// of their session keys have been verified
+    pub(crate) fn init(
+        validators: Vec<ExternalValidator<E>>,
+        params: Params,
+        me: usize,
+        session_keypair: ferveo_common::Keypair<E>,
+        observer: bool,
+    ) -> Result<Self> {
+        params.validate()?;
+        // The domain is rounded up to the next power of two; only the
+        // first `shares_num` points of the domain are assigned to shares
         let domain = ark_poly::Radix2EvaluationDomain::<E::Fr>::new(
             params.shares_num as usize,
         )
-        .ok_or_else(|| anyhow!("unable to construct domain"))?;
+        .ok_or(DkgError::InvalidDomain(params.shares_num))?;
 
-        // keep track of the owner of this instance in the validator set
-        let me = validators.iter().position(|probe| me == probe).context(
-            "could not find this validator in the provided validator set",
-        )?;
-
-        let validators = make_validators(validators);
+        let validators = make_validators(validators, &params)?;
+        // check that the dealing schedule can be computed
+        DealingSchedule::new(&params, &validators)?;
 
-        // TODO: Remove my_partition
-        let my_partition =
-            params.retry_after * (2 * me as u32 / params.retry_after);
         Ok(Self {
             session_keypair,
             params,
@@ -59,42 +139,30 @@ impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
                 h: E::G2Projective::prime_subgroup_generator(),
             },
             vss: BTreeMap::new(),
+            faulty_dealers: BTreeSet::new(),
             domain,
             state: DkgState::Sharing {
                 accumulated_shares: 0,
-                block: 0,
             },
             me,
             validators,
-            // TODO: Remove window
-            window: (my_partition, my_partition + params.retry_after),
+            refresh: None,
+            recovery: None,
+            observer,
+            deadlines: Deadlines::default(),
+            refresh_round: 0,
+            refreshed: BTreeSet::new(),
+            rekeyed: BTreeMap::new(),
+            recovery_keypair: None,
         })
     }
 
-    /// Increment the number of blocks processed since the DKG protocol
-    /// began if we are still sharing PVSS transcripts.
-    ///
-    /// Returns a value indicating if we should issue a PVSS transcript
-    pub fn increase_block(&mut self) -> PvssScheduler {
-        match self.state {
-            DkgState::Sharing { ref mut block, .. }
-                if !self.vss.contains_key(&(self.me as u32)) =>
-            {
-                *block += 1;
-                // if our scheduled window begins, issue PVSS
-                if self.window.0 + 1 == *block {
-                    PvssScheduler::Issue
-                } else if &self.window.1 < block {
-                    // reset the window during which we try to get our
-                    // PVSS on chain
-                    *block = self.window.0 + 1;
-                    // reissue PVSS
-                    PvssScheduler::Issue
-                } else {
-                    PvssScheduler::Wait
-                }
-            }
-            _ => PvssScheduler::Wait,
+    /// Fail if this DKG is an observer, which can not `action`
+    pub(crate) fn check_participant(&self, action: DkgOperation) -> Result<()> {
+        if self.observer {
+            Err(DkgError::Observer(action))
+        } else {
+            Ok(())
         }
     }
 
@@ -104,17 +172,55 @@ impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
     pub fn share<R: Rng>(&mut self, rng: &mut R) -> Result<Message<E>> {
         use ark_std::UniformRand;
         print_time!("PVSS Sharing");
+        self.check_participant(DkgOperation::Deal)?;
         let vss = Pvss::<E>::new(&E::Fr::rand(rng), self, rng)?;
         match self.state {
             DkgState::Sharing { .. } | DkgState::Dealt => {
                 Ok(Message::Deal(vss))
             }
-            _ => {
-                Err(anyhow!("DKG is not in a valid state to deal PVSS shares"))
-            }
+            _ => Err(DkgError::WrongState(DkgOperation::Deal)),
         }
     }
 
+    /// Create the PVSS instance of this dealer within this DKG session,
+    /// with a secret polynomial derived from the session keypair, the
+    /// session id and the validator set instead of sampled at random. The
+    /// randomness is a ChaCha20 stream, whose output is fixed across
+    /// versions of `rand`, unlike that of `StdRng`
+    ///
+    /// Re-issuing a transcript with this method always produces the same
+    /// transcript, so a dealer never has two different transcripts in flight
+    pub fn share_deterministic(&mut self) -> Result<Message<E>> {
+        use rand_chacha::rand_core::SeedableRng;
+        let seed = self.dealing_seed()?;
+        self.share(&mut rand_chacha::ChaCha20Rng::from_seed(seed))
+    }
+
+    /// The seed of the secret polynomial of [`Self::share_deterministic`],
+    /// a keyed hash of the session id and validator set under the session
+    /// decryption key
+    fn dealing_seed(&self) -> Result<[u8; 32]> {
+        let mut key = vec![];
+        CanonicalSerialize::serialize(
+            &self.session_keypair.decryption_key,
+            &mut key,
+        )?;
+        let session = session_hash(&self.params, &self.external_validators())?;
+        let mut seed = [0u8; 32];
+        seed.copy_from_slice(
+            blake2b_simd::Params::new()
+                .hash_length(32)
+                .key(&key)
+                .personal(DEALING_SEED_PERSONAL)
+                .to_state()
+                .update(&self.params.tau.to_le_bytes())
+                .update(&session)
+                .finalize()
+                .as_bytes(),
+        );
+        Ok(seed)
+    }
+
     /// Aggregate all received PVSS messages into a single message, prepared to post on-chain
     pub fn aggregate(&self) -> Result<Message<E>> {
         match self.state {
@@ -123,19 +229,63 @@ impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
                 Ok(Message::Aggregate(Aggregation {
                     vss: aggregate(self),
                     final_key,
+                    dealers: DealerBitmap::new(self.vss.keys().copied()),
                 }))
             }
-            _ => Err(anyhow!(
-                "Not enough PVSS transcripts received to aggregate"
-            )),
+            DkgState::Sharing { accumulated_shares } => {
+                Err(DkgError::InsufficientShares {
+                    received: accumulated_shares,
+                    required: self.params.degree(),
+                })
+            }
+            _ => Err(DkgError::WrongState(DkgOperation::Aggregate)),
+        }
+    }
+
+    /// Aggregate the PVSS transcripts of a chosen set of `dealers` into a
+    /// single message, prepared to post on-chain. The dealers must have
+    /// dealt a transcript and their weight must meet the security threshold
+    pub fn aggregate_dealers(
+        &self,
+        dealers: &BTreeSet<u32>,
+    ) -> Result<Message<E>> {
+        if !matches!(self.state, DkgState::Dealt) {
+            return Err(DkgError::WrongState(DkgOperation::Aggregate));
+        }
+        let mut weight = 0;
+        for dealer in dealers {
+            if !self.vss.contains_key(dealer) {
+                return Err(DkgError::MissingTranscript(*dealer));
+            }
+            weight += self.validators[*dealer as usize].weight;
         }
+        let required = self.params.degree();
+        if weight < required {
+            return Err(DkgError::InsufficientShares {
+                received: weight,
+                required,
+            });
+        }
+        Ok(Message::Aggregate(Aggregation {
+            vss: aggregate_dealers(self, dealers),
+            final_key: self.dealers_key(dealers),
+            dealers: DealerBitmap::new(dealers.iter().copied()),
+        }))
     }
 
     /// Returns the public key generated by the DKG
     pub fn final_key(&self) -> E::G1Affine {
-        self.vss
-            .values()
-            .map(|vss| vss.coeffs[0].into_projective())
+        self.dealers_key(self.vss.keys())
+    }
+
+    /// Returns the public key generated by the transcripts of `dealers`
+    fn dealers_key<'a>(
+        &self,
+        dealers: impl IntoIterator<Item = &'a u32>,
+    ) -> E::G1Affine {
+        dealers
+            .into_iter()
+            .map(|dealer| self.vss[dealer].coeffs[0].into_projective())
             .sum::<E::G1Projective>()
             .into_affine()
     }
@@ -149,40 +299,138 @@ impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
         payload: &Message<E>,
     ) -> Result<()> {
         match payload {
-            Message::Deal(pvss) if matches!(self.state, DkgState::Sharing{..} | DkgState::Dealt) => {
+            Message::Deal(pvss)
+                if matches!(
+                    self.state,
+                    DkgState::Sharing { .. } | DkgState::Dealt
+                ) =>
+            {
                 // TODO: If this is two slow, we can convert self.validators to
                 // an address keyed hashmap after partitioning the shares shares
                 // in the [`new`] method
-                let sender = self.validators
-                    .iter().position(|probe| sender == &probe.validator)
-                    .context("dkg received unknown dealer")?;
-                if self.vss.contains_key(&(sender as u32)) {
-                    Err(anyhow!("Repeat dealer {}", sender))
-                } else if !pvss.verify_optimistic() {
-                    Err(anyhow!("Invalid PVSS transcript"))
+                let dealer = self
+                    .validators
+                    .iter()
+                    .position(|probe| sender == &probe.validator)
+                    .ok_or_else(|| {
+                        DkgError::UnknownValidator(sender.address.clone())
+                    })? as u32;
+                if self.vss.contains_key(&dealer) {
+                    Err(DkgError::DuplicateDealer(dealer))
+                } else if self.faulty_dealers.contains(&dealer) {
+                    Err(DkgError::FaultyDealer(dealer))
+                } else if !pvss.is_well_formed(self) {
+                    Err(DkgError::MalformedTranscript(dealer))
+                } else if !pvss.verify_optimistic(self, sender) {
+                    Err(DkgError::InvalidTranscript(dealer))
                 } else {
                     Ok(())
                 }
             }
-            Message::Aggregate(Aggregation { vss, final_key }) if matches!(self.state, DkgState::Dealt) => {
-                let minimum_shares = self.params.shares_num
-                    - self.params.security_threshold;
-                let verified_shares = vss.verify_aggregation(self)?;
+            Message::Aggregate(Aggregation {
+                vss,
+                final_key,
+                dealers,
+            }) if matches!(self.state, DkgState::Dealt) => {
+                let minimum_shares = self.params.degree();
+                let dealers = dealers.dealers().collect();
+                let verified_shares =
+                    vss.verify_aggregation_exact(self, &dealers)?;
                 // we reject aggregations that fail to meet the security threshold
                 if verified_shares < minimum_shares {
-                    Err(
-                        anyhow!("Aggregation failed because the verified shares was insufficient")
-                    )
-                } else if &self.final_key() == final_key {
+                    Err(DkgError::InsufficientShares {
+                        received: verified_shares,
+                        required: minimum_shares,
+                    })
+                } else if &self.dealers_key(&dealers) == final_key {
                     Ok(())
                 } else {
-                    Err(
-                        anyhow!("The final key was not correctly derived from the aggregated transcripts")
-                    )
+                    Err(DkgError::FinalKeyMismatch)
                 }
             }
-            _ => Err(anyhow!("DKG state machine is not in correct state to verify this message"))
+            Message::Complaint(complaint)
+                if matches!(
+                    self.state,
+                    DkgState::Sharing { .. } | DkgState::Dealt
+                ) =>
+            {
+                let complainer = self
+                    .validators
+                    .iter()
+                    .position(|probe| sender == &probe.validator)
+                    .ok_or_else(|| {
+                        DkgError::UnknownValidator(sender.address.clone())
+                    })?;
+                let pvss = self
+                    .vss
+                    .get(&complaint.dealer)
+                    .ok_or(DkgError::MissingTranscript(complaint.dealer))?;
+                complaint.verify(self, complainer, pvss)
+            }
+            Message::Refresh(pvss)
+                if matches!(self.state, DkgState::Success { .. }) =>
+            {
+                self.verify_refresh(sender, pvss)
+            }
+            Message::RecoveryRequest(..)
+            | Message::RecoveryMask(_)
+            | Message::RecoveryShares(_)
+            | Message::RecoveryComplete(_)
+                if matches!(self.state, DkgState::Success { .. }) =>
+            {
+                self.verify_recovery(sender, payload)
+            }
+            _ => Err(DkgError::WrongState(DkgOperation::VerifyMessage)),
+        }
+    }
+
+    /// Verify the PVSS dealing messages of a block proposal at once
+    /// `deals` are the transcripts with the validators that sent them
+    /// `rng` is a cryptographic random number generator
+    ///
+    /// The proofs of knowledge are checked in a batch; if the batch
+    /// fails, the error names the first dealer whose transcript is invalid
+    pub fn verify_deals<R: Rng>(
+        &self,
+        deals: &[(ExternalValidator<E>, Pvss<E>)],
+        rng: &mut R,
+    ) -> Result<()> {
+        if !matches!(self.state, DkgState::Sharing { .. } | DkgState::Dealt) {
+            return Err(DkgError::WrongState(DkgOperation::VerifyMessage));
+        }
+        let mut dealers = Vec::with_capacity(deals.len());
+        let mut seen = BTreeSet::new();
+        for (sender, pvss) in deals {
+            let dealer = self
+                .validators
+                .iter()
+                .position(|probe| sender == &probe.validator)
+                .ok_or_else(|| {
+                    DkgError::UnknownValidator(sender.address.clone())
+                })? as u32;
+            if self.vss.contains_key(&dealer) || !seen.insert(dealer) {
+                return Err(DkgError::DuplicateDealer(dealer));
+            } else if self.faulty_dealers.contains(&dealer) {
+                return Err(DkgError::FaultyDealer(dealer));
+            } else if !pvss.is_well_formed(self) {
+                return Err(DkgError::MalformedTranscript(dealer));
+            }
+            dealers.push(dealer);
+        }
+        let batch = deals
+            .iter()
+            .map(|(sender, pvss)| (sender, pvss))
+            .collect::<Vec<_>>();
+        if batch_verify_optimistic(self, &batch, rng) {
+            return Ok(());
+        }
+        // find the culprit
+        for ((sender, pvss), dealer) in deals.iter().zip(dealers) {
+            if !pvss.verify_optimistic(self, sender) {
+                return Err(DkgError::InvalidTranscript(dealer));
+            }
         }
+        Ok(())
     }
 
     /// After consensus has agreed to include a verified
@@ -194,29 +442,95 @@ impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
         payload: Message<E>,
     ) -> Result<()> {
         match payload {
-            Message::Deal(pvss) if matches!(self.state, DkgState::Sharing{..} | DkgState::Dealt) => {
+            Message::Deal(pvss)
+                if matches!(
+                    self.state,
+                    DkgState::Sharing { .. } | DkgState::Dealt
+                ) =>
+            {
                 // Add the ephemeral public key and pvss transcript
-                let sender = self.validators
-                    .iter().position(|probe| sender.address == probe.validator.address)
-                    .context("dkg received unknown dealer")?;
+                let sender = self
+                    .validators
+                    .iter()
+                    .position(|probe| sender == probe.validator)
+                    .ok_or_else(|| {
+                        DkgError::UnknownValidator(sender.address.clone())
+                    })?;
                 self.vss.insert(sender as u32, pvss);
 
                 // we keep track of the amount of shares seen until the security
                 // threshold is met. Then we may change the state of the DKG
-                if let DkgState::Sharing { ref mut accumulated_shares, .. } = &mut self.state {
-                    *accumulated_shares += 1;
-                    if *accumulated_shares >= self.params.shares_num - self.params.security_threshold {
+                if let DkgState::Sharing {
+                    ref mut accumulated_shares,
+                    ..
+                } = &mut self.state
+                {
+                    *accumulated_shares += self.validators[sender].weight;
+                    if *accumulated_shares >= self.params.degree() {
                         self.state = DkgState::Dealt;
                     }
                 }
                 Ok(())
             }
-            Message::Aggregate(_) if matches!(self.state, DkgState::Dealt) => {
+            Message::Aggregate(Aggregation { dealers, .. })
+                if matches!(self.state, DkgState::Dealt) =>
+            {
+                // only the transcripts of the aggregated dealers make up
+                // the final key and the key shares
+                self.vss.retain(|dealer, _| dealers.contains(*dealer));
                 // change state and cache the final key
-                self.state = DkgState::Success { final_key: self.final_key() };
+                self.state = DkgState::Success {
+                    final_key: self.final_key(),
+                };
+                Ok(())
+            }
+            Message::Complaint(complaint)
+                if matches!(
+                    self.state,
+                    DkgState::Sharing { .. } | DkgState::Dealt
+                ) =>
+            {
+                // drop the transcript of the faulty dealer so it is not aggregated
+                if self.vss.remove(&complaint.dealer).is_some() {
+                    self.faulty_dealers.insert(complaint.dealer);
+                    let dealt_weight = self
+                        .vss
+                        .keys()
+                        .map(|dealer| self.validators[*dealer as usize].weight)
+                        .sum::<u32>();
+                    match &mut self.state {
+                        DkgState::Sharing {
+                            ref mut accumulated_shares,
+                            ..
+                        } => {
+                            *accumulated_shares = dealt_weight;
+                        }
+                        // we may fall back under the threshold, in which case
+                        // the sharing phase starts again
+                        _ if dealt_weight < self.params.degree() => {
+                            self.state = DkgState::Sharing {
+                                accumulated_shares: dealt_weight,
+                            };
+                        }
+                        _ => {}
+                    }
+                }
                 Ok(())
             }
-            _ => Err(anyhow!("DKG state machine is not in correct state to apply this message"))
+            Message::Refresh(pvss)
+                if matches!(self.state, DkgState::Success { .. }) =>
+            {
+                self.apply_refresh(&sender, pvss)
+            }
+            Message::RecoveryRequest(..)
+            | Message::RecoveryMask(_)
+            | Message::RecoveryShares(_)
+            | Message::RecoveryComplete(_)
+                if matches!(self.state, DkgState::Success { .. }) =>
+            {
+                self.apply_recovery(&sender, payload)
+            }
+            _ => Err(DkgError::WrongState(DkgOperation::ApplyMessage)),
         }
     }
 }
@@ -231,19 +545,76 @@ impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
 )]
 #[serde(bound = "")]
 pub struct Aggregation<E: PairingEngine> {
-    #[serde(with = "ferveo_common::ark_serde")]
     vss: AggregatedPvss<E>,
     #[serde(with = "ferveo_common::ark_serde")]
     final_key: E::G1Affine,
+    dealers: DealerBitmap,
+}
+
+impl<E: PairingEngine> Aggregation<E> {
+    /// The dealers whose transcripts are aggregated
+    pub fn dealers(&self) -> impl Iterator<Item = u32> + '_ {
+        self.dealers.dealers()
+    }
+}
+
+/// A set of dealers as a bitmap, indexed by the position
+/// of the dealer in the validator set
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct DealerBitmap {
+    bits: Vec<u8>,
+}
+
+impl DealerBitmap {
+    /// Create the bitmap of the set of `dealers`
+    pub fn new(dealers: impl IntoIterator<Item = u32>) -> Self {
+        let mut bits = vec![];
+        for dealer in dealers {
+            let byte = dealer as usize / 8;
+            if bits.len() <= byte {
+                bits.resize(byte + 1, 0);
+            }
+            bits[byte] |= 1 << (dealer % 8);
+        }
+        Self { bits }
+    }
+
+    /// Returns if `dealer` is in the set
+    pub fn contains(&self, dealer: u32) -> bool {
+        self.bits
+            .get(dealer as usize / 8)
+            .is_some_and(|byte| byte & (1 << (dealer % 8)) != 0)
+    }
+
+    /// The dealers in the set, in increasing order
+    pub fn dealers(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..self.bits.len() as u32 * 8)
+            .filter(move |dealer| self.contains(*dealer))
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(bound = "")]
 pub enum Message<E: PairingEngine> {
-    #[serde(with = "ferveo_common::ark_serde")]
     Deal(Pvss<E>),
-    #[serde(with = "ferveo_common::ark_serde")]
     Aggregate(Aggregation<E>),
+    Complaint(Complaint<E>),
+    Refresh(Pvss<E>),
+    RecoveryRequest(PublicKey<E>, ProofOfPossession<E>),
+    RecoveryMask(Vec<Pvss<E>>),
+    RecoveryShares(Vec<Vec<KeyShareCiphertext<E>>>),
+    #[serde(with = "ferveo_common::ark_serde::seq")]
+    RecoveryComplete(Vec<E::G2Affine>),
 }
 
 /// Factory functions for testing
@@ -264,6 +635,15 @@ pub(crate) mod test_common {
             .collect()
     }
 
+    /// Generate a message signing keypair for each of `n` validators
+    pub fn gen_signing_keypairs(n: usize) -> Vec<ed25519_dalek::Keypair> {
+        use rand_old::SeedableRng;
+        let rng = &mut rand_old::rngs::StdRng::seed_from_u64(0);
+        (0..n)
+            .map(|_| ed25519_dalek::Keypair::generate(rng))
+            .collect()
+    }
+
     /// Generate a set of keypairs for each validator
     pub fn gen_keypairs() -> Vec<ferveo_common::Keypair<EllipticCurve>> {
         gen_n_keypairs(4)
@@ -273,10 +653,36 @@ pub(crate) mod test_common {
         keypairs: &[ferveo_common::Keypair<EllipticCurve>],
         n: u32,
     ) -> Vec<ExternalValidator<EllipticCurve>> {
-        (0..n)
-            .map(|i| ExternalValidator {
+        gen_validators_with_powers(keypairs, &vec![1; n as usize])
+    }
+
+    /// Generate validators with the given voting powers
+    pub fn gen_validators_with_powers(
+        keypairs: &[ferveo_common::Keypair<EllipticCurve>],
+        powers: &[u64],
+    ) -> Vec<ExternalValidator<EllipticCurve>> {
+        powers
+            .iter()
+            .enumerate()
+            .map(|(i, power)| ExternalValidator {
                 address: format!("validator_{}", i),
-                public_key: keypairs[i as usize].public(),
+                public_key: keypairs[i].public(),
+                power: *power,
+            })
+            .collect()
+    }
+
+    /// Generate the proofs of possession of the session keys of `validators`
+    pub fn gen_proofs(
+        keypairs: &[ferveo_common::Keypair<EllipticCurve>],
+        validators: &[ExternalValidator<EllipticCurve>],
+    ) -> Vec<ProofOfPossession<EllipticCurve>> {
+        let rng = &mut ark_std::test_rng();
+        validators
+            .iter()
+            .zip(keypairs.iter())
+            .map(|(validator, keypair)| {
+                keypair.prove_possession(&validator.address, rng)
             })
             .collect()
     }
@@ -293,16 +699,33 @@ pub(crate) mod test_common {
         shares_num: u32,
         my_index: usize,
     ) -> PubliclyVerifiableDkg<EllipticCurve> {
-        let keypairs = gen_n_keypairs(shares_num);
-        let validators = gen_n_validators(&keypairs, shares_num);
+        setup_dkg_with_powers(
+            security_threshold,
+            shares_num,
+            &vec![1; shares_num as usize],
+            my_index,
+        )
+    }
+
+    /// Create a test dkg where the validators have the given voting powers
+    pub fn setup_dkg_with_powers(
+        security_threshold: u32,
+        shares_num: u32,
+        powers: &[u64],
+        my_index: usize,
+    ) -> PubliclyVerifiableDkg<EllipticCurve> {
+        let keypairs = gen_n_keypairs(powers.len() as u32);
+        let validators = gen_validators_with_powers(&keypairs, powers);
+        let proofs = gen_proofs(&keypairs, &validators);
         let me = validators[my_index].clone();
         PubliclyVerifiableDkg::new(
             validators,
+            &proofs,
             Params {
                 tau: 0,
                 security_threshold,
                 shares_num,
-                retry_after: 2,
+                dealings_per_block: 2,
             },
             &me,
             keypairs[my_index],
@@ -328,25 +751,72 @@ pub(crate) mod test_common {
         security_threshold: u32,
         shares_num: u32,
     ) -> PubliclyVerifiableDkg<EllipticCurve> {
-        // Make sure that the number of shares is a power of 2 for the FFT to work (Radix-2 FFT domain is being used)
-        let is_power_of_2 = |n: u32| n != 0 && (n & (n - 1)) == 0;
-        assert!(is_power_of_2(shares_num));
+        setup_dealt_dkg_with_powers(
+            security_threshold,
+            shares_num,
+            &vec![1; shares_num as usize],
+        )
+    }
+
+    /// Run a DKG among validators with the given voting powers to
+    /// completion, returning the DKG of every validator
+    pub fn setup_finished_dkgs(
+        security_threshold: u32,
+        shares_num: u32,
+        powers: &[u64],
+    ) -> Vec<PubliclyVerifiableDkg<EllipticCurve>> {
+        let rng = &mut ark_std::test_rng();
+        let transcripts = (0..powers.len())
+            .map(|i| {
+                setup_dkg_with_powers(security_threshold, shares_num, powers, i)
+                    .share(rng)
+                    .expect("Test failed")
+            })
+            .collect::<Vec<_>>();
+        (0..powers.len())
+            .map(|i| {
+                let mut dkg = setup_dkg_with_powers(
+                    security_threshold,
+                    shares_num,
+                    powers,
+                    i,
+                );
+                for (sender, pvss) in transcripts.iter().enumerate() {
+                    let sender = dkg.validators[sender].validator.clone();
+                    dkg.apply_message(sender, pvss.clone())
+                        .expect("Test failed");
+                }
+                let aggregate = dkg.aggregate().expect("Test failed");
+                let sender = dkg.validators[0].validator.clone();
+                dkg.apply_message(sender, aggregate).expect("Test failed");
+                dkg
+            })
+            .collect()
+    }
 
+    /// Set up a dkg where every validator, weighted by the given voting
+    /// powers, has dealt a pvss transcript
+    pub fn setup_dealt_dkg_with_powers(
+        security_threshold: u32,
+        shares_num: u32,
+        powers: &[u64],
+    ) -> PubliclyVerifiableDkg<EllipticCurve> {
         let rng = &mut ark_std::test_rng();
 
         // Gather everyone's transcripts
-        let transcripts = (0..shares_num).map(|i| {
-            let mut dkg = setup_dkg_for_n_validators(
+        let transcripts = (0..powers.len()).map(|i| {
+            let mut dkg = setup_dkg_with_powers(
                 security_threshold,
                 shares_num,
-                i as usize,
+                powers,
+                i,
             );
             dkg.share(rng).expect("Test failed")
         });
 
         // Our test dkg
         let mut dkg =
-            setup_dkg_for_n_validators(security_threshold, shares_num, 0);
+            setup_dkg_with_powers(security_threshold, shares_num, powers, 0);
         transcripts.enumerate().for_each(|(sender, pvss)| {
             dkg.apply_message(dkg.validators[sender].validator.clone(), pvss)
                 .expect("Setup failed");
@@ -367,35 +837,140 @@ mod test_dkg_init {
         let rng = &mut ark_std::test_rng();
         let keypairs = gen_keypairs();
         let keypair = ferveo_common::Keypair::<EllipticCurve>::new(rng);
+        let validators = gen_validators(&keypairs);
         let err = PubliclyVerifiableDkg::<EllipticCurve>::new(
-            gen_validators(&keypairs),
+            validators.clone(),
+            &gen_proofs(&keypairs, &validators),
             Params {
                 tau: 0,
                 security_threshold: 4,
                 shares_num: 8,
-                retry_after: 2,
+                dealings_per_block: 2,
             },
             &ExternalValidator::<EllipticCurve> {
                 address: "non-existant-validator".into(),
                 public_key: keypair.public(),
+                power: 1,
             },
             keypair,
         )
         .expect_err("Test failed");
-        assert_eq!(
-            err.to_string(),
-            "could not find this validator in the provided validator set"
+        assert!(matches!(err, DkgError::NotInValidatorSet))
+    }
+
+    /// Test that validators are allocated contiguous ranges of
+    /// shares proportional to their voting power
+    #[test]
+    fn test_validator_share_ranges() {
+        let dkg = setup_dkg_with_powers(2, 8, &[1, 2, 5], 0);
+        let ranges = dkg
+            .validators
+            .iter()
+            .map(|v| (v.weight, v.share_start, v.share_end))
+            .collect::<Vec<_>>();
+        assert_eq!(ranges, vec![(1, 0, 1), (2, 1, 3), (5, 3, 8)]);
+
+        // leftover shares go to the largest remainders first
+        let dkg = setup_dkg_with_powers(2, 8, &[1, 1, 1], 0);
+        let weights =
+            dkg.validators.iter().map(|v| v.weight).collect::<Vec<_>>();
+        assert_eq!(weights, vec![3, 3, 2]);
+    }
+
+    /// Test that dkg fails to start if the validators
+    /// have no voting power
+    #[test]
+    fn test_dkg_fail_zero_voting_power() {
+        let keypairs = gen_keypairs();
+        let validators = gen_validators_with_powers(&keypairs, &[0, 0, 0, 0]);
+        let err = PubliclyVerifiableDkg::<EllipticCurve>::new(
+            validators.clone(),
+            &gen_proofs(&keypairs, &validators),
+            Params {
+                tau: 0,
+                security_threshold: 2,
+                shares_num: 4,
+                dealings_per_block: 2,
+            },
+            &validators[0],
+            keypairs[0],
         )
+        .expect_err("Test failed");
+        assert!(matches!(err, DkgError::ZeroVotingPower))
     }
 
-    /// Test that the windows of a validator are correctly
-    /// computed from the `retry_after` param
+    /// Test that a zero dealing budget is rejected
     #[test]
-    fn test_validator_windows() {
-        for i in 0..4_u32 {
-            let dkg = setup_dkg(i as usize);
-            assert_eq!(dkg.window, (2 * i, 2 * i + 2));
-        }
+    fn test_dkg_fail_zero_dealing_budget() {
+        let keypairs = gen_keypairs();
+        let validators = gen_validators(&keypairs);
+        let me = validators[0].clone();
+        let proofs = gen_proofs(&keypairs, &validators);
+        let err = PubliclyVerifiableDkg::new(
+            validators,
+            &proofs,
+            Params {
+                tau: 0,
+                security_threshold: 2,
+                shares_num: 4,
+                dealings_per_block: 0,
+            },
+            &me,
+            keypairs[0],
+        )
+        .expect_err("Test failed");
+        assert!(matches!(
+            err,
+            DkgError::Params(ParamsError::ZeroDealingBudget)
+        ))
+    }
+
+    /// Test that dkg fails to start if a validator registers a session
+    /// key it can not prove possession of
+    #[test]
+    fn test_dkg_fail_invalid_proof_of_possession() {
+        let keypairs = gen_keypairs();
+        let params = Params {
+            tau: 0,
+            security_threshold: 2,
+            shares_num: 4,
+            dealings_per_block: 2,
+        };
+        let new_dkg =
+            |validators: &[ExternalValidator<EllipticCurve>],
+             proofs: &[ProofOfPossession<EllipticCurve>]| {
+                PubliclyVerifiableDkg::new(
+                    validators.to_vec(),
+                    proofs,
+                    params,
+                    &validators[0],
+                    keypairs[0],
+                )
+            };
+
+        // validator 1 copies the key and the proof of validator 0
+        let mut validators = gen_validators(&keypairs);
+        let mut proofs = gen_proofs(&keypairs, &validators);
+        validators[1].public_key = validators[0].public_key;
+        proofs[1] = proofs[0];
+        let err = new_dkg(&validators, &proofs).expect_err("Test failed");
+        assert!(matches!(
+            err,
+            DkgError::InvalidProofOfPossession(address) if address == "validator_1"
+        ));
+
+        // a proof is needed for every validator
+        let validators = gen_validators(&keypairs);
+        let proofs = gen_proofs(&keypairs, &validators);
+        let err = new_dkg(&validators, &proofs[1..]).expect_err("Test failed");
+        assert!(matches!(
+            err,
+            DkgError::Params(ParamsError::ProofCountMismatch {
+                validators: 4,
+                proofs: 3,
+            })
+        ));
+        assert!(new_dkg(&validators, &proofs).is_ok());
     }
 }
 
@@ -452,6 +1027,39 @@ mod test_dealing {
         }
     }
 
+    /// Test that the accumulated shares are weighted by
+    /// the voting power of the dealers
+    #[test]
+    fn test_pvss_dealing_is_weighted() {
+        let rng = &mut ark_std::test_rng();
+        let powers = [1, 2, 5];
+        let transcripts = (0..powers.len())
+            .map(|i| {
+                let mut dkg = setup_dkg_with_powers(2, 8, &powers, i);
+                dkg.share(rng).expect("Test failed")
+            })
+            .collect::<Vec<_>>();
+        let mut dkg = setup_dkg_with_powers(2, 8, &powers, 0);
+
+        for (sender, pvss) in transcripts.iter().enumerate().take(2) {
+            let validator = dkg.validators[sender].validator.clone();
+            assert!(dkg.verify_message(&validator, pvss).is_ok());
+            assert!(dkg.apply_message(validator, pvss.clone()).is_ok());
+        }
+        assert!(matches!(
+            dkg.state,
+            DkgState::Sharing {
+                accumulated_shares: 3,
+                ..
+            }
+        ));
+
+        // the heaviest dealer pushes us over the threshold
+        let validator = dkg.validators[2].validator.clone();
+        assert!(dkg.apply_message(validator, transcripts[2].clone()).is_ok());
+        assert!(matches!(dkg.state, DkgState::Dealt));
+    }
+
     /// Test the verification and application of
     /// pvss transcripts from unknown validators
     /// are rejected
@@ -463,7 +1071,6 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 0,
-                block: 0
             }
         ));
         let pvss = dkg.share(rng).expect("Test failed");
@@ -471,9 +1078,13 @@ mod test_dealing {
             address: "fake-address".into(),
             public_key: ferveo_common::Keypair::<EllipticCurve>::new(rng)
                 .public(),
+            power: 1,
         };
         // check that verification fails
-        assert!(dkg.verify_message(&sender, &pvss).is_err());
+        assert!(matches!(
+            dkg.verify_message(&sender, &pvss),
+            Err(DkgError::UnknownValidator(_))
+        ));
         // check that application fails
         assert!(dkg.apply_message(sender, pvss).is_err());
         // check that state has not changed
@@ -481,11 +1092,25 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 0,
-                block: 0,
             }
         ));
     }
 
+    /// Test that a pvss transcript copied by another
+    /// dealer is rejected
+    #[test]
+    fn test_pvss_replayed_by_other_dealer_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dkg(0);
+        let pvss = dkg.share(rng).expect("Test failed");
+        let sender = dkg.validators[1].validator.clone();
+        // check that verification fails
+        assert!(matches!(
+            dkg.verify_message(&sender, &pvss),
+            Err(DkgError::InvalidTranscript(1))
+        ));
+    }
+
     /// Test that if a validator sends two pvss transcripts,
     /// the second fails to verify
     #[test]
@@ -497,12 +1122,11 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 0,
-                block: 0,
             }
         ));
 
         let pvss = dkg.share(rng).expect("Test failed");
-        let sender = dkg.validators[3].validator.clone();
+        let sender = dkg.validators[0].validator.clone();
 
         // First PVSS is accepted
         assert!(dkg.verify_message(&sender, &pvss).is_ok());
@@ -511,12 +1135,87 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 1,
-                block: 0,
             }
         ));
 
         // Second PVSS is rejected
-        assert!(dkg.verify_message(&sender, &pvss).is_err());
+        assert!(matches!(
+            dkg.verify_message(&sender, &pvss),
+            Err(DkgError::DuplicateDealer(0))
+        ));
+    }
+
+    /// Test that a transcript with the wrong number of coefficients or
+    /// shares is rejected before it can be aggregated
+    #[test]
+    fn test_pvss_malformed_transcript_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dkg(0);
+        let pvss = match setup_dkg(1).share(rng).expect("Test failed") {
+            Message::Deal(pvss) => pvss,
+            _ => panic!("Test failed"),
+        };
+        let sender = dkg.validators[1].validator.clone();
+        let malformations: [fn(&mut Pvss<EllipticCurve>); 4] = [
+            |pvss| {
+                pvss.coeffs.pop();
+            },
+            |pvss| pvss.coeffs.push(pvss.coeffs[0]),
+            |pvss| {
+                pvss.shares.pop();
+            },
+            |pvss| {
+                pvss.shares[0]
+                    .push(<EllipticCurve as PairingEngine>::G2Affine::zero())
+            },
+        ];
+        for malform in malformations {
+            let mut malformed = pvss.clone();
+            malform(&mut malformed);
+            assert!(matches!(
+                dkg.verify_message(&sender, &Message::Deal(malformed.clone())),
+                Err(DkgError::MalformedTranscript(1))
+            ));
+            assert!(matches!(
+                dkg.verify_deals(&[(sender.clone(), malformed)], rng),
+                Err(DkgError::MalformedTranscript(1))
+            ));
+        }
+        assert!(dkg.verify_message(&sender, &Message::Deal(pvss)).is_ok());
+    }
+
+    /// Test that the dealings of a block are verified in a batch,
+    /// and that a forged or repeated dealing is named in the error
+    #[test]
+    fn test_verify_deals() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dkg(0);
+        let mut deals = (0..dkg.validators.len())
+            .map(|i| {
+                let pvss = match setup_dkg(i).share(rng).expect("Test failed") {
+                    Message::Deal(pvss) => pvss,
+                    _ => panic!("Test failed"),
+                };
+                (dkg.validators[i].validator.clone(), pvss)
+            })
+            .collect::<Vec<_>>();
+        assert!(dkg.verify_deals(&deals, rng).is_ok());
+        assert!(dkg.verify_deals(&[], rng).is_ok());
+
+        // a transcript replayed by another dealer
+        let mut forged = deals.clone();
+        forged[2].1 = forged[0].1.clone();
+        assert!(matches!(
+            dkg.verify_deals(&forged, rng),
+            Err(DkgError::InvalidTranscript(2))
+        ));
+
+        // the same dealer twice in a block
+        deals[3] = deals[1].clone();
+        assert!(matches!(
+            dkg.verify_deals(&deals, rng),
+            Err(DkgError::DuplicateDealer(1))
+        ));
     }
 
     /// Test that if a validators tries to verify it's own
@@ -530,7 +1229,6 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 0,
-                block: 0,
             }
         ));
 
@@ -541,7 +1239,6 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 0,
-                block: 0,
             }
         ));
 
@@ -554,7 +1251,6 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 1,
-                block: 0,
             }
         ));
     }
@@ -569,7 +1265,6 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 0,
-                block: 0,
             }
         ));
 
@@ -595,10 +1290,9 @@ mod test_dealing {
             dkg.state,
             DkgState::Sharing {
                 accumulated_shares: 0,
-                block: 0,
             }
         ));
-        let sender = dkg.validators[3].validator.clone();
+        let sender = dkg.validators[0].validator.clone();
         dkg.state = DkgState::Success {
             final_key: G1::zero(),
         };
@@ -612,17 +1306,80 @@ mod test_dealing {
         assert!(matches!(dkg.state, DkgState::Dealt))
     }
 
-    /// Check that if a validators window has not arrived,
+    /// Test that every dealer is scheduled exactly once per round
+    /// and that no block exceeds the dealing budget
+    #[test]
+    fn test_schedule_respects_budget() {
+        let dkg = setup_dkg(0);
+        let schedule = dkg.dealing_schedule().expect("Test failed");
+        assert_eq!(schedule.round_length(), 2);
+        let vss = BTreeMap::<u32, ()>::new();
+        let mut scheduled = vec![];
+        for block in 0..schedule.round_length() {
+            let dealers = schedule.dealers_at(block, &vss);
+            assert!(dealers.len() <= dkg.params.dealings_per_block as usize);
+            scheduled.extend(dealers);
+        }
+        scheduled.sort_unstable();
+        assert_eq!(scheduled, vec![0, 1, 2, 3]);
+    }
+
+    /// Test that the order in which dealers are scheduled
+    /// depends on the session id
+    #[test]
+    fn test_schedule_depends_on_tau() {
+        let mut dkg = setup_dkg(0);
+        let schedules = (0..8)
+            .map(|tau| {
+                dkg.params.tau = tau;
+                dkg.dealing_schedule().expect("Test failed")
+            })
+            .collect::<Vec<_>>();
+        assert!(schedules.iter().any(|s| *s != schedules[0]));
+    }
+
+    /// Test that a zero dealing budget set after the DKG was created
+    /// is reported as an error, and schedules no dealer
+    #[test]
+    fn test_schedule_zero_budget() {
+        let mut dkg = setup_dkg(0);
+        dkg.params.dealings_per_block = 0;
+        assert!(matches!(
+            dkg.dealing_schedule(),
+            Err(DkgError::Params(ParamsError::ZeroDealingBudget))
+        ));
+        assert_eq!(dkg.schedule_pvss(0), PvssScheduler::Wait);
+    }
+
+    /// Test that dealers whose transcripts are on chain
+    /// are not scheduled again
+    #[test]
+    fn test_schedule_skips_dealers_on_chain() {
+        let dkg = setup_dkg(0);
+        let schedule = dkg.dealing_schedule().expect("Test failed");
+        let block = schedule.slot(0);
+        let vss = BTreeMap::from([(0u32, ())]);
+        let dealers = schedule.dealers_at(block, &vss);
+        assert!(!dealers.contains(&0));
+        assert!(schedule
+            .dealers_at(block, &BTreeMap::<u32, ()>::new())
+            .contains(&0));
+    }
+
+    /// Check that if our slot has not arrived,
     /// the DKG advises us to wait
     #[test]
-    fn test_pvss_wait_before_window() {
-        let mut dkg = setup_dkg(1);
-        if let DkgState::Sharing { block, .. } = dkg.state {
-            assert!(dkg.window.0 > block);
-        } else {
-            panic!("Test failed");
+    fn test_pvss_wait_before_slot() {
+        let dkg = setup_dkg(0);
+        let slot = dkg.dealing_schedule().expect("Test failed").slot(0);
+        for block in
+            0..dkg.dealing_schedule().expect("Test failed").round_length()
+        {
+            if block != slot {
+                assert_eq!(dkg.schedule_pvss(block), PvssScheduler::Wait);
+            }
         }
-        assert_eq!(dkg.increase_block(), PvssScheduler::Wait);
+        assert_eq!(dkg.schedule_pvss(slot), PvssScheduler::Issue);
     }
 
     /// Test that the DKG advises us to not issue a PVSS transcript
@@ -630,15 +1387,18 @@ mod test_dealing {
     #[test]
     fn test_pvss_wait_if_not_in_sharing_state() {
         let mut dkg = setup_dkg(0);
-        for state in vec![
+        let slot = dkg.dealing_schedule().expect("Test failed").slot(0);
+        for state in [
             Dealt,
             DkgState::Success {
                 final_key: G1::zero(),
             },
-            DkgState::Invalid,
+            DkgState::Invalid {
+                reason: FailureReason::DealingTimeout,
+            },
         ] {
             dkg.state = state;
-            assert_eq!(dkg.increase_block(), PvssScheduler::Wait);
+            assert_eq!(dkg.schedule_pvss(slot), PvssScheduler::Wait);
         }
     }
 
@@ -648,39 +1408,59 @@ mod test_dealing {
     fn test_pvss_wait_if_already_applied() {
         let rng = &mut ark_std::test_rng();
         let mut dkg = setup_dkg(0);
+        let slot = dkg.dealing_schedule().expect("Test failed").slot(0);
         let pvss = dkg.share(rng).expect("Test failed");
         let sender = dkg.validators[0].validator.clone();
-        // check that verification fails
         assert!(dkg.verify_message(&sender, &pvss).is_ok());
         assert!(dkg.apply_message(sender, pvss).is_ok());
-        assert_eq!(dkg.increase_block(), PvssScheduler::Wait);
+        assert_eq!(dkg.schedule_pvss(slot), PvssScheduler::Wait);
     }
 
     /// Test that if our own PVSS transcript is not on chain
-    /// after the retry window, the DKG advises us to issue again.
+    /// after a round, the DKG advises us to issue again.
     #[test]
     fn test_pvss_reissue() {
-        let mut dkg = setup_dkg(0);
-        dkg.state = DkgState::Sharing {
-            accumulated_shares: 0,
-            block: 2,
-        };
-        assert_eq!(dkg.increase_block(), PvssScheduler::Issue);
-        assert_eq!(dkg.increase_block(), PvssScheduler::Wait);
+        let dkg = setup_dkg(0);
+        let schedule = dkg.dealing_schedule().expect("Test failed");
+        let slot = schedule.slot(0);
+        assert_eq!(dkg.schedule_pvss(slot), PvssScheduler::Issue);
+        assert_eq!(dkg.schedule_pvss(slot + 1), PvssScheduler::Wait);
+        assert_eq!(
+            dkg.schedule_pvss(slot + schedule.round_length()),
+            PvssScheduler::Issue
+        );
     }
 
-    /// Test that we are only advised to issue a PVSS at the
-    /// beginning of our window, not for every block in it
+    /// Test that a deterministic transcript is byte-identical when it is
+    /// re-issued, and is bound to the dealer and the session
     #[test]
-    fn test_pvss_wait_middle_of_window() {
+    fn test_pvss_deterministic_reissue() {
+        let rng = &mut ark_std::test_rng();
+        let deal = |dkg: &mut PubliclyVerifiableDkg<EllipticCurve>| {
+            let pvss = dkg.share_deterministic().expect("Test failed");
+            bincode::serialize(&pvss).expect("Test failed")
+        };
         let mut dkg = setup_dkg(0);
-        assert_eq!(dkg.increase_block(), PvssScheduler::Issue);
-        if let DkgState::Sharing { block, .. } = dkg.state {
-            assert!(dkg.window.0 < block && block < dkg.window.1);
-        } else {
-            panic!("Test failed");
-        }
-        assert_eq!(dkg.increase_block(), PvssScheduler::Wait);
+        let first = deal(&mut dkg);
+        assert_eq!(deal(&mut dkg), first);
+        assert_eq!(deal(&mut setup_dkg(0)), first);
+        assert_ne!(deal(&mut setup_dkg(1)), first);
+        let mut other_session = setup_dkg(0);
+        other_session.params.tau = 1;
+        assert_ne!(deal(&mut other_session), first);
+        let random = dkg.share(rng).expect("Test failed");
+        assert_ne!(bincode::serialize(&random).expect("Test failed"), first);
+
+        // the transcript is valid
+        let pvss: Message<EllipticCurve> =
+            bincode::deserialize(&first).expect("Test failed");
+        let sender = dkg.validators[0].validator.clone();
+        let mut receiver = setup_dkg(1);
+        receiver
+            .verify_message(&sender, &pvss)
+            .expect("Test failed");
+        receiver.apply_message(sender, pvss).expect("Test failed");
+        assert!(receiver.vss.contains_key(&0));
     }
 }
 
@@ -701,6 +1481,80 @@ mod test_aggregation {
         assert!(matches!(dkg.state, DkgState::Success { .. }));
     }
 
+    /// Test that aggregation works when the number
+    /// of shares is not a power of two
+    #[test]
+    fn test_aggregate_non_power_of_two() {
+        let mut dkg = setup_dealt_dkg_with_n_validators(3, 7);
+        assert_eq!(dkg.domain.size(), 8);
+        let aggregate = dkg.aggregate().expect("Test failed");
+        let sender = dkg.validators[dkg.me].validator.clone();
+        assert!(dkg.verify_message(&sender, &aggregate).is_ok());
+        assert!(dkg.apply_message(sender, aggregate).is_ok());
+        assert!(matches!(dkg.state, DkgState::Success { .. }));
+    }
+
+    /// Test that a chosen set of dealers meeting the threshold can be
+    /// aggregated, and that the final key only uses their transcripts
+    #[test]
+    fn test_aggregate_dealers() {
+        let mut dkg = setup_dealt_dkg();
+        let dealers = [0, 2].iter().copied().collect::<BTreeSet<_>>();
+        let aggregate = dkg.aggregate_dealers(&dealers).expect("Test failed");
+        if let Message::Aggregate(aggregation) = &aggregate {
+            assert_eq!(aggregation.dealers().collect::<Vec<_>>(), vec![0, 2]);
+        }
+        let sender = dkg.validators[dkg.me].validator.clone();
+        assert!(dkg.verify_message(&sender, &aggregate).is_ok());
+        assert!(dkg.apply_message(sender, aggregate).is_ok());
+        assert_eq!(dkg.vss.keys().copied().collect::<BTreeSet<_>>(), dealers);
+        match dkg.state {
+            DkgState::Success { final_key } => {
+                assert_eq!(final_key, dkg.final_key())
+            }
+            _ => panic!("Test failed"),
+        }
+    }
+
+    /// Test that a set of dealers under the threshold, or with
+    /// a dealer that did not deal, can not be aggregated
+    #[test]
+    fn test_aggregate_dealers_rejected() {
+        let dkg = setup_dealt_dkg();
+        let dealers = [1].iter().copied().collect::<BTreeSet<_>>();
+        assert!(matches!(
+            dkg.aggregate_dealers(&dealers),
+            Err(DkgError::InsufficientShares {
+                received: 1,
+                required: 2
+            })
+        ));
+        let dealers = [1, 5].iter().copied().collect::<BTreeSet<_>>();
+        assert!(matches!(
+            dkg.aggregate_dealers(&dealers),
+            Err(DkgError::MissingTranscript(5))
+        ));
+    }
+
+    /// Test that an aggregation fails to verify if its
+    /// dealer set differs from the aggregated transcripts
+    #[test]
+    fn test_aggregate_wont_verify_if_wrong_dealers() {
+        let dkg = setup_dealt_dkg();
+        let mut aggregate = dkg
+            .aggregate_dealers(&[0, 2].iter().copied().collect())
+            .expect("Test failed");
+        if let Message::Aggregate(Aggregation { dealers, .. }) = &mut aggregate
+        {
+            *dealers = DealerBitmap::new(vec![0, 1, 2]);
+        }
+        let sender = dkg.validators[dkg.me].validator.clone();
+        assert!(matches!(
+            dkg.verify_message(&sender, &aggregate),
+            Err(DkgError::AggregationMismatch)
+        ));
+    }
+
     /// Test that aggregate only succeeds if we are in
     /// the state [`DkgState::Dealt]
     #[test]
@@ -708,7 +1562,6 @@ mod test_aggregation {
         let mut dkg = setup_dealt_dkg();
         dkg.state = DkgState::Sharing {
             accumulated_shares: 0,
-            block: 0,
         };
         assert!(dkg.aggregate().is_err());
         dkg.state = DkgState::Success {
@@ -727,7 +1580,6 @@ mod test_aggregation {
         let sender = dkg.validators[dkg.me].validator.clone();
         dkg.state = DkgState::Sharing {
             accumulated_shares: 0,
-            block: 0,
         };
         assert!(dkg.verify_message(&sender, &aggregate).is_err());
         assert!(dkg
@@ -744,11 +1596,56 @@ mod test_aggregation {
     /// security threshold is not met
     #[test]
     fn test_aggregate_wont_verify_if_under_threshold() {
+        let dkg = setup_dealt_dkg();
+        // a single dealer holds less than the weight needed to aggregate
+        let dealers = BTreeSet::from([0]);
+        let aggregate = Message::Aggregate(Aggregation {
+            vss: aggregate_dealers(&dkg, &dealers),
+            final_key: dkg.dealers_key(&dealers),
+            dealers: DealerBitmap::new(dealers.iter().copied()),
+        });
+        let sender = dkg.validators[dkg.me].validator.clone();
+        assert!(matches!(
+            dkg.verify_message(&sender, &aggregate),
+            Err(DkgError::InsufficientShares { .. })
+        ));
+    }
+
+    /// Test that an aggregate message will fail to verify if
+    /// any of the aggregated shares is invalid
+    #[test]
+    fn test_aggregate_wont_verify_if_share_invalid() {
         let mut dkg = setup_dealt_dkg();
-        dkg.params.shares_num = 10;
+        dkg.vss.get_mut(&0).unwrap().shares[1][0] =
+            <EllipticCurve as PairingEngine>::G2Affine::zero();
         let aggregate = dkg.aggregate().expect("Test failed");
         let sender = dkg.validators[dkg.me].validator.clone();
-        assert!(dkg.verify_message(&sender, &aggregate).is_err());
+        let err = dkg
+            .verify_message(&sender, &aggregate)
+            .expect_err("Test failed");
+        match err {
+            DkgError::AggregationFailed(report) => {
+                assert!(report.faulty_dealers.contains_key(&0))
+            }
+            _ => panic!("Test failed"),
+        }
+    }
+
+    /// Test that an aggregate message with an extra share is rejected
+    #[test]
+    fn test_aggregate_wont_verify_if_malformed() {
+        let dkg = setup_dealt_dkg();
+        let mut aggregate = match dkg.aggregate().expect("Test failed") {
+            Message::Aggregate(aggregate) => aggregate,
+            _ => panic!("Test failed"),
+        };
+        aggregate.vss.shares[0]
+            .push(<EllipticCurve as PairingEngine>::G2Affine::zero());
+        let sender = dkg.validators[dkg.me].validator.clone();
+        assert!(matches!(
+            dkg.verify_message(&sender, &Message::Aggregate(aggregate)),
+            Err(DkgError::MalformedAggregation)
+        ));
     }
 
     /// If the aggregated pvss passes, check that the announced
@@ -769,3 +1666,221 @@ mod test_aggregation {
         assert!(dkg.verify_message(&sender, &aggregate).is_err());
     }
 }
+
+/// Test following the DKG as an observer
+#[cfg(test)]
+mod test_observer {
+    use super::test_common::*;
+
+    /// Create an observer of the DKG set up by `setup_dkg`
+    fn setup_observer() -> PubliclyVerifiableDkg<EllipticCurve> {
+        let keypairs = gen_keypairs();
+        let validators = gen_validators(&keypairs);
+        let proofs = gen_proofs(&keypairs, &validators);
+        PubliclyVerifiableDkg::new_observer(
+            validators,
+            &proofs,
+            setup_dkg(0).params,
+        )
+        .expect("Setup failed")
+    }
+
+    /// Test that an observer verifies and applies every message
+    /// of the DKG and learns the same final key as the validators
+    #[test]
+    fn test_observer_follows_dkg() {
+        let rng = &mut ark_std::test_rng();
+        let mut observer = setup_observer();
+        let mut dkg = setup_dkg(0);
+        for i in 0..4 {
+            let pvss = setup_dkg(i).share(rng).expect("Test failed");
+            let sender = observer.validators[i].validator.clone();
+            observer
+                .verify_message(&sender, &pvss)
+                .expect("Test failed");
+            observer
+                .apply_message(sender.clone(), pvss.clone())
+                .expect("Test failed");
+            dkg.apply_message(sender, pvss).expect("Test failed");
+        }
+        let aggregate = dkg.aggregate().expect("Test failed");
+        let sender = dkg.validators[0].validator.clone();
+        observer
+            .verify_message(&sender, &aggregate)
+            .expect("Test failed");
+        observer
+            .apply_message(sender.clone(), aggregate.clone())
+            .expect("Test failed");
+        dkg.apply_message(sender, aggregate).expect("Test failed");
+        assert!(matches!(observer.state, DkgState::Success { .. }));
+        assert_eq!(observer.final_key(), dkg.final_key());
+
+        // the snapshot of an observer is restored as an observer
+        let snapshot = observer.save_snapshot().expect("Test failed");
+        let validators = observer
+            .validators
+            .iter()
+            .map(|v| v.validator.clone())
+            .collect::<Vec<_>>();
+        let restored = PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            &snapshot,
+            &observer.params,
+            &validators,
+        )
+        .expect("Test failed");
+        assert!(restored.observer);
+        assert_eq!(restored.final_key(), dkg.final_key());
+    }
+
+    /// Test that an observer can not deal, complain or decrypt
+    #[test]
+    fn test_observer_has_no_identity() {
+        let rng = &mut ark_std::test_rng();
+        let mut observer = setup_observer();
+        assert!(matches!(observer.share(rng), Err(DkgError::Observer(_))));
+        assert!(matches!(observer.schedule_pvss(0), PvssScheduler::Wait));
+
+        let dkg = setup_dealt_dkg();
+        let (dealer, pvss) = dkg.vss.iter().next().unwrap();
+        let sender = dkg.validators[*dealer as usize].validator.clone();
+        assert!(matches!(
+            observer.check_dealt_shares(&sender, pvss, rng),
+            Err(DkgError::Observer(_))
+        ));
+
+        // the keypair of a validator does not give an observer its shares
+        let keypairs = gen_keypairs();
+        observer.state = dkg.state.clone();
+        observer.vss = dkg.vss.clone();
+        let aggregate = dkg.aggregate().expect("Test failed");
+        observer
+            .apply_message(sender, aggregate)
+            .expect("Test failed");
+        assert!(matches!(
+            observer.decryption_contexts_simple(&keypairs[0]),
+            Err(DkgError::Observer(_))
+        ));
+        assert!(matches!(observer.refresh(rng), Err(DkgError::Observer(_))));
+        assert!(matches!(
+            observer.request_recovery(&keypairs[0], rng),
+            Err(DkgError::Observer(_))
+        ));
+    }
+}
+
+/// Test the serde representations of the DKG and its messages
+#[cfg(test)]
+mod test_serde {
+    use super::test_common::*;
+    use bincode::Options;
+    use serde::de::DeserializeSeed;
+
+    fn canonical_bytes<T: CanonicalSerialize>(t: &T) -> Vec<u8> {
+        let mut bytes = vec![];
+        CanonicalSerialize::serialize(t, &mut bytes).expect("Test failed");
+        bytes
+    }
+
+    /// Test that the DKG state round trips losslessly through JSON, in
+    /// which group elements are hex strings and the state is tagged
+    #[test]
+    fn test_dkg_json_round_trip() {
+        let dkg = setup_finished_dkgs(2, 6, &[1, 2, 3]).remove(0);
+        let validators = dkg.external_validators();
+        let restore = || RestoreState {
+            params: &dkg.params,
+            validators: &validators,
+            session_keypair: Some(gen_n_keypairs(3).remove(0)),
+        };
+        let json = serde_json::to_value(&dkg).expect("Test failed");
+        assert_eq!(json["params"]["shares_num"], 6);
+        assert_eq!(json["validators"][2]["validator"]["power"], 3);
+        assert_eq!(
+            json["state"]["Success"]["final_key"],
+            serde_json::json!(hex::encode(canonical_bytes(&dkg.final_key())))
+        );
+        assert!(json["vss"]["0"]["coeffs"][0].is_string());
+
+        let restored = restore().deserialize(&json).expect("Test failed");
+        assert_eq!(canonical_bytes(&restored), canonical_bytes(&dkg));
+
+        // bincode keeps the compact binary form
+        let bytes = bincode::serialize(&dkg).expect("Test failed");
+        let restored = restore()
+            .deserialize(&mut bincode::Deserializer::from_slice(
+                &bytes,
+                bincode::DefaultOptions::new()
+                    .with_fixint_encoding()
+                    .allow_trailing_bytes(),
+            ))
+            .expect("Test failed");
+        assert_eq!(canonical_bytes(&restored), canonical_bytes(&dkg));
+        let json = serde_json::to_string(&dkg).expect("Test failed");
+        assert!(bytes.len() < json.len());
+
+        let json = serde_json::to_value(setup_dkg(0)).expect("Test failed");
+        assert_eq!(json["state"]["Sharing"]["accumulated_shares"], 0);
+    }
+
+    /// Test that the serde form of the DKG never holds the decryption key,
+    /// and that it is only restored with the keypair of its validator
+    #[test]
+    fn test_dkg_serde_omits_session_keypair() {
+        let dkg = setup_finished_dkgs(2, 6, &[1, 2, 3]).remove(0);
+        let validators = dkg.external_validators();
+        let decryption_key =
+            hex::encode(canonical_bytes(&dkg.session_keypair.decryption_key));
+        let json = serde_json::to_string(&dkg).expect("Test failed");
+        assert!(!json.contains(&decryption_key));
+        assert!(!json.contains("session_keypair"));
+        let bytes = bincode::serialize(&dkg).expect("Test failed");
+        let key_bytes = canonical_bytes(&dkg.session_keypair.decryption_key);
+        assert!(!bytes.windows(key_bytes.len()).any(|w| w == key_bytes));
+
+        let restore = |session_keypair| RestoreState {
+            params: &dkg.params,
+            validators: &validators,
+            session_keypair,
+        };
+        let json = serde_json::to_value(&dkg).expect("Test failed");
+        assert!(restore(None).deserialize(&json).is_err());
+        assert!(restore(Some(gen_n_keypairs(3).remove(1)))
+            .deserialize(&json)
+            .is_err());
+        // the state must belong to the session
+        let mut params = dkg.params;
+        params.tau += 1;
+        assert!(RestoreState {
+            params: &params,
+            validators: &validators,
+            session_keypair: Some(gen_n_keypairs(3).remove(0)),
+        }
+        .deserialize(&json)
+        .is_err());
+    }
+
+    /// Test that DKG messages round trip losslessly through JSON, with
+    /// named fields
+    #[test]
+    fn test_message_json_round_trip() {
+        let rng = &mut ark_std::test_rng();
+        let deal = setup_dkg(0).share(rng).expect("Test failed");
+        let aggregate = setup_dealt_dkg().aggregate().expect("Test failed");
+        for msg in [deal, aggregate] {
+            let json = serde_json::to_string(&msg).expect("Test failed");
+            let restored: Message<EllipticCurve> =
+                serde_json::from_str(&json).expect("Test failed");
+            assert_eq!(
+                bincode::serialize(&restored).expect("Test failed"),
+                bincode::serialize(&msg).expect("Test failed")
+            );
+        }
+
+        let msg = setup_dealt_dkg().aggregate().expect("Test failed");
+        let json = serde_json::to_value(&msg).expect("Test failed");
+        let aggregation = &json["Aggregate"];
+        assert!(aggregation["final_key"].is_string());
+        assert!(aggregation["vss"]["sigma"].is_string());
+        assert!(aggregation["vss"]["shares"][0][0].is_string());
+    }
+}
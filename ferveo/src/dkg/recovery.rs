@@ -0,0 +1,756 @@
+use crate::*;
+use ark_serialize::*;
+use ferveo_common::{ExternalValidator, Keypair, PublicKey};
+use group_threshold_cryptography::{
+    lagrange_basis_at, make_random_polynomial_at,
+};
+use itertools::Itertools;
+use std::collections::BTreeMap;
+
+/// The state of the recovery of the key shares of a validator that lost
+/// its session key.
+///
+/// The recovery runs in two rounds. First, the other validators post mask
+/// transcripts: for each lost share at point `\omega_r`, a PVSS instance of
+/// a random polynomial `d(x)` with `d(\omega_r) = 0`. Once the masks reach
+/// the security threshold, each helper decrypts its own key shares with
+/// the masks added, `Z_j + h^{\sum d(\omega_j)}`, and encrypts them to the
+/// new session key. The recovering validator interpolates these at
+/// `\omega_r` to rebuild its key share, without learning any other share.
+/// Finally, it posts its key shares encrypted to its new session key,
+/// which re-keys the validator: its shares of the aggregated transcript
+/// are encrypted to the new key from then on, see [`Rekeyed`].
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+#[serde(bound = "")]
+pub struct Recovery<E: PairingEngine> {
+    /// The position of the recovering validator
+    pub validator: u32,
+    /// The new session public key of the recovering validator
+    pub public_key: PublicKey<E>,
+    /// The mask transcripts of each helper, one per lost share
+    pub masks: BTreeMap<u32, Vec<Pvss<E>>>,
+    /// The masked key shares of each helper encrypted to `public_key`,
+    /// for each lost share, one per key share of the helper
+    pub shares: BTreeMap<u32, Vec<Vec<KeyShareCiphertext<E>>>>,
+}
+
+/// The session key registered by a validator that recovered its key
+/// shares, which replaces its key in the validator set for encrypting and
+/// verifying shares. The validator set itself is left as it is, since it
+/// identifies the session
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+#[serde(bound = "")]
+pub struct Rekeyed<E: PairingEngine> {
+    /// The new session public key of the validator
+    pub public_key: PublicKey<E>,
+    /// The difference between the key shares of the validator encrypted
+    /// to `public_key` and to its key in the validator set, added to its
+    /// shares of the aggregated transcript
+    #[serde(with = "ferveo_common::ark_serde::seq")]
+    pub shares: ShareEncryptions<E>,
+}
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// The session public key that the shares of validator `i` are
+    /// encrypted to: the key it registered by recovering its key shares,
+    /// if any, or else its key in the validator set
+    pub fn session_key(&self, i: usize) -> PublicKey<E> {
+        match self.rekeyed.get(&(i as u32)) {
+            Some(rekeyed) => rekeyed.public_key,
+            None => self.validators[i].validator.public_key,
+        }
+    }
+
+    /// The number of key shares needed to interpolate a lost share
+    fn recovery_threshold(&self) -> usize {
+        self.params.decryption_threshold() as usize
+    }
+
+    /// The total weight of the given validators
+    fn weight_of<'a>(&self, validators: impl Iterator<Item = &'a u32>) -> u32 {
+        validators
+            .map(|v| self.validators[*v as usize].weight)
+            .sum::<u32>()
+    }
+
+    /// Returns if enough masks have been posted for helpers to
+    /// post their masked key shares
+    fn recovery_masked(&self, recovery: &Recovery<E>) -> bool {
+        self.weight_of(recovery.masks.keys()) >= self.params.degree()
+    }
+
+    /// Returns if enough masked key shares have been posted for the
+    /// recovering validator to rebuild its key shares
+    pub fn recovery_complete(&self) -> bool {
+        match &self.recovery {
+            Some(recovery) => {
+                self.weight_of(recovery.shares.keys()) as usize
+                    >= self.recovery_threshold()
+            }
+            None => false,
+        }
+    }
+
+    /// Request the recovery of our key shares after losing our session key
+    /// `keypair` is our new session keypair
+    /// `rng` is a cryptographic random number generator
+    /// Returns a recovery request message to post on-chain
+    pub fn request_recovery<R: Rng>(
+        &self,
+        keypair: &Keypair<E>,
+        rng: &mut R,
+    ) -> Result<Message<E>> {
+        self.check_participant(DkgOperation::Recover)?;
+        let address = &self.validators[self.me].validator.address;
+        match self.state {
+            DkgState::Success { .. } => Ok(Message::RecoveryRequest(
+                keypair.public(),
+                keypair.prove_possession(address, rng),
+            )),
+            _ => Err(DkgError::WrongState(DkgOperation::Recover)),
+        }
+    }
+
+    /// Create our mask transcripts for the recovery in progress
+    /// `rng` is a cryptographic random number generator
+    /// Returns a recovery mask message to post on-chain
+    pub fn recovery_mask<R: Rng>(&self, rng: &mut R) -> Result<Message<E>> {
+        self.check_participant(DkgOperation::HelpRecovery)?;
+        let recovery = self.recovery.as_ref().ok_or(DkgError::NoRecovery)?;
+        let lost = &self.validators[recovery.validator as usize];
+        let masks = (lost.share_start..lost.share_end)
+            .map(|share_index| {
+                let d = make_random_polynomial_at::<E>(
+                    self.recovery_threshold(),
+                    &self.domain.element(share_index),
+                    rng,
+                );
+                Pvss::<E>::from_polynomial(&d, self)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Message::RecoveryMask(masks))
+    }
+
+    /// Decrypt our key shares with the recovery masks added, and encrypt
+    /// them to the new session key of the recovering validator
+    /// `rng` is a cryptographic random number generator
+    /// Returns a recovery shares message to post on-chain
+    pub fn recovery_shares<R: Rng>(&self, rng: &mut R) -> Result<Message<E>> {
+        self.check_participant(DkgOperation::HelpRecovery)?;
+        let recovery = self.recovery.as_ref().ok_or(DkgError::NoRecovery)?;
+        if !self.recovery_masked(recovery) {
+            return Err(DkgError::InsufficientRecoveryMasks);
+        }
+        let decryption_key = self
+            .session_keypair
+            .decryption_key
+            .inverse()
+            .ok_or(DkgError::ZeroSessionKey)?;
+        let key_shares = &aggregate_for_decryption(self)[self.me];
+        let ek = recovery.public_key.encryption_key;
+        let shares = (0..self.validators[recovery.validator as usize].weight)
+            .map(|lost_share| {
+                key_shares
+                    .iter()
+                    .enumerate()
+                    .map(|(k, y)| {
+                        // Y_j + \sum_i Y_{i,j}, our encrypted masked share
+                        let masked = recovery.masks.values().fold(
+                            y.into_projective(),
+                            |acc, masks| {
+                                acc + masks[lost_share as usize].shares[self.me]
+                                    [k]
+                                    .into_projective()
+                            },
+                        );
+                        KeyShareCiphertext::new(
+                            masked.into_affine().mul(decryption_key),
+                            ek,
+                            rng,
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Message::RecoveryShares(shares))
+    }
+
+    /// Rebuild our key shares from the masked key shares posted by the
+    /// other validators, using the `keypair` of our new session key
+    pub fn recover_shares(
+        &self,
+        keypair: &Keypair<E>,
+    ) -> Result<Vec<E::G2Affine>> {
+        self.check_participant(DkgOperation::Recover)?;
+        let recovery = self.recovery.as_ref().ok_or(DkgError::NoRecovery)?;
+        if keypair.public() != recovery.public_key {
+            return Err(DkgError::RecoveryKeypairMismatch);
+        }
+        if !self.recovery_complete() {
+            return Err(DkgError::InsufficientRecoveryShares);
+        }
+        let public_key_shares = self.public_key_shares()?;
+        let lost = &self.validators[recovery.validator as usize];
+        (lost.share_start..lost.share_end)
+            .enumerate()
+            .map(|(lost_share, share_index)| {
+                // the points and values of the masked polynomial
+                let (shares_x, shares_y): (Vec<_>, Vec<_>) = recovery
+                    .shares
+                    .iter()
+                    .flat_map(|(helper, shares)| {
+                        let helper = &self.validators[*helper as usize];
+                        (helper.share_start..helper.share_end)
+                            .zip(shares[lost_share].iter())
+                    })
+                    .map(|(s, share)| {
+                        (self.domain.element(s), share.decrypt(keypair))
+                    })
+                    .unzip();
+                let lagrange = lagrange_basis_at::<E>(
+                    &shares_x,
+                    &self.domain.element(share_index),
+                );
+                let z_r = shares_y
+                    .iter()
+                    .zip_eq(lagrange.iter())
+                    .map(|(z, l)| z.mul(*l))
+                    .sum::<E::G2Projective>()
+                    .into_affine();
+                // e(g, Z_r) = e(A_r, h)
+                if E::pairing(self.pvss_params.g, z_r)
+                    != E::pairing(
+                        public_key_shares[share_index],
+                        self.pvss_params.h,
+                    )
+                {
+                    return Err(DkgError::InvalidRecoveredShare(share_index));
+                }
+                Ok(z_r)
+            })
+            .collect()
+    }
+
+    /// Register our new session key once our key shares are recovered, by
+    /// encrypting the recovered key shares to it, `Y_r = Z_r^{dk}`
+    /// `keypair` is our new session keypair
+    /// Returns a recovery completion message to post on-chain
+    ///
+    /// Once the message is applied, `keypair` replaces our `session_keypair`
+    pub fn complete_recovery(
+        &mut self,
+        keypair: &Keypair<E>,
+    ) -> Result<Message<E>> {
+        let shares = self.recover_shares(keypair)?;
+        self.recovery_keypair = Some(*keypair);
+        Ok(Message::RecoveryComplete(
+            shares
+                .iter()
+                .map(|z| z.mul(keypair.decryption_key).into_affine())
+                .collect(),
+        ))
+    }
+
+    /// Verify a recovery message from `sender`
+    pub(crate) fn verify_recovery(
+        &self,
+        sender: &ExternalValidator<E>,
+        payload: &Message<E>,
+    ) -> Result<()> {
+        let sender = self
+            .validators
+            .iter()
+            .position(|probe| sender == &probe.validator)
+            .ok_or_else(|| {
+                DkgError::UnknownValidator(sender.address.clone())
+            })?;
+        match (payload, &self.recovery) {
+            (Message::RecoveryRequest(public_key, proof), _) => {
+                if let Some(recovery) = &self.recovery {
+                    Err(DkgError::RecoveryInProgress(recovery.validator))
+                } else if public_key == &self.session_key(sender) {
+                    Err(DkgError::RecoveryKeyReused(sender as u32))
+                } else if !public_key.verify_possession(
+                    &self.validators[sender].validator.address,
+                    proof,
+                ) {
+                    Err(DkgError::InvalidProofOfPossession(
+                        self.validators[sender].validator.address.clone(),
+                    ))
+                } else {
+                    Ok(())
+                }
+            }
+            (Message::RecoveryMask(masks), Some(recovery)) => {
+                let lost = &self.validators[recovery.validator as usize];
+                if sender == recovery.validator as usize {
+                    Err(DkgError::RecoveringValidatorHelps(sender as u32))
+                } else if recovery.masks.contains_key(&(sender as u32)) {
+                    Err(DkgError::DuplicateRecoveryMask(sender as u32))
+                } else if self.recovery_masked(recovery) {
+                    Err(DkgError::RecoveryMasksComplete(sender as u32))
+                } else if masks.len() != lost.weight as usize
+                    || !masks.iter().zip(lost.share_start..lost.share_end).all(
+                        |(mask, share_index)| {
+                            self.verify_recovery_mask(mask, share_index)
+                        },
+                    )
+                {
+                    Err(DkgError::InvalidRecoveryMask(sender as u32))
+                } else {
+                    Ok(())
+                }
+            }
+            (Message::RecoveryShares(shares), Some(recovery)) => {
+                if sender == recovery.validator as usize {
+                    Err(DkgError::RecoveringValidatorHelps(sender as u32))
+                } else if recovery.shares.contains_key(&(sender as u32)) {
+                    Err(DkgError::DuplicateRecoveryShares(sender as u32))
+                } else if !self.recovery_masked(recovery) {
+                    Err(DkgError::InsufficientRecoveryMasks)
+                } else if !self.verify_recovery_shares(recovery, sender, shares)
+                {
+                    Err(DkgError::InvalidRecoveryShares(sender as u32))
+                } else {
+                    Ok(())
+                }
+            }
+            (Message::RecoveryComplete(shares), Some(recovery)) => {
+                if sender != recovery.validator as usize {
+                    Err(DkgError::RecoveryCompletedByOther(sender as u32))
+                } else if !self.recovery_complete() {
+                    Err(DkgError::InsufficientRecoveryShares)
+                } else if !self.verify_recovered_shares(recovery, shares) {
+                    Err(DkgError::InvalidRecoveredShares(sender as u32))
+                } else {
+                    Ok(())
+                }
+            }
+            _ => Err(DkgError::NoRecovery),
+        }
+    }
+
+    /// Check that `mask` shares a polynomial vanishing at the point of
+    /// share `share_index`, and that its shares match its commitment
+    fn verify_recovery_mask(&self, mask: &Pvss<E>, share_index: usize) -> bool {
+        let omega = self.domain.element(share_index);
+        // D(\omega_r) = \sum_i D_i \omega_r^i
+        let mut power = E::Fr::one();
+        let mut d_r = E::G1Projective::zero();
+        for coeff in mask.coeffs.iter() {
+            d_r += coeff.mul(power);
+            power *= omega;
+        }
+        mask.coeffs.len() == self.recovery_threshold()
+            && d_r.is_zero()
+            && mask.verify_full(self)
+    }
+
+    /// Check the masked key shares posted by `helper` against the
+    /// commitments of the aggregated transcript and of the masks
+    fn verify_recovery_shares(
+        &self,
+        recovery: &Recovery<E>,
+        helper: usize,
+        shares: &[Vec<KeyShareCiphertext<E>>],
+    ) -> bool {
+        let public_key_shares = match self.public_key_shares() {
+            Ok(public_key_shares) => public_key_shares,
+            Err(_) => return false,
+        };
+        let helper = &self.validators[helper];
+        let ek = recovery.public_key.encryption_key;
+        shares.len()
+            == self.validators[recovery.validator as usize].weight as usize
+            && shares.iter().enumerate().all(|(lost_share, shares)| {
+                // the commitment to the sum of the masks of this lost share
+                let mut mask =
+                    vec![E::G1Projective::zero(); self.recovery_threshold()];
+                for masks in recovery.masks.values() {
+                    mask.iter_mut()
+                        .zip_eq(masks[lost_share].coeffs.iter())
+                        .for_each(|(a, b)| *a += b.into_projective());
+                }
+                self.domain.fft_in_place(&mut mask);
+                shares.len() == helper.weight as usize
+                    && shares
+                        .iter()
+                        .zip(helper.share_start..helper.share_end)
+                        .all(|(share, s)| {
+                            let a = public_key_shares[s].into_projective()
+                                + mask[s];
+                            share.verify(a.into_affine(), ek)
+                        })
+            })
+    }
+
+    /// Check the key shares of the recovering validator encrypted to its
+    /// new session key against the aggregated commitment,
+    /// e(g, Y_r) = e(A_r, ek)
+    fn verify_recovered_shares(
+        &self,
+        recovery: &Recovery<E>,
+        shares: &[E::G2Affine],
+    ) -> bool {
+        let public_key_shares = match self.public_key_shares() {
+            Ok(public_key_shares) => public_key_shares,
+            Err(_) => return false,
+        };
+        let lost = &self.validators[recovery.validator as usize];
+        let ek = recovery.public_key.encryption_key;
+        shares.len() == lost.weight as usize
+            && (lost.share_start..lost.share_end).zip(shares).all(
+                |(share_index, y)| {
+                    E::pairing(self.pvss_params.g, *y)
+                        == E::pairing(public_key_shares[share_index], ek)
+                },
+            )
+    }
+
+    /// Apply a verified recovery message from `sender`
+    pub(crate) fn apply_recovery(
+        &mut self,
+        sender: &ExternalValidator<E>,
+        payload: Message<E>,
+    ) -> Result<()> {
+        let sender = self
+            .validators
+            .iter()
+            .position(|probe| sender == &probe.validator)
+            .ok_or_else(|| DkgError::UnknownValidator(sender.address.clone()))?
+            as u32;
+        match (payload, &mut self.recovery) {
+            (Message::RecoveryRequest(public_key, _), _) => {
+                self.recovery = Some(Recovery {
+                    validator: sender,
+                    public_key,
+                    masks: BTreeMap::new(),
+                    shares: BTreeMap::new(),
+                });
+                Ok(())
+            }
+            (Message::RecoveryMask(masks), Some(recovery)) => {
+                recovery.masks.insert(sender, masks);
+                Ok(())
+            }
+            (Message::RecoveryShares(shares), Some(recovery)) => {
+                recovery.shares.insert(sender, shares);
+                Ok(())
+            }
+            (Message::RecoveryComplete(shares), Some(recovery)) => {
+                let lost = recovery.validator;
+                let public_key = recovery.public_key;
+                // add the difference between the key shares encrypted to
+                // the new and to the lost key, so that the aggregated
+                // transcript holds the key shares encrypted to the new key
+                let encrypted_shares =
+                    aggregate_for_decryption(self).swap_remove(lost as usize);
+                let weight = self.validators[lost as usize].weight as usize;
+                let rekeyed =
+                    self.rekeyed.entry(lost).or_insert_with(|| Rekeyed {
+                        public_key,
+                        shares: vec![E::G2Affine::zero(); weight],
+                    });
+                rekeyed.public_key = public_key;
+                rekeyed
+                    .shares
+                    .iter_mut()
+                    .zip_eq(shares.iter().zip_eq(encrypted_shares.iter()))
+                    .for_each(|(a, (new, old))| {
+                        *a = (a.into_projective() + new.into_projective()
+                            - old.into_projective())
+                        .into_affine();
+                    });
+                if lost as usize == self.me {
+                    if let Some(keypair) = self.recovery_keypair.take() {
+                        if keypair.public() == public_key {
+                            self.session_keypair = keypair;
+                        }
+                    }
+                }
+                self.recovery = None;
+                Ok(())
+            }
+            _ => Err(DkgError::NoRecovery),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_recovery {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+    use group_threshold_cryptography as tpke;
+
+    type E = EllipticCurve;
+
+    /// Verify `message` from validator `sender` in every DKG and apply it
+    fn post(
+        dkgs: &mut [PubliclyVerifiableDkg<E>],
+        sender: usize,
+        message: Message<E>,
+    ) {
+        for dkg in dkgs.iter_mut() {
+            let validator = dkg.validators[sender].validator.clone();
+            dkg.verify_message(&validator, &message)
+                .expect("Test failed");
+            dkg.apply_message(validator, message.clone())
+                .expect("Test failed");
+        }
+    }
+
+    /// The key shares of validator `i`, decrypted with its session key
+    fn key_shares(
+        dkg: &PubliclyVerifiableDkg<E>,
+    ) -> Vec<<E as PairingEngine>::G2Affine> {
+        let decryption_key =
+            dkg.session_keypair.decryption_key.inverse().unwrap();
+        aggregate_for_decryption(dkg)[dkg.me]
+            .iter()
+            .map(|y| y.mul(decryption_key).into_affine())
+            .collect()
+    }
+
+    /// Run the recovery of validator `lost` to its new session key
+    /// `keypair` with the help of `helpers`
+    fn recover(
+        dkgs: &mut [PubliclyVerifiableDkg<E>],
+        lost: usize,
+        keypair: &Keypair<E>,
+        helpers: &[usize],
+    ) -> Vec<<E as PairingEngine>::G2Affine> {
+        let rng = &mut ark_std::test_rng();
+        let request = dkgs[lost]
+            .request_recovery(keypair, rng)
+            .expect("Test failed");
+        post(dkgs, lost, request);
+
+        for helper in helpers.iter() {
+            if dkgs[0].recovery_masked(dkgs[0].recovery.as_ref().unwrap()) {
+                break;
+            }
+            let mask = dkgs[*helper].recovery_mask(rng).expect("Test failed");
+            post(dkgs, *helper, mask);
+        }
+        for helper in helpers.iter() {
+            if dkgs[0].recovery_complete() {
+                break;
+            }
+            let shares =
+                dkgs[*helper].recovery_shares(rng).expect("Test failed");
+            post(dkgs, *helper, shares);
+        }
+        dkgs[lost].recover_shares(keypair).expect("Test failed")
+    }
+
+    /// Test that a validator recovers its key shares after
+    /// registering a new session key
+    #[test]
+    fn test_recovery() {
+        let mut dkgs = setup_finished_dkgs(2, 4, &[1, 1, 1, 1]);
+        let expected = key_shares(&dkgs[1]);
+        let keypair = Keypair::<E>::new(&mut ark_std::test_rng());
+        let recovered = recover(&mut dkgs, 1, &keypair, &[0, 2, 3]);
+        assert_eq!(recovered, expected);
+    }
+
+    /// Test that a weighted validator recovers all of its key shares
+    #[test]
+    fn test_recovery_weighted() {
+        let mut dkgs = setup_finished_dkgs(3, 6, &[1, 2, 3]);
+        let expected = key_shares(&dkgs[1]);
+        assert_eq!(expected.len(), 2);
+        let keypair = Keypair::<E>::new(&mut ark_std::test_rng());
+        let recovered = recover(&mut dkgs, 1, &keypair, &[2, 0]);
+        assert_eq!(recovered, expected);
+    }
+
+    /// Test that completing a recovery re-keys the recovered validator
+    /// without changing the session, and that it builds its decryption
+    /// contexts with its new session key and decrypts together with the
+    /// other validators, also after a refresh
+    #[test]
+    fn test_recovery_registers_new_key() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkgs = setup_finished_dkgs(3, 6, &[1, 2, 3]);
+        let final_key = dkgs[0].final_key();
+        let validators = dkgs[0].external_validators();
+        let keypair = Keypair::<E>::new(rng);
+        let expected = key_shares(&dkgs[1]);
+        recover(&mut dkgs, 1, &keypair, &[2, 0]);
+
+        // the completion is only accepted from the recovering validator,
+        // with its key shares encrypted to its new key
+        let complete =
+            dkgs[1].complete_recovery(&keypair).expect("Test failed");
+        let other = dkgs[0].validators[0].validator.clone();
+        assert!(dkgs[0].verify_message(&other, &complete).is_err());
+        let mut tampered = complete.clone();
+        if let Message::RecoveryComplete(shares) = &mut tampered {
+            shares[0] = expected[0];
+        }
+        let lost = dkgs[0].validators[1].validator.clone();
+        assert!(matches!(
+            dkgs[0].verify_message(&lost, &tampered),
+            Err(DkgError::InvalidRecoveredShares(1))
+        ));
+        post(&mut dkgs, 1, complete);
+        assert_eq!(dkgs[1].session_keypair.public(), keypair.public());
+        assert!(dkgs[1].recovery_keypair.is_none());
+        for dkg in dkgs.iter() {
+            assert_eq!(dkg.external_validators(), validators);
+            assert_eq!(dkg.session_key(1), keypair.public());
+            assert!(dkg.recovery.is_none());
+            assert!(dkg.refresh.is_none());
+            assert_eq!(dkg.final_key(), final_key);
+            let snapshot = dkg.save_snapshot().expect("Test failed");
+            PubliclyVerifiableDkg::<E>::load_snapshot(
+                &snapshot,
+                &dkg.params,
+                &validators,
+            )
+            .expect("Test failed");
+        }
+        assert_eq!(key_shares(&dkgs[1]), expected);
+
+        // refresh transcripts deal to the new key of the recovered validator
+        for i in 0..dkgs.len() {
+            let refresh = dkgs[i].refresh(rng).expect("Test failed");
+            post(&mut dkgs, i, refresh);
+        }
+        assert!(aggregate(&dkgs[0]).verify_full(&dkgs[0]));
+        assert_ne!(key_shares(&dkgs[1]), expected);
+        dkgs[0]
+            .audit_transcript()
+            .expect("Test failed")
+            .verify(rng)
+            .expect("Test failed");
+
+        let mut keypairs = gen_n_keypairs(3);
+        keypairs[1] = keypair;
+        let msg: &[u8] = "abc".as_bytes();
+        let aad: &[u8] = "my-aad".as_bytes();
+        let ciphertext = tpke::encrypt::<_, E>(msg, aad, &final_key, rng);
+        let mut contexts = vec![];
+        for (dkg, keypair) in dkgs.iter().zip(keypairs.iter()) {
+            let (private, _) =
+                dkg.decryption_contexts_fast(keypair).expect("Test failed");
+            contexts.extend(private);
+        }
+        let shares = contexts
+            .iter()
+            .map(|c| c.create_share(&ciphertext))
+            .collect::<Vec<_>>();
+        assert!(contexts[0].batch_verify_decryption_shares(
+            std::slice::from_ref(&ciphertext),
+            std::slice::from_ref(&shares),
+            rng
+        ));
+        let public_contexts = &contexts[0].public_decryption_contexts;
+        let prepared_key_shares =
+            tpke::prepare_combine_fast(public_contexts, &shares);
+        let shared_secret =
+            tpke::share_combine_fast(&shares, &prepared_key_shares);
+        let plaintext = tpke::checked_decrypt_with_shared_secret(
+            &ciphertext,
+            aad,
+            &shared_secret,
+        )
+        .expect("Test failed");
+        assert_eq!(plaintext, msg);
+    }
+
+    /// Test that invalid recovery messages are rejected
+    #[test]
+    fn test_recovery_invalid_messages_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkgs = setup_finished_dkgs(2, 4, &[1, 1, 1, 1]);
+        let keypairs = gen_n_keypairs(4);
+        let keypair = ferveo_common::Keypair::<E>::new(rng);
+
+        // masks and shares are rejected before a recovery is requested
+        let mask = Message::RecoveryMask(vec![]);
+        let sender = dkgs[0].validators[0].validator.clone();
+        assert!(dkgs[0].verify_message(&sender, &mask).is_err());
+        assert!(dkgs[0].recovery_mask(rng).is_err());
+
+        // the recovering validator must register a new key
+        let lost = dkgs[1].validators[1].validator.clone();
+        let stale = Message::RecoveryRequest(
+            lost.public_key,
+            keypairs[1].prove_possession(&lost.address, rng),
+        );
+        assert!(dkgs[1].verify_message(&lost, &stale).is_err());
+        // the new key must come with a proof of possession by its owner
+        let other = dkgs[0].validators[2].validator.clone();
+        let stolen = Message::RecoveryRequest(
+            keypair.public(),
+            keypair.prove_possession(&other.address, rng),
+        );
+        assert!(matches!(
+            dkgs[1].verify_message(&lost, &stolen),
+            Err(DkgError::InvalidProofOfPossession(_))
+        ));
+        let request = dkgs[1]
+            .request_recovery(&keypair, rng)
+            .expect("Test failed");
+        post(&mut dkgs, 1, request.clone());
+        // only one recovery at a time
+        assert!(dkgs[0].verify_message(&other, &request).is_err());
+
+        // a mask that does not vanish at the lost share is rejected
+        let mut bad =
+            Pvss::<E>::new_refresh(&dkgs[0], rng).expect("Test failed");
+        let err = dkgs[0]
+            .verify_message(&sender, &Message::RecoveryMask(vec![bad.clone()]))
+            .expect_err("Test failed");
+        assert!(matches!(err, DkgError::InvalidRecoveryMask(0)));
+        bad.coeffs.clear();
+        assert!(dkgs[0]
+            .verify_message(&sender, &Message::RecoveryMask(vec![bad]))
+            .is_err());
+
+        // the recovering validator can not mask its own recovery
+        let mask = dkgs[1].recovery_mask(rng).expect("Test failed");
+        assert!(dkgs[0].verify_message(&lost, &mask).is_err());
+
+        // shares are rejected until enough masks are posted
+        assert!(dkgs[0].recovery_shares(rng).is_err());
+        for helper in [0, 2] {
+            let mask = dkgs[helper].recovery_mask(rng).expect("Test failed");
+            post(&mut dkgs, helper, mask);
+        }
+
+        // tampered shares are rejected
+        let shares = dkgs[0].recovery_shares(rng).expect("Test failed");
+        let mut tampered = shares.clone();
+        if let Message::RecoveryShares(shares) = &mut tampered {
+            shares[0][0].masked = shares[0][0].masked
+                + <E as PairingEngine>::G2Affine::prime_subgroup_generator();
+        }
+        let err = dkgs[0]
+            .verify_message(&sender, &tampered)
+            .expect_err("Test failed");
+        assert!(matches!(err, DkgError::InvalidRecoveryShares(0)));
+        assert!(dkgs[0].verify_message(&sender, &shares).is_ok());
+
+        // the shares can not be recovered with another key
+        post(&mut dkgs, 0, shares);
+        assert!(dkgs[1].recover_shares(&dkgs[1].session_keypair).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+use crate::*;
+use ferveo_common::ExternalValidator;
+use itertools::Itertools;
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// Create a refresh transcript, a PVSS instance of a random polynomial
+    /// with a zero constant term. Once applied, it re-randomizes the key
+    /// shares of every validator while the final key stays the same.
+    /// `rng` is a cryptographic random number generator
+    /// Returns a refresh message to post on-chain
+    pub fn refresh<R: Rng>(&self, rng: &mut R) -> Result<Message<E>> {
+        print_time!("PVSS Refresh");
+        self.check_participant(DkgOperation::Refresh)?;
+        match self.state {
+            DkgState::Success { .. } => {
+                Ok(Message::Refresh(Pvss::<E>::new_refresh(self, rng)?))
+            }
+            _ => Err(DkgError::WrongState(DkgOperation::Refresh)),
+        }
+    }
+
+    /// Start a new refresh round, in which every validator may refresh
+    /// once more. Rounds are started by the application, for instance
+    /// every given number of blocks, and not when every validator has
+    /// refreshed, so that an offline validator does not block the refresh
+    /// of the others. The refresh transcripts of earlier rounds are
+    /// rejected from then on
+    pub fn start_refresh_round(&mut self) -> Result<()> {
+        match self.state {
+            DkgState::Success { .. } => {
+                self.refresh_round += 1;
+                self.refreshed.clear();
+                Ok(())
+            }
+            _ => Err(DkgError::WrongState(DkgOperation::Refresh)),
+        }
+    }
+
+    /// Verify a refresh transcript from `sender`: the constant term of the
+    /// polynomial must be the identity, the proof of knowledge must bind
+    /// it to `sender` and the current refresh round, and every share must
+    /// match the commitment. Each validator refreshes at most once per
+    /// refresh round
+    pub(crate) fn verify_refresh(
+        &self,
+        sender: &ExternalValidator<E>,
+        pvss: &Pvss<E>,
+    ) -> Result<()> {
+        let dealer = sender;
+        let sender = self
+            .validators
+            .iter()
+            .position(|probe| sender == &probe.validator)
+            .ok_or_else(|| DkgError::UnknownValidator(sender.address.clone()))?
+            as u32;
+        let degree = self.params.degree() as usize;
+        if self.refreshed.contains(&sender) {
+            Err(DkgError::DuplicateRefresh(sender))
+        } else if self.recovery.is_some() {
+            // the recovery relies on the key shares staying the same
+            Err(DkgError::RefreshDuringRecovery(sender))
+        } else if pvss.coeffs.len() != degree + 1 {
+            Err(DkgError::MalformedRefresh(sender))
+        } else if !pvss.coeffs[0].is_zero() {
+            Err(DkgError::RefreshChangesKey(sender))
+        } else if !verify_refresh_pok(
+            dealer,
+            self.params.tau,
+            self.refresh_round,
+            &pvss.coeffs,
+            pvss.sigma,
+        ) || !pvss.verify_full(self)
+        {
+            Err(DkgError::InvalidRefresh(sender))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Add a verified refresh transcript from `sender` to the
+    /// refresh of the aggregated transcript
+    pub(crate) fn apply_refresh(
+        &mut self,
+        sender: &ExternalValidator<E>,
+        pvss: Pvss<E>,
+    ) -> Result<()> {
+        let sender = self
+            .validators
+            .iter()
+            .position(|probe| sender == &probe.validator)
+            .ok_or_else(|| DkgError::UnknownValidator(sender.address.clone()))?
+            as u32;
+        if !self.refreshed.insert(sender) {
+            return Err(DkgError::DuplicateRefresh(sender));
+        }
+        self.refresh = Some(match self.refresh.take() {
+            Some(mut refresh) => {
+                refresh
+                    .coeffs
+                    .iter_mut()
+                    .zip_eq(pvss.coeffs.iter())
+                    .for_each(|(a, b)| *a = *a + *b);
+                refresh
+                    .shares
+                    .iter_mut()
+                    .zip_eq(pvss.shares.iter())
+                    .for_each(|(a, b)| {
+                        a.iter_mut().zip_eq(b.iter()).for_each(|(a, b)| {
+                            *a = *a + *b;
+                        })
+                    });
+                refresh.sigma = refresh.sigma + pvss.sigma;
+                refresh
+            }
+            None => pvss,
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_refresh {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+    use group_threshold_cryptography as tpke;
+
+    type E = EllipticCurve;
+    type Fr = <E as PairingEngine>::Fr;
+    type G2 = <E as PairingEngine>::G2Affine;
+
+    /// Create a dkg in state [`DkgState::Success`]
+    fn setup_finished_dkg() -> PubliclyVerifiableDkg<E> {
+        let mut dkg = setup_dealt_dkg();
+        let aggregate = dkg.aggregate().expect("Test failed");
+        let sender = dkg.validators[dkg.me].validator.clone();
+        dkg.apply_message(sender, aggregate).expect("Test failed");
+        dkg
+    }
+
+    /// Test that a refresh round changes the key shares of every
+    /// validator while preserving the final key, and that ciphertexts
+    /// can still be decrypted with the refreshed shares
+    #[test]
+    fn test_refresh_preserves_final_key() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkgs = setup_finished_dkgs(2, 4, &[1, 1, 1, 1]);
+        let final_key = dkgs[0].final_key();
+        let shares = aggregate_for_decryption(&dkgs[0]);
+
+        for i in 0..dkgs.len() {
+            let refresh = dkgs[i].refresh(rng).expect("Test failed");
+            for dkg in dkgs.iter_mut() {
+                let sender = dkg.validators[i].validator.clone();
+                assert!(dkg.verify_message(&sender, &refresh).is_ok());
+                assert!(dkg.apply_message(sender, refresh.clone()).is_ok());
+            }
+        }
+        let dkg = &dkgs[0];
+        assert!(matches!(dkg.state, DkgState::Success { .. }));
+        assert_eq!(dkg.final_key(), final_key);
+
+        // the aggregated transcript is refreshed and still consistent
+        let aggregate = aggregate(dkg);
+        assert_eq!(aggregate.coeffs[0], final_key);
+        assert!(aggregate.verify_full(dkg));
+        let refreshed = aggregate_for_decryption(dkg);
+        assert_eq!(refreshed, aggregate.shares);
+        shares.iter().zip(refreshed.iter()).for_each(|(a, b)| {
+            a.iter().zip(b.iter()).for_each(|(a, b)| assert_ne!(a, b))
+        });
+
+        let msg: &[u8] = "abc".as_bytes();
+        let aad: &[u8] = "my-aad".as_bytes();
+        let ciphertext = tpke::encrypt::<_, E>(msg, aad, &final_key, rng);
+        let decryption_shares =
+            make_decryption_shares(&ciphertext, gen_keypairs(), refreshed);
+        let shares_x = &dkg.domain.elements().collect::<Vec<_>>();
+        let lagrange_coeffs = tpke::prepare_combine_simple::<E>(shares_x);
+        let shared_secret = tpke::share_combine_simple::<E>(
+            &decryption_shares,
+            &lagrange_coeffs,
+        );
+        let plaintext = tpke::checked_decrypt_with_shared_secret(
+            &ciphertext,
+            aad,
+            &shared_secret,
+        )
+        .expect("Test failed");
+        assert_eq!(plaintext, msg);
+    }
+
+    /// Test that a refresh transcript that would change
+    /// the final key is rejected
+    #[test]
+    fn test_refresh_nonzero_constant_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_finished_dkg();
+        let sender = dkg.validators[0].validator.clone();
+        let pvss =
+            Pvss::<E>::new(&Fr::rand(rng), &dkg, rng).expect("Test failed");
+        let err = dkg
+            .verify_message(&sender, &Message::Refresh(pvss))
+            .expect_err("Test failed");
+        assert!(matches!(err, DkgError::RefreshChangesKey(0)));
+    }
+
+    /// Test that a refresh transcript with a share that
+    /// does not match the commitment is rejected
+    #[test]
+    fn test_refresh_invalid_share_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_finished_dkg();
+        let sender = dkg.validators[0].validator.clone();
+        let mut pvss = Pvss::<E>::new_refresh(&dkg, rng).expect("Test failed");
+        pvss.shares[1][0] = G2::prime_subgroup_generator();
+        let err = dkg
+            .verify_message(&sender, &Message::Refresh(pvss.clone()))
+            .expect_err("Test failed");
+        assert!(matches!(err, DkgError::InvalidRefresh(0)));
+
+        pvss.shares[1].push(G2::prime_subgroup_generator());
+        assert!(dkg
+            .verify_message(&sender, &Message::Refresh(pvss))
+            .is_err());
+    }
+
+    /// Test that a validator can refresh only once per refresh round, and
+    /// again in the next round, which starts without waiting for the other
+    /// validators to refresh
+    #[test]
+    fn test_refresh_once_per_round() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_finished_dkg();
+        let sender = dkg.validators[0].validator.clone();
+        let refresh = dkg.refresh(rng).expect("Test failed");
+        dkg.apply_message(sender.clone(), refresh)
+            .expect("Test failed");
+
+        let refresh = dkg.refresh(rng).expect("Test failed");
+        assert!(matches!(
+            dkg.verify_message(&sender, &refresh),
+            Err(DkgError::DuplicateRefresh(0))
+        ));
+        assert!(matches!(
+            dkg.apply_message(sender.clone(), refresh.clone()),
+            Err(DkgError::DuplicateRefresh(0))
+        ));
+
+        dkg.start_refresh_round().expect("Test failed");
+        assert_eq!(dkg.refresh_round, 1);
+        assert!(dkg.refreshed.is_empty());
+        // the transcript of the previous round is bound to it
+        assert!(matches!(
+            dkg.verify_message(&sender, &refresh),
+            Err(DkgError::InvalidRefresh(0))
+        ));
+        let refresh = dkg.refresh(rng).expect("Test failed");
+        dkg.verify_message(&sender, &refresh).expect("Test failed");
+        dkg.apply_message(sender, refresh).expect("Test failed");
+    }
+
+    /// Test that a refresh transcript re-posted by another validator, or
+    /// with a forged proof of knowledge, is rejected
+    #[test]
+    fn test_refresh_reposted_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_finished_dkg();
+        let refresh = dkg.refresh(rng).expect("Test failed");
+        let dealer = dkg.validators[0].validator.clone();
+        let other = dkg.validators[1].validator.clone();
+        assert!(dkg.verify_message(&dealer, &refresh).is_ok());
+        assert!(matches!(
+            dkg.verify_message(&other, &refresh),
+            Err(DkgError::InvalidRefresh(1))
+        ));
+
+        let mut forged = refresh;
+        if let Message::Refresh(pvss) = &mut forged {
+            pvss.sigma = G2::zero();
+        }
+        assert!(matches!(
+            dkg.verify_message(&dealer, &forged),
+            Err(DkgError::InvalidRefresh(0))
+        ));
+    }
+
+    /// Test that a refresh from a sender whose session key does not
+    /// match the validator set is rejected
+    #[test]
+    fn test_refresh_impersonated_sender_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_finished_dkg();
+        let mut sender = dkg.validators[0].validator.clone();
+        sender.public_key = dkg.validators[1].validator.public_key;
+        let refresh = dkg.refresh(rng).expect("Test failed");
+        assert!(matches!(
+            dkg.verify_message(&sender, &refresh),
+            Err(DkgError::UnknownValidator(_))
+        ));
+        assert!(matches!(
+            dkg.apply_message(sender, refresh),
+            Err(DkgError::UnknownValidator(_))
+        ));
+        assert!(dkg.refresh.is_none());
+    }
+
+    /// Test that refreshing is only possible once the DKG has succeeded
+    #[test]
+    fn test_refresh_requires_final_key() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dealt_dkg();
+        assert!(dkg.refresh(rng).is_err());
+        assert!(dkg.start_refresh_round().is_err());
+        let pvss = Pvss::<E>::new_refresh(&dkg, rng).expect("Test failed");
+        let sender = dkg.validators[0].validator.clone();
+        let refresh = Message::Refresh(pvss);
+        assert!(dkg.verify_message(&sender, &refresh).is_err());
+        assert!(dkg.apply_message(sender, refresh).is_err());
+    }
+}
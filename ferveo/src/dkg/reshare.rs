@@ -0,0 +1,765 @@
+use crate::*;
+use ark_serialize::*;
+use ferveo_common::{ExternalValidator, Keypair, ProofOfPossession};
+use group_threshold_cryptography::prepare_combine_simple;
+use itertools::Itertools;
+use std::collections::BTreeMap;
+use subproductdomain::fast_multiexp;
+
+/// An encryption of a G2 element `x` to the session key `ek = h^{dk}` of a
+/// validator, `(g^r, h^r, x + ek^r)`.
+///
+/// Unlike PVSS shares, which encrypt scalars in the exponent, these can
+/// carry key shares that are only known as group elements.
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    PartialEq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+#[serde(bound = "")]
+pub struct KeyShareCiphertext<E: PairingEngine> {
+    /// g^r, used to check the ciphertext against the commitment
+    #[serde(with = "ferveo_common::ark_serde")]
+    pub r_g: E::G1Affine,
+    /// h^r, used to decrypt the ciphertext
+    #[serde(with = "ferveo_common::ark_serde")]
+    pub r_h: E::G2Affine,
+    /// x + ek^r
+    #[serde(with = "ferveo_common::ark_serde")]
+    pub masked: E::G2Affine,
+}
+
+impl<E: PairingEngine> KeyShareCiphertext<E> {
+    /// Encrypt `x` to the session key `ek`
+    pub(crate) fn new<R: Rng>(
+        x: E::G2Projective,
+        ek: E::G2Affine,
+        rng: &mut R,
+    ) -> Self {
+        use ark_std::UniformRand;
+        let r = E::Fr::rand(rng);
+        Self {
+            r_g: E::G1Affine::prime_subgroup_generator().mul(r).into_affine(),
+            r_h: E::G2Affine::prime_subgroup_generator().mul(r).into_affine(),
+            masked: (x + ek.mul(r)).into_affine(),
+        }
+    }
+
+    /// Check that the ciphertext encrypts h^{a} to `ek`, where `a_g = g^{a}`
+    pub(crate) fn verify(&self, a_g: E::G1Affine, ek: E::G2Affine) -> bool {
+        let g = E::G1Affine::prime_subgroup_generator();
+        let h = E::G2Affine::prime_subgroup_generator();
+        // e(g, x + ek^r) = e(g^a, h) e(g^r, ek)
+        E::product_of_pairings(&[
+            ((-g).into(), self.masked.into()),
+            (a_g.into(), h.into()),
+            (self.r_g.into(), ek.into()),
+        ]) == E::Fqk::one()
+            // e(g^r, h) = e(g, h^r)
+            && E::product_of_pairings(&[
+                (self.r_g.into(), h.into()),
+                ((-g).into(), self.r_h.into()),
+            ]) == E::Fqk::one()
+    }
+
+    /// Decrypt the ciphertext with the session keypair it was encrypted to
+    pub fn decrypt(&self, keypair: &Keypair<E>) -> E::G2Affine {
+        (self.masked.into_projective() - self.r_h.mul(keypair.decryption_key))
+            .into_affine()
+    }
+
+    /// Scale the encrypted element by `lambda`
+    fn mul(
+        &self,
+        lambda: E::Fr,
+    ) -> (E::G1Projective, E::G2Projective, E::G2Projective) {
+        (
+            self.r_g.mul(lambda),
+            self.r_h.mul(lambda),
+            self.masked.mul(lambda),
+        )
+    }
+}
+
+/// A sharing of a G2 element among the validators of the next epoch,
+/// with a Feldman commitment `coeffs` to the sharing polynomial.
+///
+/// Dealt by an old validator for each of its key shares, and also the
+/// result of aggregating the dealings into the key shares of the new epoch
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Resharing<E: PairingEngine> {
+    /// Feldman commitment to the sharing polynomial
+    pub coeffs: Vec<E::G1Affine>,
+    /// The encrypted shares of each validator of the next epoch
+    pub shares: Vec<Vec<KeyShareCiphertext<E>>>,
+}
+
+impl<E: PairingEngine> Resharing<E> {
+    /// The public key shared by this resharing
+    pub fn final_key(&self) -> E::G1Affine {
+        self.coeffs[0]
+    }
+
+    /// Decrypt the private key shares of the validator at position
+    /// `validator` in the next epoch with its session `keypair`
+    pub fn decrypt_shares(
+        &self,
+        validator: usize,
+        keypair: &Keypair<E>,
+    ) -> Vec<E::G2Affine> {
+        self.shares[validator]
+            .iter()
+            .map(|share| share.decrypt(keypair))
+            .collect()
+    }
+}
+
+/// The transcript posted by an old validator during the handover: one
+/// resharing of each key share it holds, in order of share index
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct ResharingTranscript<E: PairingEngine> {
+    pub dealings: Vec<Resharing<E>>,
+}
+
+/// The context of a key handover from the validators of one epoch to the
+/// validators of the next, preserving the final key of the DKG.
+///
+/// Every old validator reshares each of its key shares to the new validator
+/// set with fresh `params`. Once the transcripts cover enough old shares to
+/// reconstruct the key, they are combined with Lagrange coefficients into a
+/// sharing of the same key among the new validators.
+#[derive(Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct Handover<E: PairingEngine> {
+    pub old_params: Params,
+    pub old_validators: Vec<ferveo_common::Validator<E>>,
+    pub old_domain: ark_poly::Radix2EvaluationDomain<E::Fr>,
+    /// The public key share of every old share, g^{f(\omega_j)}
+    pub public_key_shares: Vec<E::G1Affine>,
+    /// The number of old shares needed to reconstruct the key
+    pub old_threshold: usize,
+    pub final_key: E::G1Affine,
+    pub params: Params,
+    pub validators: Vec<ferveo_common::Validator<E>>,
+    pub domain: ark_poly::Radix2EvaluationDomain<E::Fr>,
+    /// The transcripts received, keyed by position of the old dealer
+    pub transcripts: BTreeMap<u32, ResharingTranscript<E>>,
+}
+
+impl<E: PairingEngine> Handover<E> {
+    /// Create the context of a handover
+    /// `old_params` and `old_validators` are those of the current epoch
+    /// `old_coeffs` is the commitment of the aggregated PVSS transcript
+    /// `params` and `validators` are those of the next epoch, and `proofs`
+    /// the proofs of possession of the session keys of `validators`
+    ///
+    /// Both `old_params` and `params` are validated, and `old_coeffs` must
+    /// have the degree of the sharing polynomial of `old_params`
+    pub fn new(
+        old_params: Params,
+        old_validators: Vec<ExternalValidator<E>>,
+        old_coeffs: &[E::G1Affine],
+        params: Params,
+        validators: Vec<ExternalValidator<E>>,
+        proofs: &[ProofOfPossession<E>],
+    ) -> Result<Self> {
+        old_params.validate()?;
+        params.validate()?;
+        let old_domain = ark_poly::Radix2EvaluationDomain::<E::Fr>::new(
+            old_params.shares_num as usize,
+        )
+        .ok_or(DkgError::InvalidDomain(old_params.shares_num))?;
+        let domain = ark_poly::Radix2EvaluationDomain::<E::Fr>::new(
+            params.shares_num as usize,
+        )
+        .ok_or(DkgError::InvalidDomain(params.shares_num))?;
+        if old_coeffs.len() != old_params.degree() as usize + 1 {
+            return Err(DkgError::OldCommitmentLength {
+                expected: old_params.degree() as usize + 1,
+                received: old_coeffs.len(),
+            });
+        }
+        verify_proofs_of_possession(&validators, proofs)?;
+
+        // A_j = g^{f(\omega_j)}, the public key share of old share j
+        let public_key_shares = commitment_key_shares::<E>(
+            &old_domain,
+            old_coeffs,
+            old_params.shares_num,
+        );
+
+        Ok(Self {
+            old_validators: make_validators(old_validators, &old_params)?,
+            old_params,
+            old_domain,
+            public_key_shares,
+            old_threshold: old_coeffs.len(),
+            final_key: old_coeffs[0],
+            validators: make_validators(validators, &params)?,
+            params,
+            domain,
+            transcripts: BTreeMap::new(),
+        })
+    }
+
+    /// The degree of the sharing polynomials of the next epoch
+    fn degree(&self) -> usize {
+        self.params.degree() as usize
+    }
+
+    /// Reshare the old key share `key_share`, with public key share `a_j`,
+    /// to the validators of the next epoch
+    fn deal<R: Rng>(
+        &self,
+        a_j: E::G1Affine,
+        key_share: E::G2Affine,
+        rng: &mut R,
+    ) -> Resharing<E> {
+        // A random polynomial with a zero constant term, the key share is
+        // added in the group: g(x) = key_share + h^{a(x)}
+        let mut a = DensePolynomial::<E::Fr>::rand(self.degree(), rng);
+        a.coeffs[0] = E::Fr::zero();
+        let mut coeffs = vec![a_j];
+        coeffs.extend(fast_multiexp(
+            &a.coeffs[1..],
+            E::G1Projective::prime_subgroup_generator(),
+        ));
+
+        let evals = a.evaluate_over_domain_by_ref(self.domain);
+        let h = E::G2Affine::prime_subgroup_generator();
+        let shares = self
+            .validators
+            .iter()
+            .map(|validator| {
+                let ek = validator.validator.public_key.encryption_key;
+                evals.evals[validator.share_start..validator.share_end]
+                    .iter()
+                    .map(|eval| {
+                        let x = key_share.into_projective() + h.mul(*eval);
+                        KeyShareCiphertext::new(x, ek, rng)
+                    })
+                    .collect()
+            })
+            .collect();
+        Resharing { coeffs, shares }
+    }
+
+    /// Check that `dealing` reshares the old key share with
+    /// public key share `a_j`
+    fn verify_dealing(&self, a_j: E::G1Affine, dealing: &Resharing<E>) -> bool {
+        if dealing.coeffs.len() != self.degree() + 1
+            || dealing.coeffs[0] != a_j
+            || dealing.shares.len() != self.validators.len()
+        {
+            return false;
+        }
+        let mut commitment = batch_to_projective(&dealing.coeffs);
+        self.domain.fft_in_place(&mut commitment);
+        let commitment = E::G1Projective::batch_normalization_into_affine(
+            &commitment[..self.params.shares_num as usize],
+        );
+        self.validators.iter().zip_eq(dealing.shares.iter()).all(
+            |(validator, shares)| {
+                let ek = validator.validator.public_key.encryption_key;
+                shares.len() == validator.weight as usize
+                    && shares
+                        .iter()
+                        .zip_eq(
+                            &commitment
+                                [validator.share_start..validator.share_end],
+                        )
+                        .all(|(share, a)| share.verify(*a, ek))
+            },
+        )
+    }
+
+    /// Create the resharing transcript of the old validator at position
+    /// `dealer`, holding the private key shares `key_shares`
+    pub fn reshare<R: Rng>(
+        &self,
+        dealer: usize,
+        key_shares: &[E::G2Affine],
+        rng: &mut R,
+    ) -> Result<ResharingTranscript<E>> {
+        let dealer = self
+            .old_validators
+            .get(dealer)
+            .ok_or(DkgError::UnknownResharingDealer(dealer))?;
+        if key_shares.len() != dealer.weight as usize {
+            return Err(DkgError::ResharingShareCount {
+                expected: dealer.weight,
+                received: key_shares.len(),
+            });
+        }
+        Ok(ResharingTranscript {
+            dealings: self.public_key_shares
+                [dealer.share_start..dealer.share_end]
+                .iter()
+                .zip(key_shares.iter())
+                .map(|(a_j, key_share)| self.deal(*a_j, *key_share, rng))
+                .collect(),
+        })
+    }
+
+    /// Verify a resharing transcript
+    /// `sender` is the old validator that sent the transcript
+    pub fn verify_transcript(
+        &self,
+        sender: &ExternalValidator<E>,
+        transcript: &ResharingTranscript<E>,
+    ) -> Result<()> {
+        let dealer = self
+            .old_validators
+            .iter()
+            .position(|probe| sender == &probe.validator)
+            .ok_or_else(|| {
+                DkgError::UnknownValidator(sender.address.clone())
+            })?;
+        let validator = &self.old_validators[dealer];
+        if self.transcripts.contains_key(&(dealer as u32)) {
+            Err(DkgError::DuplicateDealer(dealer as u32))
+        } else if transcript.dealings.len() != validator.weight as usize
+            || !self.public_key_shares
+                [validator.share_start..validator.share_end]
+                .iter()
+                .zip_eq(transcript.dealings.iter())
+                .all(|(a_j, dealing)| self.verify_dealing(*a_j, dealing))
+        {
+            Err(DkgError::InvalidTranscript(dealer as u32))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Apply a verified resharing transcript from `sender`
+    pub fn apply_transcript(
+        &mut self,
+        sender: ExternalValidator<E>,
+        transcript: ResharingTranscript<E>,
+    ) -> Result<()> {
+        let dealer = self
+            .old_validators
+            .iter()
+            .position(|probe| sender == probe.validator)
+            .ok_or_else(|| {
+                DkgError::UnknownValidator(sender.address.clone())
+            })?;
+        self.transcripts.insert(dealer as u32, transcript);
+        Ok(())
+    }
+
+    /// Combine the received transcripts into a sharing of the final key
+    /// among the validators of the next epoch
+    pub fn aggregate(&self) -> Result<Resharing<E>> {
+        // The old shares that were reshared, with their resharings
+        let dealings = self
+            .transcripts
+            .iter()
+            .flat_map(|(dealer, transcript)| {
+                let validator = &self.old_validators[*dealer as usize];
+                (validator.share_start..validator.share_end)
+                    .zip(transcript.dealings.iter())
+            })
+            .collect::<Vec<_>>();
+        if dealings.len() < self.old_threshold {
+            return Err(DkgError::InsufficientShares {
+                received: dealings.len() as u32,
+                required: self.old_threshold as u32,
+            });
+        }
+
+        let shares_x = dealings
+            .iter()
+            .map(|(share_index, _)| self.old_domain.element(*share_index))
+            .collect::<Vec<_>>();
+        let lagrange_coeffs = prepare_combine_simple::<E>(&shares_x);
+
+        let mut coeffs = vec![E::G1Projective::zero(); self.degree() + 1];
+        let mut shares = self
+            .validators
+            .iter()
+            .map(|validator| {
+                vec![
+                    (
+                        E::G1Projective::zero(),
+                        E::G2Projective::zero(),
+                        E::G2Projective::zero()
+                    );
+                    validator.weight as usize
+                ]
+            })
+            .collect::<Vec<_>>();
+        for ((_, dealing), lambda) in dealings.iter().zip_eq(lagrange_coeffs) {
+            coeffs
+                .iter_mut()
+                .zip_eq(dealing.coeffs.iter())
+                .for_each(|(a, b)| *a += b.mul(lambda));
+            shares.iter_mut().zip_eq(dealing.shares.iter()).for_each(
+                |(a, b)| {
+                    a.iter_mut().zip_eq(b.iter()).for_each(|(a, b)| {
+                        let (r_g, r_h, masked) = b.mul(lambda);
+                        a.0 += r_g;
+                        a.1 += r_h;
+                        a.2 += masked;
+                    })
+                },
+            );
+        }
+
+        let aggregate = Resharing {
+            coeffs: E::G1Projective::batch_normalization_into_affine(&coeffs),
+            shares: shares
+                .into_iter()
+                .map(|shares| {
+                    shares
+                        .into_iter()
+                        .map(|(r_g, r_h, masked)| KeyShareCiphertext {
+                            r_g: r_g.into_affine(),
+                            r_h: r_h.into_affine(),
+                            masked: masked.into_affine(),
+                        })
+                        .collect()
+                })
+                .collect(),
+        };
+        if aggregate.final_key() != self.final_key {
+            return Err(DkgError::FinalKeyMismatch);
+        }
+        Ok(aggregate)
+    }
+}
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// Create the context of a handover of the key generated by this DKG
+    /// to `validators`, with the new parameters `params`
+    /// `proofs` are the proofs of possession of the session keys of `validators`
+    pub fn handover(
+        &self,
+        params: Params,
+        validators: Vec<ExternalValidator<E>>,
+        proofs: &[ProofOfPossession<E>],
+    ) -> Result<Handover<E>> {
+        match self.state {
+            DkgState::Success { .. } => Handover::new(
+                self.params,
+                self.validators
+                    .iter()
+                    .map(|v| v.validator.clone())
+                    .collect(),
+                &aggregate(self).coeffs,
+                params,
+                validators,
+                proofs,
+            ),
+            _ => Err(DkgError::WrongState(DkgOperation::Handover)),
+        }
+    }
+
+    /// Decrypt our private key shares and reshare them in `handover`
+    /// Returns the resharing transcript to post on-chain
+    pub fn reshare<R: Rng>(
+        &self,
+        handover: &Handover<E>,
+        rng: &mut R,
+    ) -> Result<ResharingTranscript<E>> {
+        self.check_participant(DkgOperation::Handover)?;
+        if !matches!(self.state, DkgState::Success { .. }) {
+            return Err(DkgError::WrongState(DkgOperation::Handover));
+        }
+        if handover.final_key != self.final_key() {
+            return Err(DkgError::HandoverKeyMismatch);
+        }
+        let decryption_key = self
+            .session_keypair
+            .decryption_key
+            .inverse()
+            .ok_or(DkgError::ZeroSessionKey)?;
+        // Z_j = Y_j^{dk^{-1}}, our private key shares
+        let key_shares = aggregate_for_decryption(self)[self.me]
+            .iter()
+            .map(|y| y.mul(decryption_key).into_affine())
+            .collect::<Vec<_>>();
+        handover.reshare(self.me, &key_shares, rng)
+    }
+}
+
+#[cfg(test)]
+mod test_reshare {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+    use group_threshold_cryptography as tpke;
+
+    type E = EllipticCurve;
+
+    /// The validators of the next epoch, and their keypairs
+    fn next_epoch(
+        powers: &[u64],
+    ) -> (Vec<ExternalValidator<E>>, Vec<ferveo_common::Keypair<E>>) {
+        let rng = &mut ark_std::test_rng();
+        // skip the keypairs of the old validators
+        let keypairs = (0..powers.len() + 8)
+            .map(|_| ferveo_common::Keypair::<E>::new(rng))
+            .skip(8)
+            .collect::<Vec<_>>();
+        let validators = powers
+            .iter()
+            .enumerate()
+            .map(|(i, power)| ExternalValidator {
+                address: format!("next_validator_{}", i),
+                public_key: keypairs[i].public(),
+                power: *power,
+            })
+            .collect();
+        (validators, keypairs)
+    }
+
+    fn next_params(security_threshold: u32, shares_num: u32) -> Params {
+        Params {
+            tau: 1,
+            security_threshold,
+            shares_num,
+            dealings_per_block: 2,
+        }
+    }
+
+    /// Test that handing over a key to a new validator set with different
+    /// parameters preserves the final key, and that the new validators can
+    /// decrypt ciphertexts encrypted to it
+    #[test]
+    fn test_handover_preserves_final_key() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 4, &[1, 1, 1, 1]);
+        let final_key = dkgs[0].final_key();
+
+        let powers = [1, 2, 3];
+        let (validators, keypairs) = next_epoch(&powers);
+        let proofs = gen_proofs(&keypairs, &validators);
+        let params = next_params(3, 6);
+        let mut handover = dkgs[0]
+            .handover(params, validators, &proofs)
+            .expect("Test failed");
+        for dkg in dkgs.iter() {
+            let transcript = dkg.reshare(&handover, rng).expect("Test failed");
+            let sender = dkg.validators[dkg.me].validator.clone();
+            handover
+                .verify_transcript(&sender, &transcript)
+                .expect("Test failed");
+            handover
+                .apply_transcript(sender, transcript)
+                .expect("Test failed");
+        }
+        let resharing = handover.aggregate().expect("Test failed");
+        assert_eq!(resharing.final_key(), final_key);
+        assert_eq!(
+            resharing.coeffs.len(),
+            params.decryption_threshold() as usize
+        );
+
+        // decrypt a ciphertext with the key shares of the new validators
+        let msg: &[u8] = "abc".as_bytes();
+        let aad: &[u8] = "my-aad".as_bytes();
+        let ciphertext = tpke::encrypt::<_, E>(msg, aad, &final_key, rng);
+        let decryption_shares = keypairs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, keypair)| resharing.decrypt_shares(i, keypair))
+            .enumerate()
+            .map(|(decrypter_index, z_i)| tpke::DecryptionShareSimple {
+                decrypter_index,
+                decryption_share: E::pairing(ciphertext.commitment, z_i),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(decryption_shares.len(), params.shares_num as usize);
+
+        let shares_x = handover
+            .domain
+            .elements()
+            .take(params.shares_num as usize)
+            .collect::<Vec<_>>();
+        let lagrange_coeffs = tpke::prepare_combine_simple::<E>(&shares_x);
+        let shared_secret = tpke::share_combine_simple::<E>(
+            &decryption_shares,
+            &lagrange_coeffs,
+        );
+        let plaintext = tpke::checked_decrypt_with_shared_secret(
+            &ciphertext,
+            aad,
+            &shared_secret,
+        )
+        .expect("Test failed");
+        assert_eq!(plaintext, msg);
+    }
+
+    /// Test that the key can be handed over once enough old
+    /// shares have been reshared, but not before
+    #[test]
+    fn test_handover_threshold() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 4, &[1, 1, 1, 1]);
+        let (validators, keypairs) = next_epoch(&[1, 1]);
+        let proofs = gen_proofs(&keypairs, &validators);
+        let mut handover = dkgs[0]
+            .handover(next_params(1, 2), validators, &proofs)
+            .expect("Test failed");
+        assert_eq!(handover.old_threshold, 3);
+        for dkg in dkgs.iter().take(3) {
+            assert!(handover.aggregate().is_err());
+            let transcript = dkg.reshare(&handover, rng).expect("Test failed");
+            let sender = dkg.validators[dkg.me].validator.clone();
+            handover
+                .apply_transcript(sender, transcript)
+                .expect("Test failed");
+        }
+        let resharing = handover.aggregate().expect("Test failed");
+        assert_eq!(resharing.final_key(), dkgs[0].final_key());
+    }
+
+    /// Test that resharing works with weighted validators in both epochs
+    /// and that the new key shares match the new commitment
+    #[test]
+    fn test_handover_weighted() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 6, &[1, 2, 3]);
+        let (validators, keypairs) = next_epoch(&[2, 1]);
+        let proofs = gen_proofs(&keypairs, &validators);
+        let mut handover = dkgs[0]
+            .handover(next_params(2, 5), validators, &proofs)
+            .expect("Test failed");
+        for dkg in dkgs.iter() {
+            let transcript = dkg.reshare(&handover, rng).expect("Test failed");
+            assert_eq!(
+                transcript.dealings.len(),
+                dkg.validators[dkg.me].weight as usize
+            );
+            let sender = dkg.validators[dkg.me].validator.clone();
+            handover
+                .verify_transcript(&sender, &transcript)
+                .expect("Test failed");
+            handover
+                .apply_transcript(sender, transcript)
+                .expect("Test failed");
+        }
+        let resharing = handover.aggregate().expect("Test failed");
+        assert_eq!(resharing.final_key(), dkgs[0].final_key());
+
+        // e(g, Z_k) = e(A_k, h) for every new key share
+        let mut commitment = batch_to_projective(&resharing.coeffs);
+        handover.domain.fft_in_place(&mut commitment);
+        let g = G1::prime_subgroup_generator();
+        let h = <E as PairingEngine>::G2Affine::prime_subgroup_generator();
+        for (i, keypair) in keypairs.iter().enumerate() {
+            let validator = &handover.validators[i];
+            let key_shares = resharing.decrypt_shares(i, keypair);
+            assert_eq!(key_shares.len(), validator.weight as usize);
+            for (z_k, a_k) in key_shares
+                .iter()
+                .zip(&commitment[validator.share_start..validator.share_end])
+            {
+                assert_eq!(
+                    E::pairing(g, *z_k),
+                    E::pairing(a_k.into_affine(), h)
+                );
+            }
+        }
+    }
+
+    /// Test that invalid resharing transcripts are rejected
+    #[test]
+    fn test_handover_invalid_transcript_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let dkgs = setup_finished_dkgs(2, 4, &[1, 1, 1, 1]);
+        let (validators, keypairs) = next_epoch(&[1, 1, 1]);
+        let proofs = gen_proofs(&keypairs, &validators);
+        let mut handover = dkgs[0]
+            .handover(next_params(1, 3), validators, &proofs)
+            .expect("Test failed");
+        let transcript = dkgs[1].reshare(&handover, rng).expect("Test failed");
+        let sender = dkgs[1].validators[1].validator.clone();
+
+        // the transcript can not be replayed by another dealer
+        let other = dkgs[1].validators[2].validator.clone();
+        assert!(handover.verify_transcript(&other, &transcript).is_err());
+
+        // a tampered key share is rejected
+        let mut tampered = transcript.clone();
+        let masked = &mut tampered.dealings[0].shares[2][0].masked;
+        *masked = *masked
+            + <E as PairingEngine>::G2Affine::prime_subgroup_generator();
+        assert!(handover.verify_transcript(&sender, &tampered).is_err());
+
+        // a resharing of another secret is rejected
+        let mut forged = transcript.clone();
+        forged.dealings[0].coeffs[0] = G1::prime_subgroup_generator();
+        assert!(handover.verify_transcript(&sender, &forged).is_err());
+
+        // the honest transcript is accepted once
+        assert!(handover.verify_transcript(&sender, &transcript).is_ok());
+        handover
+            .apply_transcript(sender.clone(), transcript.clone())
+            .expect("Test failed");
+        assert!(handover.verify_transcript(&sender, &transcript).is_err());
+    }
+
+    /// Test that a handover with invalid parameters for either epoch, or a
+    /// commitment of the old key of the wrong degree, is rejected instead
+    /// of panicking
+    #[test]
+    fn test_handover_invalid_params_rejected() {
+        let dkgs = setup_finished_dkgs(2, 4, &[1, 1, 1, 1]);
+        let (validators, keypairs) = next_epoch(&[1, 1]);
+        let proofs = gen_proofs(&keypairs, &validators);
+        assert!(matches!(
+            dkgs[0].handover(next_params(3, 2), validators.clone(), &proofs),
+            Err(DkgError::Params(
+                ParamsError::SecurityThresholdOutOfRange { .. }
+            ))
+        ));
+
+        let old_validators = dkgs[0].external_validators();
+        let old_coeffs = aggregate(&dkgs[0]).coeffs;
+        let new_handover = |old_params: Params, old_coeffs: &[G1]| {
+            Handover::new(
+                old_params,
+                old_validators.clone(),
+                old_coeffs,
+                next_params(1, 2),
+                validators.clone(),
+                &proofs,
+            )
+        };
+        assert!(matches!(
+            new_handover(
+                Params {
+                    security_threshold: 5,
+                    ..dkgs[0].params
+                },
+                &old_coeffs
+            ),
+            Err(DkgError::Params(
+                ParamsError::SecurityThresholdOutOfRange { .. }
+            ))
+        ));
+        assert!(matches!(
+            new_handover(dkgs[0].params, &old_coeffs[..1]),
+            Err(DkgError::OldCommitmentLength {
+                expected: 3,
+                received: 1,
+            })
+        ));
+        assert!(new_handover(dkgs[0].params, &old_coeffs).is_ok());
+    }
+
+    /// Test that a DKG that has not finished can not hand over its key
+    #[test]
+    fn test_handover_requires_final_key() {
+        let dkg = setup_dealt_dkg();
+        let (validators, keypairs) = next_epoch(&[1, 1]);
+        let proofs = gen_proofs(&keypairs, &validators);
+        assert!(dkg
+            .handover(next_params(1, 2), validators, &proofs)
+            .is_err());
+    }
+}
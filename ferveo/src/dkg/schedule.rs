@@ -0,0 +1,118 @@
+use crate::*;
+use std::collections::BTreeMap;
+
+/// A deterministic schedule of the blocks in which each dealer should post
+/// its PVSS transcript.
+///
+/// Dealers are ordered by a hash of the session id `tau` and their address,
+/// and the ordered dealers are split into slots of at most
+/// `Params::dealings_per_block` dealers. A round lasts one block per slot;
+/// in each round, every dealer whose transcript is not yet on chain issues
+/// (or re-issues) its transcript in the block of its slot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DealingSchedule {
+    /// The slot of each dealer, indexed by position in the validator set
+    slots: Vec<u32>,
+    /// The number of blocks in a round
+    round_length: u32,
+}
+
+impl DealingSchedule {
+    /// Compute the dealing schedule of the session with parameters `params`
+    pub fn new<E: PairingEngine>(
+        params: &Params,
+        validators: &[ferveo_common::Validator<E>],
+    ) -> Result<Self> {
+        if params.dealings_per_block == 0 {
+            return Err(DkgError::Params(ParamsError::ZeroDealingBudget));
+        }
+        let mut order = validators
+            .iter()
+            .enumerate()
+            .map(|(dealer, validator)| {
+                let mut state = blake2b_simd::Params::new().to_state();
+                state.update(&params.tau.to_le_bytes());
+                state.update(validator.validator.address.as_bytes());
+                (*state.finalize().as_array(), dealer)
+            })
+            .collect::<Vec<_>>();
+        order.sort();
+
+        let mut slots = vec![0u32; validators.len()];
+        for (position, (_, dealer)) in order.into_iter().enumerate() {
+            slots[dealer] = position as u32 / params.dealings_per_block;
+        }
+        let round_length = (validators.len() as u32)
+            .div_ceil(params.dealings_per_block)
+            .max(1);
+        Ok(Self {
+            slots,
+            round_length,
+        })
+    }
+
+    /// The number of blocks after which a dealer whose transcript has
+    /// not landed on chain re-issues it
+    pub fn round_length(&self) -> u32 {
+        self.round_length
+    }
+
+    /// The block offset within each round at which `dealer` issues
+    pub fn slot(&self, dealer: usize) -> u32 {
+        self.slots[dealer]
+    }
+
+    /// Returns if `dealer` is scheduled to issue a PVSS transcript
+    /// `blocks_since_start` blocks after the DKG began
+    pub fn is_scheduled(&self, dealer: usize, blocks_since_start: u32) -> bool {
+        blocks_since_start % self.round_length == self.slots[dealer]
+    }
+
+    /// The dealers scheduled to issue a PVSS transcript
+    /// `blocks_since_start` blocks after the DKG began, given the
+    /// transcripts `vss` already on chain
+    pub fn dealers_at<T>(
+        &self,
+        blocks_since_start: u32,
+        vss: &BTreeMap<u32, T>,
+    ) -> Vec<usize> {
+        (0..self.slots.len())
+            .filter(|dealer| {
+                self.is_scheduled(*dealer, blocks_since_start)
+                    && !vss.contains_key(&(*dealer as u32))
+            })
+            .collect()
+    }
+}
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// The dealing schedule of this DKG session. Fails if the dealing
+    /// budget of `params` was set to zero after the DKG was created
+    pub fn dealing_schedule(&self) -> Result<DealingSchedule> {
+        DealingSchedule::new(&self.params, &self.validators)
+    }
+
+    /// Returns a value indicating if we should issue a PVSS transcript
+    /// `blocks_since_start` blocks after the DKG began, given the
+    /// transcripts already on chain. No dealer is scheduled if the
+    /// dealing budget is zero
+    ///
+    /// `blocks_since_start` is not the chain height: it is the height
+    /// minus the height of the block in which the DKG began, so that
+    /// every validator counts rounds from the same block
+    pub fn schedule_pvss(&self, blocks_since_start: u32) -> PvssScheduler {
+        match self.state {
+            DkgState::Sharing { .. }
+                if !self.observer
+                    && !self.vss.contains_key(&(self.me as u32))
+                    && !self.faulty_dealers.contains(&(self.me as u32))
+                    && self.dealing_schedule().is_ok_and(|schedule| {
+                        schedule.is_scheduled(self.me, blocks_since_start)
+                    }) =>
+            {
+                PvssScheduler::Issue
+            }
+            _ => PvssScheduler::Wait,
+        }
+    }
+}
@@ -0,0 +1,569 @@
+use crate::*;
+use ark_serialize::*;
+use ferveo_common::ExternalValidator;
+use serde::de::{DeserializeSeed, Deserializer, Error as _};
+use std::collections::{BTreeMap, BTreeSet};
+use std::convert::TryInto;
+
+/// Magic bytes at the start of every DKG snapshot
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"FVDK";
+
+/// The current version of the DKG snapshot format
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// A migration upgrades the payload of a snapshot by one format version
+pub type SnapshotMigration = fn(Vec<u8>) -> Result<Vec<u8>>;
+
+/// Migrations between snapshot format versions, the migration at index
+/// `i` upgrades a payload of version `i + 1` to version `i + 2`.
+///
+/// When the serialization of [`PubliclyVerifiableDkg`] changes, bump
+/// [`SNAPSHOT_VERSION`] and add the migration from the previous format here.
+const MIGRATIONS: [SnapshotMigration; SNAPSHOT_VERSION as usize - 1] = [];
+
+/// The header prepended to a serialized DKG snapshot
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    /// The format version of the payload
+    pub version: u16,
+    /// A hash of the DKG parameters and the validator set of the session
+    pub session_hash: [u8; 32],
+    /// A hash of the payload, used to detect corruption
+    pub payload_hash: [u8; 32],
+}
+
+impl SnapshotHeader {
+    /// The length in bytes of a serialized header
+    pub const LENGTH: usize = SNAPSHOT_MAGIC.len() + 2 + 32 + 32;
+
+    /// Serialize the header into `bytes`
+    pub fn write(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&SNAPSHOT_MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.session_hash);
+        bytes.extend_from_slice(&self.payload_hash);
+    }
+
+    /// Parse the header at the start of `snapshot`, returning it together
+    /// with the payload that follows it
+    pub fn read(snapshot: &[u8]) -> Result<(Self, &[u8])> {
+        if snapshot.len() < Self::LENGTH {
+            return Err(DkgError::InvalidSnapshot(SnapshotError::TooShort));
+        }
+        let (magic, rest) = snapshot.split_at(SNAPSHOT_MAGIC.len());
+        if magic != SNAPSHOT_MAGIC {
+            return Err(DkgError::InvalidSnapshot(SnapshotError::BadMagic));
+        }
+        let (version, rest) = rest.split_at(2);
+        let (session_hash, rest) = rest.split_at(32);
+        let (payload_hash, payload) = rest.split_at(32);
+        let header = Self {
+            version: u16::from_le_bytes([version[0], version[1]]),
+            session_hash: session_hash.try_into().expect("split at 32 bytes"),
+            payload_hash: payload_hash.try_into().expect("split at 32 bytes"),
+        };
+        Ok((header, payload))
+    }
+}
+
+/// Hash the DKG parameters `params` and the validator set `validators`
+/// identifying a DKG session
+pub fn session_hash<E: PairingEngine>(
+    params: &Params,
+    validators: &[ExternalValidator<E>],
+) -> Result<[u8; 32]> {
+    let mut bytes = vec![];
+    CanonicalSerialize::serialize(params, &mut bytes)?;
+    CanonicalSerialize::serialize(&validators.to_vec(), &mut bytes)?;
+    Ok(hash_32(&bytes))
+}
+
+fn hash_32(bytes: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(
+        blake2b_simd::Params::new()
+            .hash_length(32)
+            .hash(bytes)
+            .as_bytes(),
+    );
+    hash
+}
+
+/// Apply the `migrations` needed to bring `payload` from format `version`
+/// to the format after the last migration
+fn migrate(
+    version: u16,
+    payload: Vec<u8>,
+    migrations: &[SnapshotMigration],
+) -> Result<Vec<u8>> {
+    if version == 0 || version as usize > migrations.len() + 1 {
+        return Err(DkgError::UnsupportedSnapshotVersion(version));
+    }
+    migrations[version as usize - 1..]
+        .iter()
+        .try_fold(payload, |payload, migration| migration(payload))
+}
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// Serialize the local state of this DKG, so that it can be resumed
+    /// with [`PubliclyVerifiableDkg::load_snapshot`] after a restart
+    ///
+    /// The snapshot includes the session keypair and must be stored
+    /// as securely as the keypair itself
+    pub fn save_snapshot(&self) -> Result<Vec<u8>> {
+        let mut payload = vec![];
+        CanonicalSerialize::serialize(self, &mut payload)?;
+        let header = SnapshotHeader {
+            version: SNAPSHOT_VERSION,
+            session_hash: session_hash(
+                &self.params,
+                &self.external_validators(),
+            )?,
+            payload_hash: hash_32(&payload),
+        };
+        let mut snapshot =
+            Vec::with_capacity(SnapshotHeader::LENGTH + payload.len());
+        header.write(&mut snapshot);
+        snapshot.extend_from_slice(&payload);
+        Ok(snapshot)
+    }
+
+    /// Restore a DKG from a `snapshot` produced by
+    /// [`PubliclyVerifiableDkg::save_snapshot`]
+    ///
+    /// `params` and `validators` are the parameters and the validator set
+    /// of the session we expect to resume; the snapshot is rejected if it
+    /// belongs to another session, is corrupted or its state is inconsistent
+    pub fn load_snapshot(
+        snapshot: &[u8],
+        params: &Params,
+        validators: &[ExternalValidator<E>],
+    ) -> Result<Self> {
+        let (header, payload) = SnapshotHeader::read(snapshot)?;
+        if header.session_hash != session_hash(params, validators)? {
+            return Err(DkgError::InvalidSnapshot(
+                SnapshotError::ForeignSession,
+            ));
+        }
+        if header.payload_hash != hash_32(payload) {
+            return Err(DkgError::InvalidSnapshot(SnapshotError::Corrupted));
+        }
+        let payload = migrate(header.version, payload.to_vec(), &MIGRATIONS)?;
+        let mut reader = payload.as_slice();
+        let dkg = <Self as CanonicalDeserialize>::deserialize(&mut reader)?;
+        if !reader.is_empty() {
+            return Err(DkgError::InvalidSnapshot(
+                SnapshotError::TrailingBytes,
+            ));
+        }
+        dkg.check_snapshot(params, validators)?;
+        Ok(dkg)
+    }
+
+    /// The validator set of the DKG, without the share partition
+    pub(crate) fn external_validators(&self) -> Vec<ExternalValidator<E>> {
+        self.validators
+            .iter()
+            .map(|v| v.validator.clone())
+            .collect()
+    }
+
+    /// Check that the state restored from a snapshot is consistent
+    /// with the session given by `params` and `validators`
+    pub(crate) fn check_snapshot(
+        &self,
+        params: &Params,
+        validators: &[ExternalValidator<E>],
+    ) -> Result<()> {
+        if session_hash(&self.params, &self.external_validators())?
+            != session_hash(params, validators)?
+        {
+            return Err(DkgError::InvalidSnapshot(
+                SnapshotError::SessionMismatch,
+            ));
+        }
+        let expected = make_validators(validators.to_vec(), params)?;
+        if expected.iter().zip(self.validators.iter()).any(|(a, b)| {
+            (a.weight, a.share_start, a.share_end)
+                != (b.weight, b.share_start, b.share_end)
+        }) {
+            return Err(DkgError::InvalidSnapshot(
+                SnapshotError::SharePartition,
+            ));
+        }
+        let domain = ark_poly::Radix2EvaluationDomain::<E::Fr>::new(
+            params.shares_num as usize,
+        )
+        .ok_or(DkgError::InvalidDomain(params.shares_num))?;
+        if self.domain != domain {
+            return Err(DkgError::InvalidSnapshot(SnapshotError::Domain));
+        }
+        match self.validators.get(self.me) {
+            Some(_)
+                if !self.observer
+                    && self.session_key(self.me)
+                        == self.session_keypair.public() => {}
+            None if self.observer && self.me == self.validators.len() => {}
+            _ => {
+                return Err(DkgError::InvalidSnapshot(
+                    SnapshotError::KeypairMismatch,
+                ))
+            }
+        }
+        for (dealer, pvss) in self.vss.iter() {
+            if *dealer as usize >= self.validators.len()
+                || self.faulty_dealers.contains(dealer)
+                || !pvss.is_well_formed(self)
+            {
+                return Err(DkgError::InvalidSnapshot(
+                    SnapshotError::InvalidTranscript(*dealer),
+                ));
+            }
+        }
+        if let Some(refresh) = &self.refresh {
+            if !matches!(self.state, DkgState::Success { .. })
+                || !refresh.coeffs.first().is_some_and(|c| c.is_zero())
+                || !refresh.is_well_formed(self)
+            {
+                return Err(DkgError::InvalidSnapshot(
+                    SnapshotError::InvalidRefresh,
+                ));
+            }
+        }
+        if self.refresh_round != 0
+            && !matches!(self.state, DkgState::Success { .. })
+        {
+            return Err(DkgError::InvalidSnapshot(
+                SnapshotError::InvalidRefresh,
+            ));
+        }
+        if let Some(validator) = self.refreshed.iter().find(|v| {
+            !matches!(self.state, DkgState::Success { .. })
+                || **v as usize >= self.validators.len()
+        }) {
+            return Err(DkgError::InvalidSnapshot(
+                SnapshotError::InvalidRefreshed(*validator),
+            ));
+        }
+        if let Some((validator, _)) = self.rekeyed.iter().find(|(v, r)| {
+            !matches!(self.state, DkgState::Success { .. })
+                || self
+                    .validators
+                    .get(**v as usize)
+                    .is_none_or(|v| v.weight as usize != r.shares.len())
+        }) {
+            return Err(DkgError::InvalidSnapshot(
+                SnapshotError::InvalidRekeyed(*validator),
+            ));
+        }
+        if let Some(recovery) = &self.recovery {
+            let mut helpers =
+                recovery.masks.keys().chain(recovery.shares.keys());
+            if !matches!(self.state, DkgState::Success { .. })
+                || recovery.validator as usize >= self.validators.len()
+                || helpers.any(|v| *v as usize >= self.validators.len())
+            {
+                return Err(DkgError::InvalidSnapshot(
+                    SnapshotError::InvalidRecovery,
+                ));
+            }
+        }
+        // our pending keypair belongs to our own recovery in progress
+        if let Some(keypair) = &self.recovery_keypair {
+            if !self.recovery.as_ref().is_some_and(|recovery| {
+                recovery.validator as usize == self.me
+                    && recovery.public_key == keypair.public()
+            }) {
+                return Err(DkgError::InvalidSnapshot(
+                    SnapshotError::InvalidRecovery,
+                ));
+            }
+        }
+        if let DkgState::Sharing { accumulated_shares } = self.state {
+            let dealt = self
+                .vss
+                .keys()
+                .map(|dealer| self.validators[*dealer as usize].weight)
+                .sum::<u32>();
+            if dealt != accumulated_shares {
+                return Err(DkgError::InvalidSnapshot(
+                    SnapshotError::AccumulatedShares {
+                        accumulated: accumulated_shares,
+                        dealt,
+                    },
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The serde form of [`PubliclyVerifiableDkg`], without the session keypair
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct DkgFields<E: PairingEngine> {
+    params: Params,
+    pvss_params: PubliclyVerifiableParams<E>,
+    validators: Vec<ferveo_common::Validator<E>>,
+    vss: BTreeMap<u32, PubliclyVerifiableSS<E>>,
+    faulty_dealers: BTreeSet<u32>,
+    #[serde(with = "ferveo_common::ark_serde")]
+    domain: ark_poly::Radix2EvaluationDomain<E::Fr>,
+    state: DkgState<E>,
+    me: usize,
+    refresh: Option<PubliclyVerifiableSS<E>>,
+    recovery: Option<Recovery<E>>,
+    observer: bool,
+    deadlines: Deadlines,
+    refresh_round: u64,
+    refreshed: BTreeSet<u32>,
+    rekeyed: BTreeMap<u32, Rekeyed<E>>,
+}
+
+/// Restores a DKG from its serde form, which omits the session keypair
+///
+/// The restored state is checked against the session given by `params`
+/// and `validators` as [`PubliclyVerifiableDkg::load_snapshot`] does, and
+/// must belong to the validator of `session_keypair`, or to an observer
+/// if it is `None`
+pub struct RestoreState<'a, E: PairingEngine> {
+    pub params: &'a Params,
+    pub validators: &'a [ExternalValidator<E>],
+    pub session_keypair: Option<ferveo_common::Keypair<E>>,
+}
+
+impl<'de, 'a, E: PairingEngine> DeserializeSeed<'de> for RestoreState<'a, E> {
+    type Value = PubliclyVerifiableDkg<E>;
+
+    fn deserialize<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> std::result::Result<Self::Value, D::Error> {
+        let fields = DkgFields::<E>::deserialize(deserializer)?;
+        let dkg = PubliclyVerifiableDkg {
+            params: fields.params,
+            pvss_params: fields.pvss_params,
+            session_keypair: self.session_keypair.unwrap_or(
+                ferveo_common::Keypair {
+                    decryption_key: E::Fr::zero(),
+                },
+            ),
+            validators: fields.validators,
+            vss: fields.vss,
+            faulty_dealers: fields.faulty_dealers,
+            domain: fields.domain,
+            state: fields.state,
+            me: fields.me,
+            refresh: fields.refresh,
+            recovery: fields.recovery,
+            observer: fields.observer,
+            deadlines: fields.deadlines,
+            refresh_round: fields.refresh_round,
+            refreshed: fields.refreshed,
+            rekeyed: fields.rekeyed,
+            recovery_keypair: None,
+        };
+        dkg.check_snapshot(self.params, self.validators)
+            .map_err(D::Error::custom)?;
+        Ok(dkg)
+    }
+}
+
+#[cfg(test)]
+mod test_snapshot {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+
+    /// The parameters and validator set used by `setup_dkg`
+    fn session() -> (Params, Vec<ExternalValidator<EllipticCurve>>) {
+        let dkg = setup_dkg(0);
+        (dkg.params, dkg.external_validators())
+    }
+
+    /// Test that a DKG in the middle of dealing can be saved
+    /// and resumed without losing transcripts
+    #[test]
+    fn test_snapshot_round_trip() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dkg(0);
+        let pvss = dkg.share(rng).expect("Test failed");
+        let sender = dkg.validators[0].validator.clone();
+        dkg.apply_message(sender, pvss).expect("Test failed");
+
+        let (params, validators) = session();
+        let snapshot = dkg.save_snapshot().expect("Test failed");
+        let restored = PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            &snapshot,
+            &params,
+            &validators,
+        )
+        .expect("Test failed");
+        assert_eq!(restored.vss.len(), 1);
+        assert!(matches!(
+            restored.state,
+            DkgState::Sharing {
+                accumulated_shares: 1
+            }
+        ));
+        assert_eq!(restored.final_key(), dkg.final_key());
+        assert_eq!(restored.save_snapshot().expect("Test failed"), snapshot);
+    }
+
+    /// Test that a dealt DKG can be resumed and aggregated
+    #[test]
+    fn test_snapshot_resume_aggregation() {
+        let dkg = setup_dealt_dkg();
+        let (params, validators) = session();
+        let snapshot = dkg.save_snapshot().expect("Test failed");
+        let mut restored =
+            PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+                &snapshot,
+                &params,
+                &validators,
+            )
+            .expect("Test failed");
+        let aggregate = restored.aggregate().expect("Test failed");
+        let sender = restored.validators[restored.me].validator.clone();
+        assert!(restored.verify_message(&sender, &aggregate).is_ok());
+        assert!(restored.apply_message(sender, aggregate).is_ok());
+        assert!(matches!(restored.state, DkgState::Success { .. }));
+    }
+
+    /// Test that a snapshot of another session is rejected
+    #[test]
+    fn test_snapshot_wrong_session_rejected() {
+        let dkg = setup_dkg(0);
+        let snapshot = dkg.save_snapshot().expect("Test failed");
+        let (mut params, validators) = session();
+        params.tau += 1;
+        let err = PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            &snapshot,
+            &params,
+            &validators,
+        )
+        .expect_err("Test failed");
+        assert!(matches!(
+            err,
+            DkgError::InvalidSnapshot(SnapshotError::ForeignSession)
+        ));
+        let (params, _) = session();
+        assert!(PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            &snapshot,
+            &params,
+            &validators[1..],
+        )
+        .is_err());
+    }
+
+    /// Test that corrupted or truncated snapshots are rejected
+    #[test]
+    fn test_snapshot_corruption_rejected() {
+        let dkg = setup_dkg(0);
+        let (params, validators) = session();
+        let mut snapshot = dkg.save_snapshot().expect("Test failed");
+
+        let truncated = &snapshot[..SnapshotHeader::LENGTH - 1];
+        assert!(PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            truncated,
+            &params,
+            &validators,
+        )
+        .is_err());
+
+        let last = snapshot.len() - 1;
+        snapshot[last] ^= 1;
+        let err = PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            &snapshot,
+            &params,
+            &validators,
+        )
+        .expect_err("Test failed");
+        assert!(matches!(
+            err,
+            DkgError::InvalidSnapshot(SnapshotError::Corrupted)
+        ));
+    }
+
+    /// Test that snapshots from an unknown format version are rejected
+    #[test]
+    fn test_snapshot_unknown_version_rejected() {
+        let dkg = setup_dkg(0);
+        let (params, validators) = session();
+        let snapshot = dkg.save_snapshot().expect("Test failed");
+        let (mut header, payload) =
+            SnapshotHeader::read(&snapshot).expect("Test failed");
+        assert_eq!(header.version, SNAPSHOT_VERSION);
+
+        header.version = SNAPSHOT_VERSION + 1;
+        let mut future = vec![];
+        header.write(&mut future);
+        future.extend_from_slice(payload);
+        let err = PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            &future,
+            &params,
+            &validators,
+        )
+        .expect_err("Test failed");
+        assert!(matches!(
+            err,
+            DkgError::UnsupportedSnapshotVersion(version)
+                if version == SNAPSHOT_VERSION + 1
+        ));
+    }
+
+    /// Test that the migrations from the version of a payload up to the
+    /// latest version are applied in order
+    #[test]
+    fn test_snapshot_migrations_applied_in_order() {
+        fn append_one(mut payload: Vec<u8>) -> Result<Vec<u8>> {
+            payload.push(1);
+            Ok(payload)
+        }
+        fn append_two(mut payload: Vec<u8>) -> Result<Vec<u8>> {
+            payload.push(2);
+            Ok(payload)
+        }
+        let migrations: [SnapshotMigration; 2] = [append_one, append_two];
+
+        // payloads of versions 1, 2 and 3 of a format whose version 2
+        // appends a 1 and version 3 appends a 2
+        let migrated = |version, payload: &[u8]| {
+            migrate(version, payload.to_vec(), &migrations)
+                .expect("Test failed")
+        };
+        assert_eq!(migrated(1, &[0]), [0, 1, 2]);
+        assert_eq!(migrated(2, &[0, 1]), [0, 1, 2]);
+        assert_eq!(migrated(3, &[0, 1, 2]), [0, 1, 2]);
+        for version in [0, 4] {
+            assert!(matches!(
+                migrate(version, vec![0], &migrations),
+                Err(DkgError::UnsupportedSnapshotVersion(v)) if v == version
+            ));
+        }
+    }
+
+    /// Test that a snapshot whose state is inconsistent with
+    /// its transcripts is rejected, even if it is well formed
+    #[test]
+    fn test_snapshot_inconsistent_state_rejected() {
+        let mut dkg = setup_dkg(0);
+        dkg.state = DkgState::Sharing {
+            accumulated_shares: 3,
+        };
+        let (params, validators) = session();
+        let snapshot = dkg.save_snapshot().expect("Test failed");
+        let err = PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            &snapshot,
+            &params,
+            &validators,
+        )
+        .expect_err("Test failed");
+        assert!(matches!(
+            err,
+            DkgError::InvalidSnapshot(SnapshotError::AccumulatedShares {
+                accumulated: 3,
+                dealt: 0,
+            })
+        ));
+    }
+}
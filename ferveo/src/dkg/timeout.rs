@@ -0,0 +1,299 @@
+use crate::*;
+use ark_serialize::*;
+use std::collections::BTreeSet;
+
+/// The deadlines of a DKG session, in blocks since the DKG began as
+/// counted by the [`DealingSchedule`]. A deadline is the last block in
+/// which the DKG may make progress, and an unset deadline never passes
+#[derive(
+    Serialize,
+    Deserialize,
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+pub struct Deadlines {
+    /// The deadline to deal enough PVSS transcripts to aggregate
+    pub dealing: Option<u32>,
+    /// The deadline to aggregate the PVSS transcripts
+    pub aggregation: Option<u32>,
+}
+
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// Set the deadlines of this DKG session
+    pub fn set_deadlines(&mut self, deadlines: Deadlines) -> Result<()> {
+        if let (Some(dealing), Some(aggregation)) =
+            (deadlines.dealing, deadlines.aggregation)
+        {
+            if aggregation < dealing {
+                return Err(DkgError::Params(ParamsError::DeadlineOrder {
+                    dealing,
+                    aggregation,
+                }));
+            }
+        }
+        self.deadlines = deadlines;
+        Ok(())
+    }
+
+    /// Move the DKG to [`DkgState::Invalid`] if one of its deadlines has
+    /// passed `blocks_since_start` blocks after the DKG began
+    /// Returns the reason the DKG failed, if it has
+    ///
+    /// Like the deadlines, `blocks_since_start` is not the chain height:
+    /// it is the height minus the height of the block in which the DKG
+    /// began, as for [`PubliclyVerifiableDkg::schedule_pvss`]
+    pub fn check_deadlines(
+        &mut self,
+        blocks_since_start: u32,
+    ) -> Option<FailureReason> {
+        let passed = |deadline: Option<u32>| {
+            deadline.is_some_and(|last| blocks_since_start > last)
+        };
+        match self.state {
+            DkgState::Sharing { .. } if passed(self.deadlines.dealing) => {
+                self.state = DkgState::Invalid {
+                    reason: FailureReason::DealingTimeout,
+                };
+            }
+            DkgState::Sharing { .. } | DkgState::Dealt
+                if passed(self.deadlines.aggregation) =>
+            {
+                self.state = DkgState::Invalid {
+                    reason: FailureReason::AggregationTimeout,
+                };
+            }
+            _ => (),
+        }
+        match self.state {
+            DkgState::Invalid { reason } => Some(reason),
+            _ => None,
+        }
+    }
+
+    /// The dealers left out of a restart of this DKG: the dealers proven
+    /// faulty, and those whose transcript never landed on chain
+    pub fn excluded_dealers(&self) -> BTreeSet<u32> {
+        (0..self.validators.len() as u32)
+            .filter(|dealer| {
+                self.faulty_dealers.contains(dealer)
+                    || !self.vss.contains_key(dealer)
+            })
+            .collect()
+    }
+
+    /// Restart this failed DKG as a new session with session id `tau`,
+    /// among the validators that are not in [`Self::excluded_dealers`]
+    ///
+    /// Returns a new DKG with the same parameters, session keypair and
+    /// deadlines, in which the shares are partitioned between the
+    /// remaining validators
+    pub fn restart(&self, tau: u64) -> Result<Self> {
+        if !matches!(self.state, DkgState::Invalid { .. }) {
+            return Err(DkgError::WrongState(DkgOperation::Restart));
+        }
+        if tau == self.params.tau {
+            return Err(DkgError::Params(ParamsError::SessionIdReused(tau)));
+        }
+        let excluded = self.excluded_dealers();
+        let validators = self
+            .validators
+            .iter()
+            .enumerate()
+            .filter(|(dealer, _)| !excluded.contains(&(*dealer as u32)))
+            .map(|(_, v)| v.validator.clone())
+            .collect::<Vec<_>>();
+        let me = if self.observer {
+            validators.len()
+        } else {
+            validators
+                .iter()
+                .position(|v| v == &self.validators[self.me].validator)
+                .ok_or(DkgError::NotInValidatorSet)?
+        };
+        // the session keys were proven when this DKG was created
+        let mut dkg = Self::init(
+            validators,
+            Params { tau, ..self.params },
+            me,
+            self.session_keypair,
+            self.observer,
+        )?;
+        dkg.deadlines = self.deadlines;
+        Ok(dkg)
+    }
+}
+
+#[cfg(test)]
+mod test_timeout {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+
+    const DEADLINES: Deadlines = Deadlines {
+        dealing: Some(5),
+        aggregation: Some(10),
+    };
+
+    /// Deal the transcripts of `dealers` and apply them to every DKG
+    fn deal(
+        dkgs: &mut [PubliclyVerifiableDkg<EllipticCurve>],
+        dealers: &[usize],
+    ) {
+        let rng = &mut ark_std::test_rng();
+        for dealer in dealers {
+            let pvss = dkgs[*dealer].share(rng).expect("Test failed");
+            let sender = dkgs[*dealer].validators[*dealer].validator.clone();
+            for dkg in dkgs.iter_mut() {
+                dkg.apply_message(sender.clone(), pvss.clone())
+                    .expect("Test failed");
+            }
+        }
+    }
+
+    /// Test that a DKG still sharing after the dealing deadline fails,
+    /// and stops accepting transcripts
+    #[test]
+    fn test_dealing_timeout() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dkg(0);
+        dkg.set_deadlines(DEADLINES).expect("Test failed");
+        assert_eq!(dkg.check_deadlines(5), None);
+        assert_eq!(dkg.check_deadlines(6), Some(FailureReason::DealingTimeout));
+        assert!(matches!(
+            dkg.state,
+            DkgState::Invalid {
+                reason: FailureReason::DealingTimeout
+            }
+        ));
+
+        let pvss = setup_dkg(1).share(rng).expect("Test failed");
+        let sender = dkg.validators[1].validator.clone();
+        assert!(matches!(
+            dkg.verify_message(&sender, &pvss),
+            Err(DkgError::WrongState(_))
+        ));
+        // the failure is final
+        assert_eq!(
+            dkg.check_deadlines(11),
+            Some(FailureReason::DealingTimeout)
+        );
+    }
+
+    /// Test that a dealt DKG fails after the aggregation deadline, but
+    /// that a DKG that succeeded never fails
+    #[test]
+    fn test_aggregation_timeout() {
+        let mut dkg = setup_dealt_dkg();
+        dkg.set_deadlines(DEADLINES).expect("Test failed");
+        assert_eq!(dkg.check_deadlines(10), None);
+        assert_eq!(
+            dkg.check_deadlines(11),
+            Some(FailureReason::AggregationTimeout)
+        );
+        let snapshot = dkg.save_snapshot().expect("Test failed");
+        let validators = dkg
+            .validators
+            .iter()
+            .map(|v| v.validator.clone())
+            .collect::<Vec<_>>();
+        let restored = PubliclyVerifiableDkg::<EllipticCurve>::load_snapshot(
+            &snapshot,
+            &dkg.params,
+            &validators,
+        )
+        .expect("Test failed");
+        assert_eq!(restored.deadlines, DEADLINES);
+        assert!(matches!(
+            restored.state,
+            DkgState::Invalid {
+                reason: FailureReason::AggregationTimeout
+            }
+        ));
+
+        let mut dkg = setup_finished_dkgs(2, 4, &[1, 1, 1, 1]).remove(0);
+        dkg.set_deadlines(DEADLINES).expect("Test failed");
+        assert_eq!(dkg.check_deadlines(100), None);
+
+        assert!(matches!(
+            dkg.set_deadlines(Deadlines {
+                dealing: Some(10),
+                aggregation: Some(5),
+            }),
+            Err(DkgError::Params(ParamsError::DeadlineOrder {
+                dealing: 10,
+                aggregation: 5,
+            }))
+        ));
+    }
+
+    /// Test that a failed DKG restarts under a new session id without
+    /// the faulty and the absent dealers, and runs to completion
+    #[test]
+    fn test_restart_excludes_bad_dealers() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkgs = (0..4).map(setup_dkg).collect::<Vec<_>>();
+        for dkg in dkgs.iter_mut() {
+            dkg.set_deadlines(DEADLINES).expect("Test failed");
+        }
+        assert!(matches!(dkgs[0].restart(1), Err(DkgError::WrongState(_))));
+
+        // dealer 2 is proven faulty and dealer 3 never deals
+        deal(&mut dkgs, &[0, 1, 2]);
+        for dkg in dkgs.iter_mut() {
+            dkg.vss.remove(&2);
+            dkg.faulty_dealers.insert(2);
+            assert_eq!(
+                dkg.check_deadlines(11),
+                Some(FailureReason::AggregationTimeout)
+            );
+            assert_eq!(dkg.excluded_dealers(), [2, 3].into());
+        }
+        assert!(matches!(
+            dkgs[0].restart(0),
+            Err(DkgError::Params(ParamsError::SessionIdReused(0)))
+        ));
+        assert!(matches!(
+            dkgs[3].restart(1),
+            Err(DkgError::NotInValidatorSet)
+        ));
+
+        let mut restarted = dkgs[..2]
+            .iter()
+            .map(|dkg| dkg.restart(1).expect("Test failed"))
+            .collect::<Vec<_>>();
+        assert_eq!(restarted[1].me, 1);
+        assert_eq!(restarted[0].params.tau, 1);
+        assert_eq!(restarted[0].deadlines, DEADLINES);
+        assert_eq!(restarted[0].validators.len(), 2);
+        assert_eq!(
+            restarted[0]
+                .validators
+                .iter()
+                .map(|v| v.weight)
+                .collect::<Vec<_>>(),
+            vec![2, 2]
+        );
+
+        // the transcripts of the failed session can not be replayed
+        let stale = setup_dkg(0).share(rng).expect("Test failed");
+        let sender = restarted[0].validators[0].validator.clone();
+        assert!(restarted[1].verify_message(&sender, &stale).is_err());
+
+        deal(&mut restarted, &[0, 1]);
+        let aggregate = restarted[0].aggregate().expect("Test failed");
+        let sender = restarted[0].validators[0].validator.clone();
+        for dkg in restarted.iter_mut() {
+            dkg.verify_message(&sender, &aggregate)
+                .expect("Test failed");
+            dkg.apply_message(sender.clone(), aggregate.clone())
+                .expect("Test failed");
+            assert!(matches!(dkg.state, DkgState::Success { .. }));
+        }
+        assert_eq!(restarted[0].final_key(), restarted[1].final_key());
+    }
+}
@@ -2,6 +2,7 @@
 
 pub mod dkg;
 pub mod msg;
+pub mod signer;
 pub mod vss;
 
 pub mod primitives;
@@ -23,9 +24,12 @@ use ark_poly::{
 use ark_std::{end_timer, start_timer};
 use serde::*;
 
-use anyhow::{anyhow, Result};
+use ark_serialize::SerializationError;
+use thiserror::Error;
+
 pub use dkg::*;
 pub use msg::*;
+pub use signer::*;
 pub use vss::*;
 
 use ark_ec::msm::FixedBaseMSM;
@@ -34,6 +38,486 @@ use ark_ff::PrimeField;
 
 use measure_time::print_time;
 
+/// The errors of invalid or unsafe DKG parameters
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum ParamsError {
+    /// There are no shares to deal
+    #[error("the number of shares must be positive")]
+    ZeroShares,
+
+    /// The number of shares needed to decrypt is zero or exceeds the shares
+    #[error(
+        "{decryption_threshold} shares needed to decrypt, out of {shares_num}"
+    )]
+    DecryptionThresholdOutOfRange {
+        decryption_threshold: u32,
+        shares_num: u32,
+    },
+
+    /// The security threshold is zero or exceeds the shares, so that no
+    /// share or more than all the shares are needed to decrypt
+    #[error(
+        "security threshold {security_threshold} is not between 1 and {shares_num}"
+    )]
+    SecurityThresholdOutOfRange {
+        security_threshold: u32,
+        shares_num: u32,
+    },
+
+    /// No more than half of the shares is enough to decrypt
+    #[error("{decryption_threshold} of {shares_num} shares is not a majority")]
+    UnsafeThreshold {
+        decryption_threshold: u32,
+        shares_num: u32,
+    },
+
+    /// No PVSS transcripts may be dealt in a block
+    #[error("the dealing budget per block must be positive")]
+    ZeroDealingBudget,
+
+    /// The validators and their proofs of possession do not match
+    #[error("{proofs} proofs of possession for {validators} validators")]
+    ProofCountMismatch { validators: usize, proofs: usize },
+
+    /// The validators and their message signing keys do not match
+    #[error("{signing_keys} signing keys for {validators} validators")]
+    SigningKeyCountMismatch {
+        validators: usize,
+        signing_keys: usize,
+    },
+
+    /// The aggregation deadline precedes the dealing deadline
+    #[error(
+        "the aggregation deadline {aggregation} precedes the dealing deadline {dealing}"
+    )]
+    DeadlineOrder { dealing: u32, aggregation: u32 },
+
+    /// A restarted DKG reuses the session id of the failed DKG
+    #[error("a restarted DKG needs a new session id, not {0}")]
+    SessionIdReused(u64),
+}
+
+/// The operations of the DKG that may be unavailable, because of the
+/// state of the DKG or because it is an observer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DkgOperation {
+    Deal,
+    Aggregate,
+    VerifyMessage,
+    ApplyMessage,
+    Complain,
+    CheckDealtShares,
+    Refresh,
+    Recover,
+    HelpRecovery,
+    Handover,
+    PublicKeyShares,
+    ExportAudit,
+    DecryptionContexts,
+    SignMessage,
+    Restart,
+}
+
+impl std::fmt::Display for DkgOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Deal => "deal PVSS shares",
+            Self::Aggregate => "aggregate PVSS transcripts",
+            Self::VerifyMessage => "verify this message",
+            Self::ApplyMessage => "apply this message",
+            Self::Complain => "complain about a share",
+            Self::CheckDealtShares => "check our dealt shares",
+            Self::Refresh => "refresh the final key",
+            Self::Recover => "recover key shares",
+            Self::HelpRecovery => "help a recovery",
+            Self::Handover => "hand over the final key",
+            Self::PublicKeyShares => "compute the public key shares",
+            Self::ExportAudit => "export an audit transcript",
+            Self::DecryptionContexts => "build decryption contexts",
+            Self::SignMessage => "sign a message",
+            Self::Restart => "restart the DKG",
+        })
+    }
+}
+
+/// The errors of snapshots that are corrupted or inconsistent with the
+/// DKG session they are restored into
+#[derive(Clone, Copy, Debug, Error, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The snapshot is shorter than its header
+    #[error("snapshot is too short to hold a header")]
+    TooShort,
+
+    /// The snapshot does not start with [`SNAPSHOT_MAGIC`]
+    #[error("snapshot does not start with the magic bytes")]
+    BadMagic,
+
+    /// The header of the snapshot names another DKG session
+    #[error("snapshot does not belong to this DKG session")]
+    ForeignSession,
+
+    /// The payload does not match the hash in the header
+    #[error("snapshot payload is corrupted")]
+    Corrupted,
+
+    /// The payload has bytes after the DKG state
+    #[error("snapshot has trailing bytes")]
+    TrailingBytes,
+
+    /// The restored parameters or validator set are not those of the session
+    #[error("snapshot state does not match the DKG session")]
+    SessionMismatch,
+
+    /// The shares are not partitioned by the voting power of the validators
+    #[error("snapshot has an invalid share partition")]
+    SharePartition,
+
+    /// The evaluation domain does not match the number of shares
+    #[error("snapshot has an invalid evaluation domain")]
+    Domain,
+
+    /// The session keypair is not that of the validator of the snapshot
+    #[error("snapshot session keypair does not match this validator")]
+    KeypairMismatch,
+
+    /// The transcript of the dealer is malformed or from a faulty dealer
+    #[error("snapshot has an invalid transcript from dealer {0}")]
+    InvalidTranscript(u32),
+
+    /// The refresh is malformed, changes the key or precedes the final key
+    #[error("snapshot has an invalid refresh")]
+    InvalidRefresh,
+
+    /// A validator that refreshed is unknown, or precedes the final key
+    #[error("snapshot has an invalid refresh from validator {0}")]
+    InvalidRefreshed(u32),
+
+    /// A re-keyed validator is unknown, precedes the final key, or has
+    /// the wrong number of shares
+    #[error("snapshot has an invalid re-keying of validator {0}")]
+    InvalidRekeyed(u32),
+
+    /// The recovery names unknown validators or precedes the final key
+    #[error("snapshot has an invalid recovery")]
+    InvalidRecovery,
+
+    /// The accumulated shares are not the weight of the dealers
+    #[error(
+        "snapshot accumulated {accumulated} shares, but its transcripts deal {dealt}"
+    )]
+    AccumulatedShares { accumulated: u32, dealt: u32 },
+}
+
+/// The errors returned by the DKG, identifying the faulty party where
+/// there is one, so that callers can decide to slash, ignore or retry
+#[derive(Debug, Error)]
+pub enum DkgError {
+    // Setup of the DKG
+    /// The parameters of the DKG are invalid or unsafe
+    #[error("invalid DKG parameters: {0}")]
+    Params(#[from] ParamsError),
+
+    /// The evaluation domain can not hold the number of shares
+    #[error("invalid evaluation domain for {0} shares")]
+    InvalidDomain(u32),
+
+    /// The validator creating the DKG is not in the validator set
+    #[error("this validator is not in the validator set")]
+    NotInValidatorSet,
+
+    /// A validator did not prove possession of its session key
+    #[error("invalid proof of possession of the session key of {0}")]
+    InvalidProofOfPossession(String),
+
+    /// The total voting power of the validator set does not fit in a u128
+    #[error("total voting power overflows")]
+    VotingPowerOverflow,
+
+    /// The validator set has no voting power
+    #[error("total voting power is zero")]
+    ZeroVotingPower,
+
+    /// The keypair does not match the session key of this validator
+    #[error("keypair does not match the session key of this validator")]
+    KeypairMismatch,
+
+    /// The session decryption key is zero and can not be inverted
+    #[error("session decryption key is zero")]
+    ZeroSessionKey,
+
+    // State of the DKG
+    /// The DKG is not in the state required by the operation
+    #[error("DKG is not in the state to {0}")]
+    WrongState(DkgOperation),
+
+    /// The operation needs a local identity, which an observer does not have
+    #[error("an observer of the DKG can not {0}")]
+    Observer(DkgOperation),
+
+    /// The sender of a message, or a dealer, is not in the validator set
+    #[error("unknown validator {0}")]
+    UnknownValidator(String),
+
+    // Dealing and aggregation
+    /// The dealer has already posted a transcript
+    #[error("duplicate PVSS transcript from dealer {0}")]
+    DuplicateDealer(u32),
+
+    /// The transcript of the dealer was dropped after a valid complaint
+    #[error("faulty dealer {0}")]
+    FaultyDealer(u32),
+
+    /// The PVSS transcript of the dealer failed verification
+    #[error("invalid PVSS transcript from dealer {0}")]
+    InvalidTranscript(u32),
+
+    /// The PVSS transcript of the dealer does not have the shape of a
+    /// transcript of this DKG
+    #[error("malformed PVSS transcript from dealer {0}")]
+    MalformedTranscript(u32),
+
+    /// Not enough shares were dealt for the operation
+    #[error("insufficient shares: received {received}, required {required}")]
+    InsufficientShares { received: u32, required: u32 },
+
+    /// The aggregated commitment does not match the dealt transcripts
+    #[error("aggregated PVSS transcript does not match the dealt transcripts")]
+    AggregationMismatch,
+
+    /// The aggregated transcript does not have the shape of a transcript
+    /// of this DKG
+    #[error("malformed aggregated PVSS transcript")]
+    MalformedAggregation,
+
+    /// Shares of the aggregated transcript failed verification
+    #[error(transparent)]
+    AggregationFailed(#[from] VerificationReport),
+
+    /// The announced final key does not match the dealt transcripts
+    #[error("final key does not match the aggregated PVSS transcript")]
+    FinalKeyMismatch,
+
+    // Complaints
+    /// A complaint targets a dealer with no transcript
+    #[error("complaint against dealer {0}, which has no transcript")]
+    MissingTranscript(u32),
+
+    /// A complaint is about a share the complainer does not hold
+    #[error(
+        "complaint about share {share_index}, not held by validator {complainer}"
+    )]
+    ComplaintShareNotHeld { complainer: u32, share_index: usize },
+
+    /// The complainer did not prove the decryption of the disputed share
+    #[error("invalid proof of share decryption from validator {0}")]
+    InvalidDecryptionProof(u32),
+
+    /// A complaint is against a correctly dealt share
+    #[error(
+        "complaint against share {share_index}, correctly dealt by dealer {dealer}"
+    )]
+    UnfoundedComplaint { dealer: u32, share_index: usize },
+
+    // Refresh
+    /// The validator has already refreshed in the current refresh round
+    #[error("duplicate refresh transcript from validator {0}")]
+    DuplicateRefresh(u32),
+
+    /// A refresh transcript was posted while a recovery is in progress,
+    /// which relies on the key shares staying the same
+    #[error("refresh transcript from validator {0} during a recovery")]
+    RefreshDuringRecovery(u32),
+
+    /// The refresh transcript does not have the shape of a transcript
+    /// of this DKG
+    #[error("malformed refresh transcript from validator {0}")]
+    MalformedRefresh(u32),
+
+    /// The refresh transcript would change the final key
+    #[error("refresh transcript from validator {0} changes the final key")]
+    RefreshChangesKey(u32),
+
+    /// The proof of knowledge or the shares of a refresh transcript failed
+    /// verification
+    #[error("invalid refresh transcript from validator {0}")]
+    InvalidRefresh(u32),
+
+    // Recovery
+    /// No recovery is in progress
+    #[error("no recovery is in progress")]
+    NoRecovery,
+
+    /// A recovery was requested while validator {0} is being recovered
+    #[error("recovery of validator {0} is already in progress")]
+    RecoveryInProgress(u32),
+
+    /// A recovery request registers the current session key of the validator
+    #[error("recovery of validator {0} must register a new session key")]
+    RecoveryKeyReused(u32),
+
+    /// The recovering validator posted a mask or shares for its own recovery
+    #[error("recovering validator {0} can not help its own recovery")]
+    RecoveringValidatorHelps(u32),
+
+    /// The validator has already posted its recovery masks
+    #[error("duplicate recovery masks from validator {0}")]
+    DuplicateRecoveryMask(u32),
+
+    /// Recovery masks were posted after enough masks were received
+    #[error("recovery masks from validator {0} after the masks are complete")]
+    RecoveryMasksComplete(u32),
+
+    /// The recovery masks of the validator failed verification
+    #[error("invalid recovery masks from validator {0}")]
+    InvalidRecoveryMask(u32),
+
+    /// Not enough recovery masks were received for the operation
+    #[error("insufficient recovery masks")]
+    InsufficientRecoveryMasks,
+
+    /// The validator has already posted its recovery shares
+    #[error("duplicate recovery shares from validator {0}")]
+    DuplicateRecoveryShares(u32),
+
+    /// The recovery shares of the validator failed verification
+    #[error("invalid recovery shares from validator {0}")]
+    InvalidRecoveryShares(u32),
+
+    /// Not enough recovery shares were received for the operation
+    #[error("insufficient recovery shares")]
+    InsufficientRecoveryShares,
+
+    /// The keypair does not match the public key of the recovery
+    #[error("keypair does not match the public key of the recovery")]
+    RecoveryKeypairMismatch,
+
+    /// A key share rebuilt from the recovery shares does not match the
+    /// commitment of the aggregated transcript
+    #[error("invalid recovered share {0}")]
+    InvalidRecoveredShare(usize),
+
+    /// A validator completed the recovery of another validator
+    #[error("validator {0} can not complete the recovery of another")]
+    RecoveryCompletedByOther(u32),
+
+    /// The re-encrypted key shares of the recovered validator failed
+    /// verification
+    #[error("invalid recovered shares from validator {0}")]
+    InvalidRecoveredShares(u32),
+
+    // Handover
+    /// The commitment of the old key of a handover does not have the
+    /// degree of the old parameters
+    #[error(
+        "commitment to the old key has {received} coefficients, expected {expected}"
+    )]
+    OldCommitmentLength { expected: usize, received: usize },
+
+    /// A resharing dealer is not in the old validator set
+    #[error("resharing dealer {0} is not in the old validator set")]
+    UnknownResharingDealer(usize),
+
+    /// A resharing dealer does not reshare one key share per share it holds
+    #[error("{received} key shares to reshare, expected {expected}")]
+    ResharingShareCount { expected: u32, received: usize },
+
+    /// A handover does not match the key of this DKG
+    #[error("handover does not match the key of this DKG")]
+    HandoverKeyMismatch,
+
+    // Audit
+    /// An audit transcript does not hold one commitment per dealer
+    #[error(
+        "audit transcript has {dealings} commitments for {dealers} dealers"
+    )]
+    AuditDealingCount { dealers: usize, dealings: usize },
+
+    /// The refresh in an audit transcript does not preserve the final key
+    #[error("invalid refresh in the audit transcript")]
+    InvalidAuditRefresh,
+
+    /// The shares of an audit transcript do not match its aggregated
+    /// commitment
+    #[error("audit transcript shares do not match the aggregated commitment")]
+    AuditSharesMismatch,
+
+    // Equivocation evidence
+    /// The messages of equivocation evidence are not distinct and in
+    /// canonical order
+    #[error("evidence messages are not distinct and in canonical order")]
+    EvidenceNotCanonical,
+
+    /// The messages of equivocation evidence have different senders
+    #[error("evidence messages have different senders {first} and {second}")]
+    EvidenceSenderMismatch { first: u32, second: u32 },
+
+    /// Equivocation evidence holds a message that is not a dealing
+    #[error("evidence message of kind {0:?} is not a dealing")]
+    EvidenceNotDealing(MessageKind),
+
+    /// The messages of equivocation evidence deal the same transcript
+    #[error("evidence messages of dealer {0} deal the same transcript")]
+    EvidenceSameTranscript(u32),
+
+    // Signed messages
+    /// A signed message has an envelope version this build can not read
+    #[error("unsupported message version {0}, expected {}", MESSAGE_VERSION)]
+    UnsupportedMessageVersion(u16),
+
+    /// A signed message from the validator belongs to another session
+    #[error("message from validator {0} belongs to another session")]
+    SessionMismatch(String),
+
+    /// The payload of a signed message does not have the kind of its
+    /// envelope
+    #[error(
+        "message of kind {envelope:?} holds a payload of kind {payload:?}"
+    )]
+    MessageKindMismatch {
+        envelope: MessageKind,
+        payload: MessageKind,
+    },
+
+    /// A message is not signed by the signing key of its sender
+    #[error("message is not signed by validator {0}")]
+    SignerMismatch(String),
+
+    /// The signature of the message of the validator is invalid, or a
+    /// batch without an invalid signature failed, naming all its senders
+    #[error("invalid message signature from validator {0}")]
+    InvalidMessageSignature(String),
+
+    /// The signature of a message is invalid
+    #[error("invalid message signature")]
+    InvalidSignature,
+
+    /// The signer of a message failed to sign it
+    #[error("message signing failed: {0}")]
+    SigningFailed(String),
+
+    /// A message could not be encoded or decoded
+    #[error("message encoding failed")]
+    MessageEncoding(#[from] bincode::Error),
+
+    // Snapshots and serialization
+    /// A snapshot is corrupted or inconsistent with the DKG session
+    #[error("invalid snapshot: {0}")]
+    InvalidSnapshot(#[from] SnapshotError),
+
+    /// A snapshot has a format version this build can not read
+    #[error(
+        "unsupported snapshot version {0}, expected at most {}",
+        SNAPSHOT_VERSION
+    )]
+    UnsupportedSnapshotVersion(u16),
+
+    /// A group element or DKG state could not be serialized or deserialized
+    #[error("serialization failed")]
+    Serialization(#[from] SerializationError),
+}
+
+pub type Result<T, E = DkgError> = std::result::Result<T, E>;
+
 #[cfg(test)]
 mod test_dkg_full {
     use super::*;
@@ -92,7 +576,7 @@ mod test_dkg_full {
     #[test]
     fn test_dkg_simple_decryption_variant() {
         let rng = &mut ark_std::test_rng();
-        let dkg = setup_dealt_dkg_with_n_validators(3, 4);
+        let dkg = setup_dealt_dkg_with_n_validators(2, 4);
 
         let msg: &[u8] = "abc".as_bytes();
         let aad: &[u8] = "my-aad".as_bytes();
@@ -135,4 +619,76 @@ mod test_dkg_full {
         .unwrap();
         assert_eq!(plaintext, msg);
     }
+
+    #[test]
+    fn test_dkg_simple_decryption_variant_non_power_of_two() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dealt_dkg_with_n_validators(2, 5);
+
+        let msg: &[u8] = "abc".as_bytes();
+        let aad: &[u8] = "my-aad".as_bytes();
+        let public_key = dkg.final_key();
+        let ciphertext = tpke::encrypt::<_, E>(msg, aad, &public_key, rng);
+
+        let aggregate = aggregate_for_decryption(&dkg);
+        let validator_keypairs = gen_n_keypairs(5);
+        let decryption_shares =
+            make_decryption_shares(&ciphertext, validator_keypairs, aggregate);
+
+        // Only the first `shares_num` points of the domain are used
+        let shares_x = &dkg
+            .domain
+            .elements()
+            .take(dkg.params.shares_num as usize)
+            .collect::<Vec<_>>();
+        let lagrange_coeffs = tpke::prepare_combine_simple::<E>(shares_x);
+
+        let shared_secret = tpke::share_combine_simple::<E>(
+            &decryption_shares,
+            &lagrange_coeffs,
+        );
+
+        let plaintext = tpke::checked_decrypt_with_shared_secret(
+            &ciphertext,
+            aad,
+            &shared_secret,
+        )
+        .unwrap();
+        assert_eq!(plaintext, msg);
+    }
+
+    #[test]
+    fn test_dkg_simple_decryption_variant_weighted() {
+        let rng = &mut ark_std::test_rng();
+        let powers = [1, 2, 5];
+        let dkg = setup_dealt_dkg_with_powers(2, 8, &powers);
+
+        let msg: &[u8] = "abc".as_bytes();
+        let aad: &[u8] = "my-aad".as_bytes();
+        let public_key = dkg.final_key();
+        let ciphertext = tpke::encrypt::<_, E>(msg, aad, &public_key, rng);
+
+        let aggregate = aggregate_for_decryption(&dkg);
+        let validator_keypairs = gen_n_keypairs(powers.len() as u32);
+        let decryption_shares =
+            make_decryption_shares(&ciphertext, validator_keypairs, aggregate);
+        // Every share held by every validator is used for decryption
+        assert_eq!(decryption_shares.len(), dkg.params.shares_num as usize);
+
+        let shares_x = &dkg.domain.elements().collect::<Vec<_>>();
+        let lagrange_coeffs = tpke::prepare_combine_simple::<E>(shares_x);
+
+        let shared_secret = tpke::share_combine_simple::<E>(
+            &decryption_shares,
+            &lagrange_coeffs,
+        );
+
+        let plaintext = tpke::checked_decrypt_with_shared_secret(
+            &ciphertext,
+            aad,
+            &shared_secret,
+        )
+        .unwrap();
+        assert_eq!(plaintext, msg);
+    }
 }
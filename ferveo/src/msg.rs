@@ -1,36 +1,658 @@
-use ed25519_dalek as ed25519;
-use ed25519_dalek::Signer;
+use ferveo_common::ExternalValidator;
 
 use crate::*;
+use ark_serialize::*;
 
-impl SignedMessage {
-    pub fn sign<M>(tau: u64, msg: &M, key: &ed25519::Keypair) -> SignedMessage
+/// The current version of the signed message envelope
+pub const MESSAGE_VERSION: u16 = 1;
+
+/// Domain separation tag of the bytes signed in a message envelope
+const MESSAGE_DOMAIN: &[u8] = b"FERVEO-DKG-MESSAGE";
+
+/// The kind of a DKG [`Message`], signed together with its payload
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    Deal,
+    Aggregate,
+    Complaint,
+    Refresh,
+    RecoveryRequest,
+    RecoveryMask,
+    RecoveryShares,
+    RecoveryComplete,
+}
+
+impl MessageKind {
+    fn from_u8(kind: u8) -> Option<Self> {
+        Some(match kind {
+            0 => MessageKind::Deal,
+            1 => MessageKind::Aggregate,
+            2 => MessageKind::Complaint,
+            3 => MessageKind::Refresh,
+            4 => MessageKind::RecoveryRequest,
+            5 => MessageKind::RecoveryMask,
+            6 => MessageKind::RecoveryShares,
+            7 => MessageKind::RecoveryComplete,
+            _ => return None,
+        })
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            MessageKind::Deal => 0,
+            MessageKind::Aggregate => 1,
+            MessageKind::Complaint => 2,
+            MessageKind::Refresh => 3,
+            MessageKind::RecoveryRequest => 4,
+            MessageKind::RecoveryMask => 5,
+            MessageKind::RecoveryShares => 6,
+            MessageKind::RecoveryComplete => 7,
+        }
+    }
+}
+
+impl<E: PairingEngine> Message<E> {
+    /// The kind of this message
+    pub fn kind(&self) -> MessageKind {
+        match self {
+            Message::Deal(_) => MessageKind::Deal,
+            Message::Aggregate(_) => MessageKind::Aggregate,
+            Message::Complaint(_) => MessageKind::Complaint,
+            Message::Refresh(_) => MessageKind::Refresh,
+            Message::RecoveryRequest(..) => MessageKind::RecoveryRequest,
+            Message::RecoveryMask(_) => MessageKind::RecoveryMask,
+            Message::RecoveryShares(_) => MessageKind::RecoveryShares,
+            Message::RecoveryComplete(_) => MessageKind::RecoveryComplete,
+        }
+    }
+}
+
+/// A DKG message signed by its sender, bound to a DKG session
+///
+/// The signature covers the envelope version, the message kind, the
+/// session id, the hash of the session parameters and validator set
+/// and the address of the sender, so a message can not be replayed in
+/// another session or attributed to another validator
+///
+/// The signature scheme `S` is ed25519 unless another [`SignatureScheme`]
+/// is given
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(bound = "")]
+pub struct SignedMessage<S: SignatureScheme = Ed25519> {
+    /// The version of the envelope
+    pub version: u16,
+    /// The kind of the message in the payload
+    pub kind: MessageKind,
+    /// The id of the session the message belongs to
+    pub tau: u64,
+    /// The [`session_hash`] of the session the message belongs to
+    pub session: [u8; 32],
+    /// The address of the validator that sent the message
+    pub sender: String,
+    payload: Vec<u8>,
+    signature: S::Signature,
+    pub signer: S::PublicKey,
+}
+
+/// The fields of a message envelope covered by its signature
+struct Envelope<'a> {
+    version: u16,
+    kind: MessageKind,
+    tau: u64,
+    session: &'a [u8; 32],
+    sender: &'a str,
+    payload: &'a [u8],
+}
+
+impl Envelope<'_> {
+    /// The bytes covered by the signature
+    fn signed_bytes(&self) -> Vec<u8> {
+        let mut bytes = MESSAGE_DOMAIN.to_vec();
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.push(self.kind.to_u8());
+        bytes.extend_from_slice(&self.tau.to_le_bytes());
+        bytes.extend_from_slice(self.session);
+        bytes.extend_from_slice(&(self.sender.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(self.sender.as_bytes());
+        bytes.extend_from_slice(self.payload);
+        bytes
+    }
+}
+
+impl<S: SignatureScheme> SignedMessage<S> {
+    /// Sign `msg` with `signer` as sent by `sender` in the session given
+    /// by `params` and `validators`
+    pub fn sign<E, K>(
+        params: &Params,
+        validators: &[ExternalValidator<E>],
+        sender: &str,
+        msg: &Message<E>,
+        signer: &K,
+    ) -> Result<Self>
     where
-        M: Serialize,
+        E: PairingEngine,
+        K: MessageSigner<Scheme = S>,
     {
         print_time!("Signing Message");
-        let msg_bytes = bincode::serialize(&(tau, msg)).unwrap();
-        let signature = key.sign(&msg_bytes);
-        SignedMessage {
-            msg_bytes,
+        let session = session_hash(params, validators)?;
+        let payload = bincode::serialize(msg)?;
+        let signature = signer.sign(
+            &Envelope {
+                version: MESSAGE_VERSION,
+                kind: msg.kind(),
+                tau: params.tau,
+                session: &session,
+                sender,
+                payload: &payload,
+            }
+            .signed_bytes(),
+        )?;
+        Ok(SignedMessage {
+            version: MESSAGE_VERSION,
+            kind: msg.kind(),
+            tau: params.tau,
+            session,
+            sender: sender.to_string(),
+            payload,
             signature,
-            signer: key.public,
+            signer: signer.public_key(),
+        })
+    }
+
+    /// The bytes covered by the signature: every field of the envelope
+    /// but the signature and the signer
+    fn signed_bytes(&self) -> Vec<u8> {
+        Envelope {
+            version: self.version,
+            kind: self.kind,
+            tau: self.tau,
+            session: &self.session,
+            sender: &self.sender,
+            payload: &self.payload,
         }
+        .signed_bytes()
     }
-    pub fn verify<'de, M>(&'de self) -> Result<(u64, M)>
-    where
-        M: Deserialize<'de>,
-    {
-        print_time!("Verifying Message");
-        self.signer
-            .verify_strict(&self.msg_bytes, &self.signature)?;
-        bincode::deserialize::<'de, _>(&self.msg_bytes).map_err(|e| e.into()) //TODO: handle error
+
+    /// Check that this message belongs to the session of `params` with
+    /// session hash `session`, and that it is signed with the signing key
+    /// of its sender in `validators`. The signature is not checked
+    ///
+    /// Returns the position of the sender in the validator set
+    pub(crate) fn check_sender<E: PairingEngine>(
+        &self,
+        params: &Params,
+        session: &[u8; 32],
+        validators: &[ExternalValidator<E>],
+        signing_keys: &[S::PublicKey],
+    ) -> Result<usize> {
+        if self.version != MESSAGE_VERSION {
+            return Err(DkgError::UnsupportedMessageVersion(self.version));
+        }
+        if self.tau != params.tau || &self.session != session {
+            return Err(DkgError::SessionMismatch(self.sender.clone()));
+        }
+        let sender = validators
+            .iter()
+            .position(|v| v.address == self.sender)
+            .ok_or_else(|| {
+            DkgError::UnknownValidator(self.sender.clone())
+        })?;
+        if signing_keys.get(sender) != Some(&self.signer) {
+            return Err(DkgError::SignerMismatch(self.sender.clone()));
+        }
+        Ok(sender)
+    }
+
+    /// Decode the message in the payload, checking that it has the
+    /// kind given in the envelope. The signature is not checked
+    pub(crate) fn decode<E: PairingEngine>(&self) -> Result<Message<E>> {
+        let msg: Message<E> = bincode::deserialize(&self.payload)?;
+        if msg.kind() != self.kind {
+            return Err(DkgError::MessageKindMismatch {
+                envelope: self.kind,
+                payload: msg.kind(),
+            });
+        }
+        Ok(msg)
+    }
+
+    /// Verify the signature of this message
+    pub fn verify(&self) -> Result<()> {
+        S::verify(&self.signer, &self.signed_bytes(), &self.signature)
+    }
+
+    /// Verify the signatures of `messages` in a single batch
+    pub fn verify_batch(messages: &[Self]) -> Result<()> {
+        print_time!("Verifying Messages");
+        let signed_bytes = messages
+            .iter()
+            .map(|m| m.signed_bytes())
+            .collect::<Vec<_>>();
+        let signed_bytes = signed_bytes
+            .iter()
+            .map(|b| b.as_slice())
+            .collect::<Vec<_>>();
+        let signatures = messages
+            .iter()
+            .map(|m| m.signature.clone())
+            .collect::<Vec<_>>();
+        let signers = messages
+            .iter()
+            .map(|m| m.signer.clone())
+            .collect::<Vec<_>>();
+        S::verify_batch(&signers, &signed_bytes, &signatures)
+    }
+}
+
+impl<S: SignatureScheme> CanonicalSerialize for SignedMessage<S> {
+    fn serialize<W: Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<(), SerializationError> {
+        CanonicalSerialize::serialize(&self.version, &mut writer)?;
+        CanonicalSerialize::serialize(&self.kind.to_u8(), &mut writer)?;
+        CanonicalSerialize::serialize(&self.tau, &mut writer)?;
+        writer.write_all(&self.session)?;
+        CanonicalSerialize::serialize(
+            &self.sender.as_bytes().to_vec(),
+            &mut writer,
+        )?;
+        CanonicalSerialize::serialize(&self.payload, &mut writer)?;
+        CanonicalSerialize::serialize(
+            &S::signature_to_bytes(&self.signature),
+            &mut writer,
+        )?;
+        CanonicalSerialize::serialize(
+            &S::public_key_to_bytes(&self.signer),
+            &mut writer,
+        )
+    }
+
+    fn serialized_size(&self) -> usize {
+        self.version.serialized_size()
+            + 1
+            + self.tau.serialized_size()
+            + self.session.len()
+            + self.sender.as_bytes().to_vec().serialized_size()
+            + self.payload.serialized_size()
+            + S::signature_to_bytes(&self.signature).serialized_size()
+            + S::public_key_to_bytes(&self.signer).serialized_size()
+    }
+}
+
+impl<S: SignatureScheme> CanonicalDeserialize for SignedMessage<S> {
+    fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+        let version = <u16 as CanonicalDeserialize>::deserialize(&mut reader)?;
+        let kind = MessageKind::from_u8(
+            <u8 as CanonicalDeserialize>::deserialize(&mut reader)?,
+        )
+        .ok_or(SerializationError::InvalidData)?;
+        let tau = <u64 as CanonicalDeserialize>::deserialize(&mut reader)?;
+        let mut session = [0u8; 32];
+        reader.read_exact(&mut session)?;
+        let sender = String::from_utf8(
+            <Vec<u8> as CanonicalDeserialize>::deserialize(&mut reader)?,
+        )
+        .map_err(|_| SerializationError::InvalidData)?;
+        let payload =
+            <Vec<u8> as CanonicalDeserialize>::deserialize(&mut reader)?;
+        let signature = S::signature_from_bytes(
+            &<Vec<u8> as CanonicalDeserialize>::deserialize(&mut reader)?,
+        )
+        .ok_or(SerializationError::InvalidData)?;
+        let signer = S::public_key_from_bytes(
+            &<Vec<u8> as CanonicalDeserialize>::deserialize(&mut reader)?,
+        )
+        .ok_or(SerializationError::InvalidData)?;
+        Ok(Self {
+            version,
+            kind,
+            tau,
+            session,
+            sender,
+            payload,
+            signature,
+            signer,
+        })
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-pub struct SignedMessage {
-    msg_bytes: Vec<u8>,
-    signature: ed25519::Signature,
-    pub signer: ed25519::PublicKey,
+impl<E: PairingEngine> PubliclyVerifiableDkg<E> {
+    /// Sign `msg` with `signer` as sent by this validator in this DKG
+    /// session
+    pub fn sign_message<K: MessageSigner>(
+        &self,
+        msg: &Message<E>,
+        signer: &K,
+    ) -> Result<SignedMessage<K::Scheme>> {
+        self.check_participant(DkgOperation::SignMessage)?;
+        SignedMessage::sign(
+            &self.params,
+            &self.external_validators(),
+            &self.validators[self.me].validator.address,
+            msg,
+            signer,
+        )
+    }
+
+    /// Open signed messages of this DKG session, whose signatures are
+    /// verified in a single batch. If the batch fails, the signatures are
+    /// verified one by one to report the sender of the first invalid one,
+    /// or every sender if each signature verifies on its own
+    ///
+    /// `signing_keys` are the message signing keys of the validators, in
+    /// the order of the validator set. Returns the sender and the message
+    /// of each envelope, to pass to [`Self::verify_message`] and
+    /// [`Self::apply_message`]
+    pub fn open_messages<S: SignatureScheme>(
+        &self,
+        messages: &[SignedMessage<S>],
+        signing_keys: &[S::PublicKey],
+    ) -> Result<Vec<(ExternalValidator<E>, Message<E>)>> {
+        if signing_keys.len() != self.validators.len() {
+            return Err(DkgError::Params(
+                ParamsError::SigningKeyCountMismatch {
+                    validators: self.validators.len(),
+                    signing_keys: signing_keys.len(),
+                },
+            ));
+        }
+        let validators = self.external_validators();
+        let session = session_hash(&self.params, &validators)?;
+        let mut opened = Vec::with_capacity(messages.len());
+        for signed in messages {
+            let sender = signed.check_sender(
+                &self.params,
+                &session,
+                &validators,
+                signing_keys,
+            )?;
+            opened.push((
+                self.validators[sender].validator.clone(),
+                signed.decode()?,
+            ));
+        }
+        if SignedMessage::verify_batch(messages).is_err() {
+            // find the culprit; a batch that fails without one is rejected
+            // all the same, since its scheme disagrees with itself
+            let culprit = match messages.iter().find(|m| m.verify().is_err()) {
+                Some(signed) => signed.sender.clone(),
+                None => messages
+                    .iter()
+                    .map(|m| m.sender.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+            return Err(DkgError::InvalidMessageSignature(culprit));
+        }
+        Ok(opened)
+    }
+
+    /// Open a signed message of this DKG session, then verify and apply it
+    pub fn apply_signed_message<S: SignatureScheme>(
+        &mut self,
+        signed: &SignedMessage<S>,
+        signing_keys: &[S::PublicKey],
+    ) -> Result<()> {
+        let (sender, msg) = self
+            .open_messages(std::slice::from_ref(signed), signing_keys)?
+            .remove(0);
+        self.verify_message(&sender, &msg)?;
+        self.apply_message(sender, msg)
+    }
+}
+
+#[cfg(test)]
+mod test_msg {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+    use ed25519_dalek as ed25519;
+
+    fn public_keys(keypairs: &[ed25519::Keypair]) -> Vec<ed25519::PublicKey> {
+        keypairs.iter().map(|k| k.public).collect()
+    }
+
+    /// Test that signed dealings are opened in a batch, and verified and
+    /// applied with the sender of their envelope
+    #[test]
+    fn test_signed_messages() {
+        let rng = &mut ark_std::test_rng();
+        let keypairs = gen_signing_keypairs(4);
+        let signing_keys = public_keys(&keypairs);
+        let mut dkg = setup_dkg(0);
+        let messages = (1..3)
+            .map(|i| {
+                let mut dealer = setup_dkg(i);
+                let pvss = dealer.share(rng).expect("Test failed");
+                dealer
+                    .sign_message(&pvss, &keypairs[i])
+                    .expect("Test failed")
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(messages[0].kind, MessageKind::Deal);
+
+        let opened = dkg
+            .open_messages(&messages, &signing_keys)
+            .expect("Test failed");
+        assert_eq!(opened[0].0, dkg.validators[1].validator);
+        assert_eq!(opened[1].0, dkg.validators[2].validator);
+
+        // the envelope survives serialization
+        let bytes = bincode::serialize(&messages[0]).expect("Test failed");
+        let signed: SignedMessage =
+            bincode::deserialize(&bytes).expect("Test failed");
+        dkg.apply_signed_message(&signed, &signing_keys)
+            .expect("Test failed");
+        assert!(dkg.vss.contains_key(&1));
+        // applying the same dealing again is rejected by the DKG
+        assert!(dkg.apply_signed_message(&signed, &signing_keys).is_err());
+    }
+
+    /// Test that a batch with an invalid signature reports the sender
+    /// of the invalid message
+    #[test]
+    fn test_signed_messages_batch_culprit() {
+        let rng = &mut ark_std::test_rng();
+        let keypairs = gen_signing_keypairs(4);
+        let signing_keys = public_keys(&keypairs);
+        let dkg = setup_dkg(0);
+        let mut messages = (1..4)
+            .map(|i| {
+                let mut dealer = setup_dkg(i);
+                let pvss = dealer.share(rng).expect("Test failed");
+                dealer
+                    .sign_message(&pvss, &keypairs[i])
+                    .expect("Test failed")
+            })
+            .collect::<Vec<_>>();
+        assert!(messages.iter().all(|m| m.verify().is_ok()));
+
+        messages[1].signature = messages[0].signature;
+        assert!(messages[1].verify().is_err());
+        assert!(matches!(
+            dkg.open_messages(&messages, &signing_keys),
+            Err(DkgError::InvalidMessageSignature(address)) if address == "validator_2"
+        ));
+    }
+
+    /// Ed25519 with a batch verification that always fails, a scheme
+    /// whose batch and single verification disagree
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    struct BatchFails;
+
+    impl SignatureScheme for BatchFails {
+        type PublicKey = ed25519::PublicKey;
+        type Signature = ed25519::Signature;
+
+        fn public_key_to_bytes(public_key: &Self::PublicKey) -> Vec<u8> {
+            Ed25519::public_key_to_bytes(public_key)
+        }
+
+        fn public_key_from_bytes(bytes: &[u8]) -> Option<Self::PublicKey> {
+            Ed25519::public_key_from_bytes(bytes)
+        }
+
+        fn signature_to_bytes(signature: &Self::Signature) -> Vec<u8> {
+            Ed25519::signature_to_bytes(signature)
+        }
+
+        fn signature_from_bytes(bytes: &[u8]) -> Option<Self::Signature> {
+            Ed25519::signature_from_bytes(bytes)
+        }
+
+        fn verify(
+            public_key: &Self::PublicKey,
+            msg: &[u8],
+            signature: &Self::Signature,
+        ) -> Result<()> {
+            Ed25519::verify(public_key, msg, signature)
+        }
+
+        fn verify_batch(
+            _public_keys: &[Self::PublicKey],
+            _msgs: &[&[u8]],
+            _signatures: &[Self::Signature],
+        ) -> Result<()> {
+            Err(DkgError::InvalidSignature)
+        }
+    }
+
+    /// An ed25519 keypair signing for the [`BatchFails`] scheme
+    struct BatchFailsSigner<'a>(&'a ed25519::Keypair);
+
+    impl MessageSigner for BatchFailsSigner<'_> {
+        type Scheme = BatchFails;
+
+        fn public_key(&self) -> ed25519::PublicKey {
+            self.0.public
+        }
+
+        fn sign(&self, msg: &[u8]) -> Result<ed25519::Signature> {
+            MessageSigner::sign(self.0, msg)
+        }
+    }
+
+    /// Test that a batch that fails is rejected even if every signature
+    /// verifies on its own, naming every sender of the batch
+    #[test]
+    fn test_signed_messages_batch_fails_without_culprit() {
+        let rng = &mut ark_std::test_rng();
+        let keypairs = gen_signing_keypairs(4);
+        let signing_keys = public_keys(&keypairs);
+        let dkg = setup_dkg(0);
+        let messages = (1..3)
+            .map(|i| {
+                let mut dealer = setup_dkg(i);
+                let pvss = dealer.share(rng).expect("Test failed");
+                dealer
+                    .sign_message(&pvss, &BatchFailsSigner(&keypairs[i]))
+                    .expect("Test failed")
+            })
+            .collect::<Vec<_>>();
+        assert!(messages.iter().all(|m| m.verify().is_ok()));
+        assert!(matches!(
+            dkg.open_messages(&messages, &signing_keys),
+            Err(DkgError::InvalidMessageSignature(senders))
+                if senders == "validator_1, validator_2"
+        ));
+    }
+
+    /// Test that forged, misattributed and replayed messages are rejected
+    #[test]
+    fn test_signed_messages_rejected() {
+        let rng = &mut ark_std::test_rng();
+        let keypairs = gen_signing_keypairs(4);
+        let signing_keys = public_keys(&keypairs);
+        let dkg = setup_dkg(0);
+        let mut dealer = setup_dkg(1);
+        let pvss = dealer.share(rng).expect("Test failed");
+        let signed = dealer
+            .sign_message(&pvss, &keypairs[1])
+            .expect("Test failed");
+        let open = |signed: &SignedMessage| {
+            dkg.open_messages(std::slice::from_ref(signed), &signing_keys)
+        };
+
+        // signed with the key of another validator
+        let forged = dealer
+            .sign_message(&pvss, &keypairs[2])
+            .expect("Test failed");
+        assert!(matches!(
+            open(&forged),
+            Err(DkgError::SignerMismatch(address)) if address == "validator_1"
+        ));
+
+        // attributed to another validator
+        let mut tampered = signed.clone();
+        tampered.sender = "validator_2".into();
+        tampered.signer = signing_keys[2];
+        assert!(matches!(
+            open(&tampered),
+            Err(DkgError::InvalidMessageSignature(address)) if address == "validator_2"
+        ));
+
+        // relabeled as another kind of message
+        let mut tampered = signed.clone();
+        tampered.kind = MessageKind::Refresh;
+        assert!(matches!(
+            open(&tampered),
+            Err(DkgError::MessageKindMismatch {
+                envelope: MessageKind::Refresh,
+                payload: MessageKind::Deal,
+            })
+        ));
+
+        let mut tampered = signed.clone();
+        tampered.version = MESSAGE_VERSION + 1;
+        assert!(matches!(
+            open(&tampered),
+            Err(DkgError::UnsupportedMessageVersion(_))
+        ));
+
+        // a message of another session can not be replayed
+        let validators = dkg.external_validators();
+        let other = SignedMessage::sign(
+            &Params {
+                tau: 1,
+                ..dkg.params
+            },
+            &validators,
+            "validator_1",
+            &pvss,
+            &keypairs[1],
+        )
+        .expect("Test failed");
+        assert!(matches!(
+            open(&other),
+            Err(DkgError::SessionMismatch(address)) if address == "validator_1"
+        ));
+        let other = SignedMessage::sign(
+            &dkg.params,
+            &validators[..3],
+            "validator_1",
+            &pvss,
+            &keypairs[1],
+        )
+        .expect("Test failed");
+        assert!(matches!(open(&other), Err(DkgError::SessionMismatch(_))));
+
+        let unknown = SignedMessage::sign(
+            &dkg.params,
+            &validators,
+            "validator_9",
+            &pvss,
+            &keypairs[1],
+        )
+        .expect("Test failed");
+        assert!(matches!(open(&unknown), Err(DkgError::UnknownValidator(_))));
+
+        assert!(open(&signed).is_ok());
+        assert!(matches!(
+            setup_dkg(0).open_messages(&[signed], &signing_keys[..3]),
+            Err(DkgError::Params(ParamsError::SigningKeyCountMismatch {
+                validators: 4,
+                signing_keys: 3,
+            }))
+        ));
+    }
 }
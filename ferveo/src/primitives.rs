@@ -1,5 +1,17 @@
+use ark_ff::PrimeField;
+
 pub fn batch_to_projective<A: ark_ec::AffineCurve>(
     p: &[A],
 ) -> Vec<A::Projective> {
     p.iter().map(|a| a.into_projective()).collect::<Vec<_>>()
 }
+
+/// Hash the `input` bytes to a scalar field element, under the
+/// domain separation tag `domain`
+pub fn hash_to_scalar<F: PrimeField>(domain: &[u8], input: &[u8]) -> F {
+    let mut state = blake2b_simd::Params::new().hash_length(64).to_state();
+    state.update(&(domain.len() as u64).to_le_bytes());
+    state.update(domain);
+    state.update(input);
+    F::from_le_bytes_mod_order(state.finalize().as_bytes())
+}
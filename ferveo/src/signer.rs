@@ -0,0 +1,337 @@
+use ed25519_dalek as ed25519;
+use serde::de::DeserializeOwned;
+
+use crate::*;
+
+/// A signature scheme for signed DKG messages
+pub trait SignatureScheme:
+    Copy + Clone + std::fmt::Debug + PartialEq + Eq
+{
+    /// The public key of a signer
+    type PublicKey: Clone
+        + std::fmt::Debug
+        + PartialEq
+        + Eq
+        + Serialize
+        + DeserializeOwned;
+    /// A signature of a message
+    type Signature: Clone
+        + std::fmt::Debug
+        + PartialEq
+        + Eq
+        + Serialize
+        + DeserializeOwned;
+
+    /// The canonical encoding of `public_key`
+    fn public_key_to_bytes(public_key: &Self::PublicKey) -> Vec<u8>;
+    /// Decode a public key, or `None` if `bytes` is not a valid encoding
+    fn public_key_from_bytes(bytes: &[u8]) -> Option<Self::PublicKey>;
+    /// The canonical encoding of `signature`
+    fn signature_to_bytes(signature: &Self::Signature) -> Vec<u8>;
+    /// Decode a signature, or `None` if `bytes` is not a valid encoding
+    fn signature_from_bytes(bytes: &[u8]) -> Option<Self::Signature>;
+
+    /// Verify that `signature` is a signature of `msg` by `public_key`
+    fn verify(
+        public_key: &Self::PublicKey,
+        msg: &[u8],
+        signature: &Self::Signature,
+    ) -> Result<()>;
+
+    /// Verify the signatures of `msgs` by `public_keys` in a batch, which
+    /// fails if the slices have different lengths. Schemes without batch
+    /// verification verify the signatures one by one
+    fn verify_batch(
+        public_keys: &[Self::PublicKey],
+        msgs: &[&[u8]],
+        signatures: &[Self::Signature],
+    ) -> Result<()> {
+        if public_keys.len() != msgs.len() || msgs.len() != signatures.len() {
+            return Err(DkgError::InvalidSignature);
+        }
+        public_keys.iter().zip(msgs).zip(signatures).try_for_each(
+            |((public_key, msg), signature)| {
+                Self::verify(public_key, msg, signature)
+            },
+        )
+    }
+}
+
+/// A signer of DKG messages
+///
+/// The signing key need not be held by this process: a signer may forward
+/// the messages to a remote signer or to a hardware wallet, and report
+/// its failures as [`DkgError::SigningFailed`]
+pub trait MessageSigner {
+    /// The signature scheme of the signer
+    type Scheme: SignatureScheme;
+
+    /// The public key of the signer
+    fn public_key(&self) -> <Self::Scheme as SignatureScheme>::PublicKey;
+
+    /// Sign `msg`
+    fn sign(
+        &self,
+        msg: &[u8],
+    ) -> Result<<Self::Scheme as SignatureScheme>::Signature>;
+}
+
+/// The ed25519 signature scheme, the default scheme of signed messages
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Ed25519;
+
+impl SignatureScheme for Ed25519 {
+    type PublicKey = ed25519::PublicKey;
+    type Signature = ed25519::Signature;
+
+    fn public_key_to_bytes(public_key: &Self::PublicKey) -> Vec<u8> {
+        public_key.as_bytes().to_vec()
+    }
+
+    fn public_key_from_bytes(bytes: &[u8]) -> Option<Self::PublicKey> {
+        ed25519::PublicKey::from_bytes(bytes).ok()
+    }
+
+    fn signature_to_bytes(signature: &Self::Signature) -> Vec<u8> {
+        signature.to_bytes().to_vec()
+    }
+
+    fn signature_from_bytes(bytes: &[u8]) -> Option<Self::Signature> {
+        ed25519::Signature::from_bytes(bytes).ok()
+    }
+
+    fn verify(
+        public_key: &Self::PublicKey,
+        msg: &[u8],
+        signature: &Self::Signature,
+    ) -> Result<()> {
+        public_key
+            .verify_strict(msg, signature)
+            .map_err(|_| DkgError::InvalidSignature)
+    }
+
+    /// The batch equation of ed25519-dalek is not strict: it accepts small
+    /// order keys and signature points that [`Self::verify`] rejects. Every
+    /// signature of a valid batch is then checked with `verify_strict`, so
+    /// that a batch verifies if and only if each of its signatures does
+    fn verify_batch(
+        public_keys: &[Self::PublicKey],
+        msgs: &[&[u8]],
+        signatures: &[Self::Signature],
+    ) -> Result<()> {
+        ed25519::verify_batch(msgs, signatures, public_keys)
+            .map_err(|_| DkgError::InvalidSignature)?;
+        public_keys.iter().zip(msgs).zip(signatures).try_for_each(
+            |((public_key, msg), signature)| {
+                Self::verify(public_key, msg, signature)
+            },
+        )
+    }
+}
+
+impl MessageSigner for ed25519::Keypair {
+    type Scheme = Ed25519;
+
+    fn public_key(&self) -> ed25519::PublicKey {
+        self.public
+    }
+
+    fn sign(&self, msg: &[u8]) -> Result<ed25519::Signature> {
+        Ok(ed25519::Signer::sign(self, msg))
+    }
+}
+
+#[cfg(test)]
+mod test_signer {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+    use std::io::{Read, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Mutex;
+
+    /// A signer that holds no key, and asks a signing service over a socket
+    /// to sign messages
+    struct RemoteSigner {
+        public_key: ed25519::PublicKey,
+        socket: Mutex<TcpStream>,
+    }
+
+    /// A connected pair of loopback sockets
+    fn socket_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Test failed");
+        let socket =
+            TcpStream::connect(listener.local_addr().expect("Test failed"))
+                .expect("Test failed");
+        let (service, _) = listener.accept().expect("Test failed");
+        (socket, service)
+    }
+
+    impl RemoteSigner {
+        /// Spawn a signing service holding `keypair`, and connect to it
+        fn spawn(keypair: ed25519::Keypair) -> Self {
+            let public_key = keypair.public;
+            let (socket, mut service) = socket_pair();
+            std::thread::spawn(move || {
+                let mut len = [0u8; 8];
+                while service.read_exact(&mut len).is_ok() {
+                    let mut msg = vec![0u8; u64::from_le_bytes(len) as usize];
+                    service.read_exact(&mut msg).expect("Test failed");
+                    let signature = ed25519::Signer::sign(&keypair, &msg);
+                    service
+                        .write_all(&signature.to_bytes())
+                        .expect("Test failed");
+                }
+            });
+            Self {
+                public_key,
+                socket: Mutex::new(socket),
+            }
+        }
+    }
+
+    impl MessageSigner for RemoteSigner {
+        type Scheme = Ed25519;
+
+        fn public_key(&self) -> ed25519::PublicKey {
+            self.public_key
+        }
+
+        fn sign(&self, msg: &[u8]) -> Result<ed25519::Signature> {
+            let mut socket = self.socket.lock().expect("Test failed");
+            let mut signature = [0u8; ed25519::SIGNATURE_LENGTH];
+            socket
+                .write_all(&(msg.len() as u64).to_le_bytes())
+                .and_then(|_| socket.write_all(msg))
+                .and_then(|_| socket.read_exact(&mut signature))
+                .map_err(|e| DkgError::SigningFailed(e.to_string()))?;
+            Ok(ed25519::Signature::from(signature))
+        }
+    }
+
+    /// Ed25519 without batch verification, to exercise the default
+    /// [`SignatureScheme::verify_batch`]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    struct Unbatched;
+
+    impl SignatureScheme for Unbatched {
+        type PublicKey = ed25519::PublicKey;
+        type Signature = ed25519::Signature;
+
+        fn public_key_to_bytes(public_key: &Self::PublicKey) -> Vec<u8> {
+            Ed25519::public_key_to_bytes(public_key)
+        }
+
+        fn public_key_from_bytes(bytes: &[u8]) -> Option<Self::PublicKey> {
+            Ed25519::public_key_from_bytes(bytes)
+        }
+
+        fn signature_to_bytes(signature: &Self::Signature) -> Vec<u8> {
+            Ed25519::signature_to_bytes(signature)
+        }
+
+        fn signature_from_bytes(bytes: &[u8]) -> Option<Self::Signature> {
+            Ed25519::signature_from_bytes(bytes)
+        }
+
+        fn verify(
+            public_key: &Self::PublicKey,
+            msg: &[u8],
+            signature: &Self::Signature,
+        ) -> Result<()> {
+            Ed25519::verify(public_key, msg, signature)
+        }
+    }
+
+    /// Test that batches whose public keys, messages and signatures do not
+    /// line up are rejected rather than truncated
+    #[test]
+    fn test_verify_batch_length_mismatch() {
+        let keypairs = gen_signing_keypairs(2);
+        let public_keys = keypairs.iter().map(|k| k.public).collect::<Vec<_>>();
+        let msgs: [&[u8]; 2] = [b"abc", b"def"];
+        let signatures = keypairs
+            .iter()
+            .zip(msgs)
+            .map(|(keypair, msg)| ed25519::Signer::sign(keypair, msg))
+            .collect::<Vec<_>>();
+
+        assert!(
+            Unbatched::verify_batch(&public_keys, &msgs, &signatures).is_ok()
+        );
+        assert!(matches!(
+            Unbatched::verify_batch(&public_keys, &msgs, &signatures[..1]),
+            Err(DkgError::InvalidSignature)
+        ));
+        assert!(matches!(
+            Unbatched::verify_batch(&public_keys[..1], &msgs, &signatures),
+            Err(DkgError::InvalidSignature)
+        ));
+        assert!(matches!(
+            Ed25519::verify_batch(&public_keys, &msgs, &signatures[..1]),
+            Err(DkgError::InvalidSignature)
+        ));
+    }
+
+    /// Test that a signature under a small order key, which the batch
+    /// equation of ed25519-dalek accepts, fails batch verification as it
+    /// fails strict verification
+    #[test]
+    fn test_verify_batch_strict() {
+        // the identity point, and the signature (R, s) = (identity, 0)
+        // that verifies any message under it
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        let public_key =
+            ed25519::PublicKey::from_bytes(&identity).expect("Test failed");
+        let mut signature = [0u8; ed25519::SIGNATURE_LENGTH];
+        signature[..32].copy_from_slice(&identity);
+        let signature = ed25519::Signature::from(signature);
+        let msg: &[u8] = b"abc";
+
+        assert!(
+            ed25519::verify_batch(&[msg], &[signature], &[public_key]).is_ok()
+        );
+        assert!(Ed25519::verify(&public_key, msg, &signature).is_err());
+        assert!(matches!(
+            Ed25519::verify_batch(&[public_key], &[msg], &[signature]),
+            Err(DkgError::InvalidSignature)
+        ));
+    }
+
+    /// Test that messages signed by a remote signer are opened with the
+    /// signing keys of the validators, as if signed with a local keypair
+    #[test]
+    fn test_remote_signer() {
+        let rng = &mut ark_std::test_rng();
+        let keypairs = gen_signing_keypairs(4);
+        let signing_keys =
+            keypairs.iter().map(|k| k.public).collect::<Vec<_>>();
+        let mut dealer = setup_dkg(1);
+        let pvss = dealer.share(rng).expect("Test failed");
+        let local = dealer
+            .sign_message(&pvss, &keypairs[1])
+            .expect("Test failed");
+
+        let keypair = ed25519::Keypair::from_bytes(&keypairs[1].to_bytes())
+            .expect("Test failed");
+        let remote = RemoteSigner::spawn(keypair);
+        let signed = dealer.sign_message(&pvss, &remote).expect("Test failed");
+        // ed25519 signatures are deterministic
+        assert_eq!(signed, local);
+
+        let mut dkg = setup_dkg(0);
+        dkg.apply_signed_message(&signed, &signing_keys)
+            .expect("Test failed");
+        assert!(dkg.vss.contains_key(&1));
+
+        // the failures of the signer are reported
+        let remote = RemoteSigner {
+            public_key: signing_keys[1],
+            socket: Mutex::new(socket_pair().0),
+        };
+        assert!(matches!(
+            dealer.sign_message(&pvss, &remote),
+            Err(DkgError::SigningFailed(_))
+        ));
+    }
+}
@@ -1,6 +1,8 @@
+pub mod batch;
 pub mod pvss;
 
 use crate::*;
+pub use batch::*;
 pub use pvss::*;
 
 /// The possible States of a VSS instance
@@ -0,0 +1,213 @@
+use crate::*;
+use ark_ec::msm::VariableBaseMSM;
+use ark_ff::UniformRand;
+use ferveo_common::ExternalValidator;
+
+/// Verify the shares of the PVSS `transcripts` against their commitments
+/// at once, as [`PubliclyVerifiableSS::verify_full`] does for one transcript
+///
+/// Each check e(G, Y_j) = e(A_j, ek_i) is weighted by a random scalar r_j
+/// drawn from `rng` and the checks are combined into the multi-pairing
+/// e(-G, \sum_j r_j Y_j) \prod_i e(\sum_{j \in i} r_j A_j, ek_i) = 1,
+/// which takes one pairing per validator instead of two per share.
+/// If any share is invalid, the product differs from one except with
+/// negligible probability
+pub fn batch_verify_full<E: PairingEngine, T, R: Rng>(
+    dkg: &PubliclyVerifiableDkg<E>,
+    transcripts: &[&PubliclyVerifiableSS<E, T>],
+    rng: &mut R,
+) -> bool {
+    print_time!("PVSS batch verify_full");
+    // a transcript with missing or extra shares fails, like in `verify_full`
+    if transcripts.iter().any(|pvss| !pvss.has_share_shape(dkg)) {
+        return false;
+    }
+
+    let mut share_bases = vec![];
+    let mut share_scalars = vec![];
+    let mut commitment_bases = vec![vec![]; dkg.validators.len()];
+    let mut commitment_scalars = vec![vec![]; dkg.validators.len()];
+    for pvss in transcripts {
+        // A_j = g^{\phi(\omega_j)}, the commitment to each share
+        let mut commitment = batch_to_projective(&pvss.coeffs);
+        dkg.domain.fft_in_place(&mut commitment);
+        let commitment =
+            E::G1Projective::batch_normalization_into_affine(&commitment);
+        for (i, (validator, shares)) in
+            dkg.validators.iter().zip(pvss.shares.iter()).enumerate()
+        {
+            for (share_index, y) in
+                (validator.share_start..validator.share_end).zip(shares)
+            {
+                let r = E::Fr::rand(rng).into_repr();
+                share_bases.push(*y);
+                share_scalars.push(r);
+                commitment_bases[i].push(commitment[share_index]);
+                commitment_scalars[i].push(r);
+            }
+        }
+    }
+
+    let mut pairs = vec![(
+        E::G1Prepared::from((-dkg.pvss_params.g).into_affine()),
+        E::G2Prepared::from(
+            VariableBaseMSM::multi_scalar_mul(&share_bases, &share_scalars)
+                .into_affine(),
+        ),
+    )];
+    pairs.extend((0..dkg.validators.len()).map(|i| {
+        (
+            E::G1Prepared::from(
+                VariableBaseMSM::multi_scalar_mul(
+                    &commitment_bases[i],
+                    &commitment_scalars[i],
+                )
+                .into_affine(),
+            ),
+            E::G2Prepared::from(dkg.session_key(i).encryption_key),
+        )
+    }));
+    E::product_of_pairings(&pairs) == E::Fqk::one()
+}
+
+/// Find the shares of the PVSS `transcripts` that fail verification,
+/// one list per transcript. The transcripts are verified in a batch
+/// first, and only if the batch fails are the shares checked one by
+/// one to find the culprits
+pub fn batch_failed_shares<E: PairingEngine, T, R: Rng>(
+    dkg: &PubliclyVerifiableDkg<E>,
+    transcripts: &[&PubliclyVerifiableSS<E, T>],
+    rng: &mut R,
+) -> Vec<Vec<FailedShare>> {
+    if batch_verify_full(dkg, transcripts, rng) {
+        vec![vec![]; transcripts.len()]
+    } else {
+        transcripts
+            .iter()
+            .map(|pvss| pvss.failed_shares(dkg))
+            .collect()
+    }
+}
+
+/// Verify the proofs of knowledge of the PVSS transcripts `deals`, each
+/// with its dealer, at once, as [`PubliclyVerifiableSS::verify_optimistic`]
+/// does for one transcript
+///
+/// The checks e(F_0, H(m)) = e(G, sigma) are weighted by random scalars
+/// r drawn from `rng` and combined into the multi-pairing
+/// \prod e(r F_0, H(m)) e(-G, \sum r sigma) = 1
+pub fn batch_verify_optimistic<E: PairingEngine, R: Rng>(
+    dkg: &PubliclyVerifiableDkg<E>,
+    deals: &[(&ExternalValidator<E>, &Pvss<E>)],
+    rng: &mut R,
+) -> bool {
+    print_time!("PVSS batch verify_optimistic");
+    let mut sigma = E::G2Projective::zero();
+    let mut pairs = Vec::with_capacity(deals.len() + 1);
+    for (dealer, pvss) in deals {
+        let f_0 = match pvss.coeffs.first() {
+            Some(f_0) => f_0,
+            None => return false,
+        };
+        let r = E::Fr::rand(rng);
+        sigma += pvss.sigma.mul(r);
+        pairs.push((
+            E::G1Prepared::from(f_0.mul(r).into_affine()),
+            E::G2Prepared::from(pok_message::<E>(
+                dealer,
+                dkg.params.tau,
+                &pvss.coeffs,
+            )),
+        ));
+    }
+    pairs.push((
+        E::G1Prepared::from((-dkg.pvss_params.g).into_affine()),
+        E::G2Prepared::from(sigma.into_affine()),
+    ));
+    E::product_of_pairings(&pairs) == E::Fqk::one()
+}
+
+#[cfg(test)]
+mod test_batch {
+    use super::*;
+    use crate::dkg::pv::test_common::*;
+
+    type E = EllipticCurve;
+    type G2 = <E as PairingEngine>::G2Affine;
+
+    /// Test that valid transcripts pass the batch verification
+    /// and that the batch agrees with the per-share checks
+    #[test]
+    fn test_batch_verify_full() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dealt_dkg_with_powers(2, 8, &[1, 2, 5]);
+        let transcripts = dkg.vss.values().collect::<Vec<_>>();
+        assert!(batch_verify_full(&dkg, &transcripts, rng));
+        assert!(batch_verify_full(&dkg, &transcripts[..1], rng));
+        assert!(batch_verify_full::<E, Unaggregated, _>(&dkg, &[], rng));
+        assert!(batch_failed_shares(&dkg, &transcripts, rng)
+            .iter()
+            .all(|failures| failures.is_empty()));
+
+        let aggregate = aggregate(&dkg);
+        assert!(batch_verify_full(&dkg, &[&aggregate], rng));
+    }
+
+    /// Test that a single bad share fails the batch and that the
+    /// fallback finds the transcript and the share at fault
+    #[test]
+    fn test_batch_failed_shares_finds_culprit() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dealt_dkg_with_powers(2, 8, &[1, 2, 5]);
+        dkg.vss.get_mut(&1).unwrap().shares[2][3] = G2::zero();
+        let transcripts = dkg.vss.values().collect::<Vec<_>>();
+        assert!(!batch_verify_full(&dkg, &transcripts, rng));
+        let failures = batch_failed_shares(&dkg, &transcripts, rng);
+        assert!(failures[0].is_empty());
+        assert_eq!(
+            failures[1],
+            vec![FailedShare {
+                validator: 2,
+                share_index: 6
+            }]
+        );
+    }
+
+    /// Test that a transcript missing shares fails the batch
+    #[test]
+    fn test_batch_verify_full_malformed() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dealt_dkg();
+        dkg.vss.get_mut(&0).unwrap().shares[1].pop();
+        let transcripts = dkg.vss.values().collect::<Vec<_>>();
+        assert!(!batch_verify_full(&dkg, &transcripts, rng));
+        let failures = batch_failed_shares(&dkg, &transcripts, rng);
+        assert_eq!(
+            failures[0],
+            vec![FailedShare {
+                validator: 1,
+                share_index: 1
+            }]
+        );
+    }
+
+    /// Test that the proofs of knowledge of many transcripts
+    /// are verified in a batch, and a forged one fails it
+    #[test]
+    fn test_batch_verify_optimistic() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dealt_dkg();
+        let mut deals = dkg
+            .vss
+            .iter()
+            .map(|(dealer, pvss)| {
+                (&dkg.validators[*dealer as usize].validator, pvss)
+            })
+            .collect::<Vec<_>>();
+        assert!(batch_verify_optimistic(&dkg, &deals, rng));
+
+        // a transcript replayed by another dealer
+        deals[1].1 = deals[0].1;
+        assert!(!batch_verify_optimistic(&dkg, &deals, rng));
+    }
+}
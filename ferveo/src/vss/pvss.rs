@@ -1,5 +1,5 @@
 use crate::*;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::marker::PhantomData;
 use std::ops::Add;
 
@@ -7,13 +7,15 @@ use ark_ec::bn::G2Affine;
 use ark_ec::PairingEngine;
 use ark_ff::UniformRand;
 use ark_serialize::*;
-use ferveo_common::{Keypair, PublicKey};
-use group_threshold_cryptography::{Ciphertext, DecryptionShareSimple};
+use ferveo_common::{ExternalValidator, Keypair, PublicKey};
+use group_threshold_cryptography::{
+    hash_to_g2, make_random_polynomial_at, Ciphertext, DecryptionShareSimple,
+};
 use itertools::{zip_eq, Itertools};
 use subproductdomain::fast_multiexp;
 
 /// These are the blinded evaluations of weight shares of a single random polynomial
-pub type ShareEncryptions<E> = <E as PairingEngine>::G2Affine;
+pub type ShareEncryptions<E> = Vec<<E as PairingEngine>::G2Affine>;
 
 /// Marker struct for unaggregated PVSS transcripts
 #[derive(CanonicalSerialize, CanonicalDeserialize, Clone, Debug)]
@@ -34,29 +36,60 @@ pub type Pvss<E> = PubliclyVerifiableSS<E>;
 /// Type alias for aggregated PVSS transcripts
 pub type AggregatedPvss<E> = PubliclyVerifiableSS<E, Aggregated>;
 
+/// Domain separation tag for the message signed by the proof of knowledge
+const POK_DOMAIN: &[u8] = b"FERVEO-PVSS-POK";
+
+/// Domain separation tag for the message signed by the proof of knowledge
+/// of a refresh transcript
+const REFRESH_POK_DOMAIN: &[u8] = b"FERVEO-REFRESH-POK";
+
 /// The choice of group generators
-#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+#[derive(
+    Serialize,
+    Deserialize,
+    Clone,
+    Debug,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+)]
+#[serde(bound = "")]
 pub struct PubliclyVerifiableParams<E: PairingEngine> {
+    #[serde(with = "ferveo_common::ark_serde")]
     pub g: E::G1Projective,
+    #[serde(with = "ferveo_common::ark_serde")]
     pub h: E::G2Projective,
 }
 
 /// Each validator posts a transcript to the chain. Once enough
 /// validators have done this (their total voting power exceeds
 /// 2/3 the total), this will be aggregated into a final key
-#[derive(CanonicalSerialize, CanonicalDeserialize, Clone, Debug)]
+#[derive(
+    Serialize,
+    Deserialize,
+    CanonicalSerialize,
+    CanonicalDeserialize,
+    Clone,
+    Debug,
+)]
+#[serde(bound = "")]
 pub struct PubliclyVerifiableSS<E: PairingEngine, T = Unaggregated> {
     /// Used in Feldman commitment to the VSS polynomial, F = g^{\phi}
+    #[serde(with = "ferveo_common::ark_serde::seq")]
     pub coeffs: Vec<E::G1Affine>,
 
-    /// The shares to be dealt to each validator
+    /// The shares to be dealt to each validator, one vector of
+    /// `weight` shares per validator
+    #[serde(with = "ferveo_common::ark_serde::seq2")]
     pub shares: Vec<ShareEncryptions<E>>,
 
-    /// Proof of Knowledge
+    /// Proof of Knowledge of the secret, a signature over the dealer
+    /// address, the session id and the commitment, sigma = H(m)^s
+    #[serde(with = "ferveo_common::ark_serde")]
     pub sigma: E::G2Affine,
 
     /// Marker struct to distinguish between aggregated and
     /// non aggregated PVSS transcripts
+    #[serde(skip)]
     phantom: PhantomData<T>,
 }
 
@@ -71,12 +104,49 @@ impl<E: PairingEngine, T> PubliclyVerifiableSS<E, T> {
         rng: &mut R,
     ) -> Result<Self> {
         // Our random polynomial, \phi(x) = s + \sum_{i=1}^{t-1} a_i x^i
-        let mut phi = DensePolynomial::<E::Fr>::rand(
-            (dkg.params.shares_num - dkg.params.security_threshold) as usize,
+        let mut phi =
+            DensePolynomial::<E::Fr>::rand(dkg.params.degree() as usize, rng);
+        phi.coeffs[0] = *s; // setting the first coefficient to secret value
+        Self::from_polynomial(&phi, dkg)
+    }
+
+    /// Create a PVSS instance of a random polynomial with a zero constant
+    /// term, which refreshes the key shares without changing the final key
+    /// `dkg`: the current DKG session
+    /// `rng` a cryptographic random number generator
+    ///
+    /// As the secret is zero, sigma proves knowledge of the coefficient
+    /// of degree one instead, and binds the transcript to the dealer and
+    /// to the current refresh round
+    pub fn new_refresh<R: Rng>(
+        dkg: &PubliclyVerifiableDkg<E>,
+        rng: &mut R,
+    ) -> Result<Self> {
+        // \delta(x) = \sum_{i=1}^{t-1} a_i x^i, so that \delta(0) = 0
+        let delta = make_random_polynomial_at::<E>(
+            dkg.params.decryption_threshold() as usize,
+            &E::Fr::zero(),
             rng,
         );
-        phi.coeffs[0] = *s; // setting the first coefficient to secret value
+        let mut pvss = Self::from_polynomial(&delta, dkg)?;
+        let dealer = &dkg.validators[dkg.me].validator;
+        pvss.sigma = refresh_pok_message::<E>(
+            dealer,
+            dkg.params.tau,
+            dkg.refresh_round,
+            &pvss.coeffs,
+        )
+        .mul(delta.coeffs.get(1).copied().unwrap_or_else(E::Fr::zero))
+        .into();
+        Ok(pvss)
+    }
 
+    /// Create a PVSS instance sharing the polynomial `phi`
+    pub(crate) fn from_polynomial(
+        phi: &DensePolynomial<E::Fr>,
+        dkg: &PubliclyVerifiableDkg<E>,
+    ) -> Result<Self> {
+        dkg.check_participant(DkgOperation::Deal)?;
         // Evaluations of the polynomial over the domain
         let evals = phi.evaluate_over_domain_by_ref(dkg.domain);
         // commitment to coeffs, F_i
@@ -84,24 +154,26 @@ impl<E: PairingEngine, T> PubliclyVerifiableSS<E, T> {
         let shares = dkg
             .validators
             .iter()
-            .map(|val| {
-                // ek_{i}^{eval_i}, i = validator index
+            .enumerate()
+            .map(|(i, val)| {
+                // ek_{i}^{eval_j}, j in the share range of validator i
                 fast_multiexp(
-                    // &evals.evals[i..i] = &evals.evals[i]
-                    &[evals.evals[val.share_index]], // one share per validator
-                    val.validator.public_key.encryption_key.into_projective(),
-                )[0]
+                    &evals.evals[val.share_start..val.share_end],
+                    dkg.session_key(i).encryption_key.into_projective(),
+                )
             })
             .collect::<Vec<ShareEncryptions<E>>>();
         if shares.len() != dkg.validators.len() {
-            return Err(anyhow!(
-                "Not all validator session keys have been announced"
-            ));
+            return Err(DkgError::WrongState(DkgOperation::Deal));
         }
         // phi.zeroize(); // TODO zeroize?
-        // TODO: Cross check proof of knowledge check with the whitepaper; this check proves that there is a relationship between the secret and the pvss transcript
-        // Sigma is a proof of knowledge of the secret, sigma = h^s
-        let sigma = E::G2Affine::prime_subgroup_generator().mul(*s).into(); //todo hash to curve
+        // Sigma is a proof of knowledge of the secret, sigma = H(m)^s, where
+        // m binds the proof to this dealer, session and commitment so that
+        // it can not be replayed by another dealer or in another session
+        let dealer = &dkg.validators[dkg.me].validator;
+        let sigma = pok_message::<E>(dealer, dkg.params.tau, &coeffs)
+            .mul(phi.coeffs[0])
+            .into();
         let vss = Self {
             coeffs,
             shares,
@@ -111,84 +183,329 @@ impl<E: PairingEngine, T> PubliclyVerifiableSS<E, T> {
         Ok(vss)
     }
 
-    /// Verify the pvss transcript from a validator. This is not the full check,
-    /// i.e. we optimistically do not check the commitment. This is deferred
-    /// until the aggregation step
-    pub fn verify_optimistic(&self) -> bool {
-        // We're only checking the proof of knowledge here, sigma ?= h^s
-        // "Does the first coefficient of the secret polynomial match the proof of knowledge?"
-        E::pairing(
-            self.coeffs[0].into_projective(), // F_0 = g^s
-            E::G2Affine::prime_subgroup_generator(), // h
-        ) == E::pairing(
-            E::G1Affine::prime_subgroup_generator(), // g
-            self.sigma,                              // h^s
-        )
-    }
-
     /// Part of checking the validity of an aggregated PVSS transcript
     ///
     /// If aggregation fails, a validator needs to know that their pvss
     /// transcript was at fault so that the can issue a new one. This
     /// function may also be used for that purpose.
+    ///
+    /// Every share is checked on its own; use [`batch_verify_full`] to
+    /// check the shares at once with randomness from a caller's `rng`
     pub fn verify_full(&self, dkg: &PubliclyVerifiableDkg<E>) -> bool {
+        self.has_share_shape(dkg) && self.failed_shares(dkg).is_empty()
+    }
+
+    /// Returns if the transcript commits to a polynomial of the degree
+    /// of the DKG and deals each validator exactly one share per unit of
+    /// weight
+    pub fn is_well_formed(&self, dkg: &PubliclyVerifiableDkg<E>) -> bool {
+        self.coeffs.len() == dkg.params.decryption_threshold() as usize
+            && self.has_share_shape(dkg)
+    }
+
+    /// Returns if the transcript deals each validator exactly one share
+    /// per unit of weight, whatever the degree of its commitment
+    pub(crate) fn has_share_shape(
+        &self,
+        dkg: &PubliclyVerifiableDkg<E>,
+    ) -> bool {
+        self.shares.len() == dkg.validators.len()
+            && self
+                .shares
+                .iter()
+                .zip(dkg.validators.iter())
+                .all(|(shares, v)| shares.len() == v.weight as usize)
+    }
+
+    /// Check every share of the transcript against the commitment and
+    /// return the shares that failed, including shares that are missing
+    /// from a malformed transcript
+    pub fn failed_shares(
+        &self,
+        dkg: &PubliclyVerifiableDkg<E>,
+    ) -> Vec<FailedShare> {
         // compute the commitment
         let mut commitment = batch_to_projective(&self.coeffs);
         print_time!("commitment fft");
         dkg.domain.fft_in_place(&mut commitment);
+        let commitment = &commitment;
 
-        // Each validator checks that their share is correct
-        dkg.validators.iter().zip(self.shares.iter()).all(
-            |(validator, share)| {
+        // Each validator checks that their shares are correct
+        dkg.validators
+            .iter()
+            .enumerate()
+            .flat_map(|(validator_index, validator)| {
                 // ek is the public key of the validator
                 // TODO: Is that the ek = [dk]H key?
-                let ek = validator
-                    .validator
-                    .public_key
-                    .encryption_key
-                    .into_projective();
+                let ek = dkg.session_key(validator_index).encryption_key;
+                let shares = self.shares.get(validator_index);
                 // Validator checks checks aggregated shares against commitment
                 // TODO: Check #3 is missing
                 // See #3 in 4.2.3 section of https://eprint.iacr.org/2022/898.pdf
-                let y = *share;
-                let a = commitment[validator.share_index];
-                // We verify that e(G, Y_j) = e(A_j, ek_j) for all j
-                // See #4 in 4.2.3 section of https://eprint.iacr.org/2022/898.pdf
-                // e(G,Y) = e(A, ek)
-                E::pairing(dkg.pvss_params.g, y) == E::pairing(a, ek)
-            },
-        )
+                (validator.share_start..validator.share_end)
+                    .filter(move |share_index| {
+                        let y = shares.and_then(|shares| {
+                            shares.get(share_index - validator.share_start)
+                        });
+                        let a = commitment[*share_index];
+                        // We verify that e(G, Y_j) = e(A_j, ek_j) for all j
+                        // See #4 in 4.2.3 section of https://eprint.iacr.org/2022/898.pdf
+                        // e(G,Y) = e(A, ek)
+                        !matches!(y, Some(y) if E::pairing(dkg.pvss_params.g, *y)
+                            == E::pairing(a, ek))
+                    })
+                    .map(move |share_index| FailedShare {
+                        validator: validator_index,
+                        share_index,
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Extra method available to unaggregated PVSS transcripts
+impl<E: PairingEngine> PubliclyVerifiableSS<E, Unaggregated> {
+    /// Verify the pvss transcript from a validator. This is not the full check,
+    /// i.e. we optimistically do not check the commitment. This is deferred
+    /// until the aggregation step
+    ///
+    /// `dealer` is the validator that sent the transcript in DKG session `dkg`
+    pub fn verify_optimistic(
+        &self,
+        dkg: &PubliclyVerifiableDkg<E>,
+        dealer: &ExternalValidator<E>,
+    ) -> bool {
+        // We're only checking the proof of knowledge here, sigma ?= H(m)^s
+        verify_pok(dealer, dkg.params.tau, &self.coeffs, self.sigma)
     }
 }
 
 /// Extra method available to aggregated PVSS transcripts
 impl<E: PairingEngine, T: Aggregate> PubliclyVerifiableSS<E, T> {
-    /// Verify that this PVSS instance is a valid aggregation of
-    /// the PVSS instances, produced by [`aggregate`],
+    /// Verify that this PVSS instance is a valid aggregation of the
+    /// PVSS instances of `dealers`, produced by [`aggregate_dealers`],
     /// and received by the DKG context `dkg`
+    /// `rng` is a cryptographic random number generator
     /// Returns the total valid weight of the aggregated PVSS
-    pub fn verify_aggregation(
+    ///
+    /// The shares are checked in a batch, as in [`batch_verify_full`].
+    /// If any share of the aggregation fails to verify, the error wraps a
+    /// [`VerificationReport`] naming the faulty dealers
+    pub fn verify_aggregation<R: Rng>(
         &self,
         dkg: &PubliclyVerifiableDkg<E>,
+        dealers: &BTreeSet<u32>,
+        rng: &mut R,
     ) -> Result<u32> {
         print_time!("PVSS verify_aggregation");
-        self.verify_full(dkg);
-        // Now, we verify that the aggregated PVSS transcript is a valid aggregation
+        let (shares_total, transcripts) =
+            self.aggregated_transcripts(dkg, dealers)?;
+        // Then, we check the shares against the aggregated commitment and,
+        // if any fails, we find out which of the transcripts was at fault
+        if batch_verify_full(dkg, &[self], rng) {
+            Ok(shares_total)
+        } else {
+            Err(VerificationReport {
+                aggregate_failures: self.failed_shares(dkg),
+                faulty_dealers: Self::faulty_dealers(
+                    dealers,
+                    batch_failed_shares(dkg, &transcripts, rng),
+                ),
+            }
+            .into())
+        }
+    }
+
+    /// Verify the aggregation as [`Self::verify_aggregation`] does, but
+    /// check every share on its own, without randomness
+    pub(crate) fn verify_aggregation_exact(
+        &self,
+        dkg: &PubliclyVerifiableDkg<E>,
+        dealers: &BTreeSet<u32>,
+    ) -> Result<u32> {
+        print_time!("PVSS verify_aggregation_exact");
+        let (shares_total, transcripts) =
+            self.aggregated_transcripts(dkg, dealers)?;
+        let aggregate_failures = self.failed_shares(dkg);
+        if aggregate_failures.is_empty() {
+            Ok(shares_total)
+        } else {
+            Err(VerificationReport {
+                aggregate_failures,
+                faulty_dealers: Self::faulty_dealers(
+                    dealers,
+                    transcripts
+                        .iter()
+                        .map(|pvss| pvss.failed_shares(dkg))
+                        .collect(),
+                ),
+            }
+            .into())
+        }
+    }
+
+    /// Check that the commitment to the final key is the sum of the
+    /// commitments of `dealers`, and return the total weight of the
+    /// dealers with their transcripts
+    fn aggregated_transcripts<'a>(
+        &self,
+        dkg: &'a PubliclyVerifiableDkg<E>,
+        dealers: &BTreeSet<u32>,
+    ) -> Result<(u32, Vec<&'a Pvss<E>>)> {
+        if !self.is_well_formed(dkg) {
+            return Err(DkgError::MalformedAggregation);
+        }
+        // First, we verify that the aggregated PVSS transcript is a valid aggregation
         // If it is, we return the total weights of the PVSS transcripts
         let mut y = E::G1Projective::zero();
         // TODO: If we don't deal with share weights anymore, do we even need to call `verify_aggregation`?
         let mut shares_total = 0u32;
-        for (_, pvss) in dkg.vss.iter() {
+        let mut transcripts = Vec::with_capacity(dealers.len());
+        for dealer in dealers {
+            let pvss = dkg
+                .vss
+                .get(dealer)
+                .ok_or(DkgError::MissingTranscript(*dealer))?;
             y += pvss.coeffs[0].into_projective();
-            shares_total += 1
+            shares_total += dkg.validators[*dealer as usize].weight;
+            transcripts.push(pvss);
         }
-        if y.into_affine() == self.coeffs[0] {
-            Ok(shares_total)
-        } else {
-            Err(anyhow!(
-                "aggregation does not match received PVSS instances"
-            ))
+        if y.into_affine() != self.coeffs[0] {
+            return Err(DkgError::AggregationMismatch);
         }
+        Ok((shares_total, transcripts))
+    }
+
+    /// Pair `dealers` with the failed shares of their transcripts, keeping
+    /// the dealers with any failure
+    fn faulty_dealers(
+        dealers: &BTreeSet<u32>,
+        failures: Vec<Vec<FailedShare>>,
+    ) -> BTreeMap<u32, Vec<FailedShare>> {
+        dealers
+            .iter()
+            .copied()
+            .zip(failures)
+            .filter(|(_, failures)| !failures.is_empty())
+            .collect()
+    }
+}
+
+/// A share of a PVSS transcript that does not match its commitment
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FailedShare {
+    /// The index of the validator the share was dealt to
+    pub validator: usize,
+    /// The index of the share in the domain
+    pub share_index: usize,
+}
+
+/// The outcome of a failed verification of an aggregated PVSS transcript
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerificationReport {
+    /// The shares of the aggregated transcript that failed to verify
+    pub aggregate_failures: Vec<FailedShare>,
+    /// The dealers whose transcripts contain shares that failed to verify
+    pub faulty_dealers: BTreeMap<u32, Vec<FailedShare>>,
+}
+
+impl std::fmt::Display for VerificationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "aggregation failed share verification: {} invalid shares, faulty dealers {:?}",
+            self.aggregate_failures.len(),
+            self.faulty_dealers.keys().collect::<Vec<_>>()
+        )
+    }
+}
+
+impl std::error::Error for VerificationReport {}
+
+/// The message signed by the proof of knowledge of a PVSS transcript:
+/// the hash to curve of the `dealer` address, the session id `tau` and
+/// the commitment `coeffs`
+pub(crate) fn pok_message<E: PairingEngine>(
+    dealer: &ExternalValidator<E>,
+    tau: u64,
+    coeffs: &[E::G1Affine],
+) -> E::G2Affine {
+    hash_to_g2(&pok_bytes::<E>(POK_DOMAIN, dealer, tau, coeffs))
+}
+
+/// The message signed by the proof of knowledge of a refresh transcript,
+/// as [`pok_message`] with the refresh `round`, so that the transcript
+/// can not be re-posted by another validator or in another round
+pub(crate) fn refresh_pok_message<E: PairingEngine>(
+    dealer: &ExternalValidator<E>,
+    tau: u64,
+    round: u64,
+    coeffs: &[E::G1Affine],
+) -> E::G2Affine {
+    let mut bytes = pok_bytes::<E>(REFRESH_POK_DOMAIN, dealer, tau, coeffs);
+    bytes.extend_from_slice(&round.to_le_bytes());
+    hash_to_g2(&bytes)
+}
+
+/// The bytes hashed into the message of a proof of knowledge, tagged with
+/// `domain`
+fn pok_bytes<E: PairingEngine>(
+    domain: &[u8],
+    dealer: &ExternalValidator<E>,
+    tau: u64,
+    coeffs: &[E::G1Affine],
+) -> Vec<u8> {
+    let mut bytes = domain.to_vec();
+    bytes.extend_from_slice(&tau.to_le_bytes());
+    bytes.extend_from_slice(&(dealer.address.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(dealer.address.as_bytes());
+    coeffs
+        .serialize(&mut bytes)
+        .expect("serializing to a vector can not fail");
+    bytes
+}
+
+/// Check the proof of knowledge `sigma` of the secret committed to by
+/// `coeffs`, dealt by `dealer` in the session `tau`
+pub(crate) fn verify_pok<E: PairingEngine>(
+    dealer: &ExternalValidator<E>,
+    tau: u64,
+    coeffs: &[E::G1Affine],
+    sigma: E::G2Affine,
+) -> bool {
+    // "Does the first coefficient of the secret polynomial match the proof of knowledge?"
+    match coeffs.first() {
+        Some(f_0) => {
+            E::pairing(
+                *f_0,                                  // F_0 = g^s
+                pok_message::<E>(dealer, tau, coeffs), // H(m)
+            ) == E::pairing(
+                E::G1Affine::prime_subgroup_generator(), // g
+                sigma,                                   // H(m)^s
+            )
+        }
+        None => false,
+    }
+}
+
+/// Check the proof of knowledge `sigma` of the coefficient of degree one
+/// of the refresh transcript committed to by `coeffs`, dealt by `dealer`
+/// in refresh round `round` of the session `tau`
+pub(crate) fn verify_refresh_pok<E: PairingEngine>(
+    dealer: &ExternalValidator<E>,
+    tau: u64,
+    round: u64,
+    coeffs: &[E::G1Affine],
+    sigma: E::G2Affine,
+) -> bool {
+    match coeffs.get(1) {
+        Some(f_1) if !f_1.is_zero() => {
+            E::pairing(
+                *f_1,
+                refresh_pok_message::<E>(dealer, tau, round, coeffs),
+            ) == E::pairing(E::G1Affine::prime_subgroup_generator(), sigma)
+        }
+        _ => false,
     }
 }
 
@@ -197,18 +514,53 @@ impl<E: PairingEngine, T: Aggregate> PubliclyVerifiableSS<E, T> {
 pub fn aggregate<E: PairingEngine>(
     dkg: &PubliclyVerifiableDkg<E>,
 ) -> PubliclyVerifiableSS<E, Aggregated> {
-    let pvss = &dkg.vss;
-    let mut pvss_iter = pvss.iter();
-    let (_, first_pvss) = pvss_iter.next().unwrap();
+    // The refresh transcripts applied after the DKG are aggregated too
+    let mut aggregate =
+        aggregate_transcripts(dkg.vss.values().chain(dkg.refresh.iter()));
+    add_rekeyed_shares(dkg, &mut aggregate.shares);
+    aggregate
+}
+
+/// Add to `shares` the change of the shares of the validators that
+/// registered a new session key, so that they are encrypted to it
+fn add_rekeyed_shares<E: PairingEngine>(
+    dkg: &PubliclyVerifiableDkg<E>,
+    shares: &mut [ShareEncryptions<E>],
+) {
+    for (validator, rekeyed) in dkg.rekeyed.iter() {
+        shares[*validator as usize]
+            .iter_mut()
+            .zip_eq(rekeyed.shares.iter())
+            .for_each(|(a, b)| *a = *a + *b);
+    }
+}
+
+/// Aggregate the PVSS instances of `dealers` only, which must all have
+/// dealt a transcript in `dkg`
+pub fn aggregate_dealers<E: PairingEngine>(
+    dkg: &PubliclyVerifiableDkg<E>,
+    dealers: &BTreeSet<u32>,
+) -> PubliclyVerifiableSS<E, Aggregated> {
+    aggregate_transcripts(dealers.iter().map(|dealer| &dkg.vss[dealer]))
+}
+
+fn aggregate_transcripts<'a, E: PairingEngine>(
+    mut pvss_iter: impl Iterator<Item = &'a PubliclyVerifiableSS<E>>,
+) -> PubliclyVerifiableSS<E, Aggregated> {
+    let first_pvss = pvss_iter.next().unwrap();
     let mut coeffs = batch_to_projective(&first_pvss.coeffs);
     let mut sigma = first_pvss.sigma;
 
-    let mut shares = batch_to_projective(&first_pvss.shares);
+    let mut shares = first_pvss
+        .shares
+        .iter()
+        .map(|shares| batch_to_projective(shares))
+        .collect::<Vec<_>>();
 
     // So now we're iterating over the PVSS instances, and adding their coefficients and shares, and their sigma
-    // sigma is the sum of all the sigma_i, which is the proof of knowledge of the secret polynomial
+    // sigma is the sum of all the sigma_i, an aggregate of the dealers' proofs of knowledge
     // Aggregating is just adding the corresponding values in pvss instances, so pvss = pvss + pvss_j
-    for (_, next) in pvss_iter {
+    for next in pvss_iter {
         sigma = sigma.add(next.sigma);
         coeffs
             .iter_mut()
@@ -217,9 +569,16 @@ pub fn aggregate<E: PairingEngine>(
         shares
             .iter_mut()
             .zip_eq(next.shares.iter())
-            .for_each(|(a, b)| *a += b.into_projective());
+            .for_each(|(a, b)| {
+                a.iter_mut()
+                    .zip_eq(b.iter())
+                    .for_each(|(a, b)| *a += b.into_projective())
+            });
     }
-    let shares = E::G2Projective::batch_normalization_into_affine(&shares);
+    let shares = shares
+        .iter()
+        .map(|shares| E::G2Projective::batch_normalization_into_affine(shares))
+        .collect::<Vec<ShareEncryptions<E>>>();
 
     PubliclyVerifiableSS {
         coeffs: E::G1Projective::batch_normalization_into_affine(&coeffs),
@@ -237,35 +596,43 @@ pub fn aggregate_for_decryption<E: PairingEngine>(
     let shares = dkg
         .vss
         .values()
+        .chain(dkg.refresh.iter())
         .map(|pvss| pvss.shares.clone())
         .collect::<Vec<_>>();
     let first_share = shares.first().unwrap().to_vec();
-    shares
+    let mut shares = shares
         .into_iter()
         .skip(1)
         // We're assuming that in every PVSS instance, the shares are in the same order
         .fold(first_share, |acc, shares| {
             acc.into_iter()
-                .zip_eq(shares.into_iter())
-                .map(|(a, b)| a + b)
+                .zip_eq(shares)
+                .map(|(a, b)| {
+                    a.into_iter().zip_eq(b).map(|(a, b)| a + b).collect()
+                })
                 .collect()
-        })
+        });
+    add_rekeyed_shares(dkg, &mut shares);
+    shares
 }
 
 pub fn make_decryption_shares<E: PairingEngine>(
     ciphertext: &Ciphertext<E>,
     validator_keypairs: Vec<Keypair<E>>,
-    aggregate: Vec<E::G2Affine>,
+    aggregate: Vec<ShareEncryptions<E>>,
 ) -> Vec<DecryptionShareSimple<E>> {
     aggregate
         .iter()
         .zip_eq(validator_keypairs.iter())
-        .map(|(encrypted_share, keypair)| {
-            // Decrypt private key shares https://nikkolasg.github.io/ferveo/pvss.html#validator-decryption-of-private-key-shares
-            let z_i = encrypted_share
-                .mul(keypair.decryption_key.inverse().unwrap().into_repr());
-            let u = ciphertext.commitment;
-            E::pairing(u, z_i)
+        .flat_map(|(encrypted_shares, keypair)| {
+            let decryption_key = keypair.decryption_key.inverse().unwrap();
+            // Each validator decrypts every share it holds with the same key
+            encrypted_shares.iter().map(move |encrypted_share| {
+                // Decrypt private key shares https://nikkolasg.github.io/ferveo/pvss.html#validator-decryption-of-private-key-shares
+                let z_i = encrypted_share.mul(decryption_key.into_repr());
+                let u = ciphertext.commitment;
+                E::pairing(u, z_i)
+            })
         })
         .enumerate()
         .map(
@@ -308,14 +675,89 @@ mod test_pvss {
         );
         // check that the correct number of shares were created
         assert_eq!(pvss.shares.len(), dkg.validators.len());
-        // check that the prove of knowledge is correct
-        assert_eq!(pvss.sigma, G2::prime_subgroup_generator().mul(s));
+        // check that the proof of knowledge is correct
+        let dealer = &dkg.validators[dkg.me].validator;
+        assert_eq!(
+            pvss.sigma,
+            pok_message::<EllipticCurve>(dealer, dkg.params.tau, &pvss.coeffs)
+                .mul(s)
+        );
         // check that the optimistic verify returns true
-        assert!(pvss.verify_optimistic());
+        assert!(pvss.verify_optimistic(&dkg, dealer));
         // check that the full verify returns true
         assert!(pvss.verify_full(&dkg));
     }
 
+    /// Test that validators with a larger voting power
+    /// are dealt a proportionally larger number of shares
+    #[test]
+    fn test_new_pvss_weighted() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dkg_with_powers(2, 8, &[1, 2, 5], 0);
+        let s = Fr::rand(rng);
+        let pvss =
+            Pvss::<EllipticCurve>::new(&s, &dkg, rng).expect("Test failed");
+        // check that each validator was dealt `weight` shares
+        let weights = pvss.shares.iter().map(|s| s.len()).collect::<Vec<_>>();
+        assert_eq!(weights, vec![1, 2, 5]);
+        // check that the full verify returns true
+        assert!(pvss.verify_full(&dkg));
+    }
+
+    /// Test that a pvss is correctly created and verified when the
+    /// number of shares is not a power of two
+    #[test]
+    fn test_new_pvss_non_power_of_two() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dkg_for_n_validators(2, 5, 0);
+        let s = Fr::rand(rng);
+        let pvss =
+            Pvss::<EllipticCurve>::new(&s, &dkg, rng).expect("Test failed");
+        assert_eq!(pvss.shares.len(), 5);
+        assert!(pvss.verify_full(&dkg));
+    }
+
+    /// Check that if one of the shares of a validator is wrong,
+    /// the full verification of PVSS fails
+    #[test]
+    fn test_verify_pvss_wrong_weighted_share() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dkg_with_powers(2, 8, &[1, 2, 5], 0);
+        let s = Fr::rand(rng);
+        let mut pvss =
+            Pvss::<EllipticCurve>::new(&s, &dkg, rng).expect("Test failed");
+        pvss.shares[2][3] = G2::zero();
+        assert!(!pvss.verify_full(&dkg));
+    }
+
+    /// Check that the failing shares of a transcript are
+    /// identified, including missing shares
+    #[test]
+    fn test_failed_shares() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dkg_with_powers(2, 8, &[1, 2, 5], 0);
+        let s = Fr::rand(rng);
+        let mut pvss =
+            Pvss::<EllipticCurve>::new(&s, &dkg, rng).expect("Test failed");
+        assert!(pvss.failed_shares(&dkg).is_empty());
+
+        pvss.shares[2][3] = G2::zero();
+        pvss.shares[1].pop();
+        assert_eq!(
+            pvss.failed_shares(&dkg),
+            vec![
+                FailedShare {
+                    validator: 1,
+                    share_index: 2
+                },
+                FailedShare {
+                    validator: 2,
+                    share_index: 6
+                },
+            ]
+        );
+    }
+
     /// Check that if the proof of knowledge is wrong,
     /// the optimistic verification of PVSS fails
     #[test]
@@ -331,14 +773,51 @@ mod test_pvss {
             PubliclyVerifiableSS::<EllipticCurve>::new(&s, &dkg, rng)
                 .expect("Test failed");
 
+        let dealer = dkg.validators[dkg.me].validator.clone();
+        assert!(pvss.verify_optimistic(&dkg, &dealer));
         pvss.sigma = G2::zero();
-        assert!(!pvss.verify_optimistic());
+        assert!(!pvss.verify_optimistic(&dkg, &dealer));
+    }
+
+    /// Check that the proof of knowledge does not verify if the
+    /// transcript is replayed by another dealer or in another session
+    #[test]
+    fn test_verify_pvss_replayed_proof_of_knowledge() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dkg(0);
+        let pvss = Pvss::<EllipticCurve>::new(&Fr::rand(rng), &dkg, rng)
+            .expect("Test failed");
+        let dealer = dkg.validators[0].validator.clone();
+        let other = dkg.validators[1].validator.clone();
+        assert!(pvss.verify_optimistic(&dkg, &dealer));
+        assert!(!pvss.verify_optimistic(&dkg, &other));
+        dkg.params.tau += 1;
+        assert!(!pvss.verify_optimistic(&dkg, &dealer));
+    }
+
+    /// Check that the proof of knowledge does not verify if
+    /// the commitment is modified
+    #[test]
+    fn test_verify_pvss_proof_of_knowledge_binds_commitment() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dkg(0);
+        let mut pvss = Pvss::<EllipticCurve>::new(&Fr::rand(rng), &dkg, rng)
+            .expect("Test failed");
+        let dealer = dkg.validators[0].validator.clone();
+        pvss.coeffs[1] = G1::prime_subgroup_generator();
+        assert!(!pvss.verify_optimistic(&dkg, &dealer));
+    }
+
+    /// The dealers of every transcript received by `dkg`
+    fn dealers(dkg: &PubliclyVerifiableDkg<EllipticCurve>) -> BTreeSet<u32> {
+        dkg.vss.keys().copied().collect()
     }
 
     /// Check that happy flow of aggregating PVSS transcripts
     /// Should have the correct form and validations pass
     #[test]
     fn test_aggregate_pvss() {
+        let rng = &mut ark_std::test_rng();
         let dkg = setup_dealt_dkg();
         let aggregate = aggregate(&dkg);
         //check that a polynomial of the correct degree was created
@@ -348,21 +827,79 @@ mod test_pvss {
         );
         // check that the correct number of shares were created
         assert_eq!(aggregate.shares.len(), dkg.validators.len());
-        // check that the optimistic verify returns true
-        assert!(aggregate.verify_optimistic());
         // check that the full verify returns true
         assert!(aggregate.verify_full(&dkg));
         // check that the verification of aggregation passes
         assert_eq!(
-            aggregate.verify_aggregation(&dkg).expect("Test failed"),
+            aggregate
+                .verify_aggregation(&dkg, &dealers(&dkg), rng)
+                .expect("Test failed"),
             dkg.validators.len() as u32
         );
     }
 
+    /// Check that an aggregation of a subset of the transcripts
+    /// only verifies against that exact set of dealers
+    #[test]
+    fn test_aggregate_pvss_subset() {
+        let rng = &mut ark_std::test_rng();
+        let dkg = setup_dealt_dkg();
+        let subset = [0, 2].iter().copied().collect::<BTreeSet<_>>();
+        let aggregate = aggregate_dealers(&dkg, &subset);
+        assert!(aggregate.verify_full(&dkg));
+        assert_eq!(
+            aggregate
+                .verify_aggregation(&dkg, &subset, rng)
+                .expect("Test failed"),
+            2
+        );
+        assert!(matches!(
+            aggregate.verify_aggregation(&dkg, &dealers(&dkg), rng),
+            Err(DkgError::AggregationMismatch)
+        ));
+        let unknown = [0, 2, 7].iter().copied().collect::<BTreeSet<_>>();
+        assert!(matches!(
+            aggregate.verify_aggregation(&dkg, &unknown, rng),
+            Err(DkgError::MissingTranscript(7))
+        ));
+    }
+
+    /// Check that if a dealer's transcript contains a bad share, the
+    /// verification of the aggregation fails and names the dealer
+    #[test]
+    fn test_verify_aggregation_reports_faulty_dealer() {
+        let rng = &mut ark_std::test_rng();
+        let mut dkg = setup_dealt_dkg();
+        dkg.vss.get_mut(&1).unwrap().shares[2][0] = G2::zero();
+        let aggregated = aggregate(&dkg);
+        let err = aggregated
+            .verify_aggregation(&dkg, &dealers(&dkg), rng)
+            .expect_err("Test failed");
+        let report = match err {
+            DkgError::AggregationFailed(report) => report,
+            _ => panic!("Test failed"),
+        };
+        assert_eq!(
+            report.aggregate_failures,
+            vec![FailedShare {
+                validator: 2,
+                share_index: 2
+            }]
+        );
+        assert_eq!(report.faulty_dealers.keys().collect::<Vec<_>>(), vec![&1]);
+        assert_eq!(report.faulty_dealers[&1], report.aggregate_failures);
+        // the exact check reports the same failures
+        assert!(matches!(
+            aggregated.verify_aggregation_exact(&dkg, &dealers(&dkg)),
+            Err(DkgError::AggregationFailed(exact)) if exact == report
+        ));
+    }
+
     /// Check that if the aggregated pvss transcript has an
     /// incorrect constant term, the verification fails
     #[test]
     fn test_verify_aggregation_fails_if_constant_term_wrong() {
+        let rng = &mut ark_std::test_rng();
         use std::ops::Neg;
         let dkg = setup_dealt_dkg();
         let mut aggregated = aggregate(&dkg);
@@ -371,12 +908,9 @@ mod test_pvss {
             aggregated = aggregate(&dkg);
         }
         aggregated.coeffs[0] = G1::zero();
-        assert_eq!(
-            aggregated
-                .verify_aggregation(&dkg)
-                .expect_err("Test failed")
-                .to_string(),
-            "aggregation does not match received PVSS instances"
-        )
+        assert!(matches!(
+            aggregated.verify_aggregation(&dkg, &dealers(&dkg), rng),
+            Err(DkgError::AggregationMismatch)
+        ))
     }
 }
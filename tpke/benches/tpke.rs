@@ -345,7 +345,7 @@ pub fn bench_refresh_shares(c: &mut Criterion) {
     let msg_size = MSG_SIZE_CASES[0];
 
     for &shares_num in NUM_SHARES_CASES.iter() {
-        let mut setup = SetupSimple::new(shares_num, msg_size, rng);
+        let setup = SetupSimple::new(shares_num, msg_size, rng);
         let threshold = setup.shared.threshold;
         group.bench_function(
             BenchmarkId::new("Refresh Shares", shares_num),
@@ -353,7 +353,7 @@ pub fn bench_refresh_shares(c: &mut Criterion) {
                 let mut rng = rand::rngs::StdRng::seed_from_u64(0);
                 b.iter(|| {
                     black_box(refresh_shares::<E>(
-                        &mut setup.contexts,
+                        &setup.contexts,
                         threshold,
                         &mut rng,
                     ));
@@ -1,5 +1,4 @@
 use crate::hash_to_curve::htp_bls12381_g2;
-use crate::SetupParams;
 
 use ark_ec::{AffineCurve, PairingEngine};
 use ark_ff::{Field, One, PrimeField, ToBytes, UniformRand, Zero};
@@ -12,7 +11,6 @@ use itertools::izip;
 use subproductdomain::SubproductDomain;
 
 use rand_core::RngCore;
-use std::usize;
 use thiserror::Error;
 
 mod ciphertext;
@@ -58,7 +56,8 @@ pub enum ThresholdEncryptionError {
 
 pub type Result<T> = std::result::Result<T, ThresholdEncryptionError>;
 
-fn hash_to_g2<T: ark_serialize::CanonicalDeserialize>(message: &[u8]) -> T {
+/// Hash `message` to a point of the BLS12-381 G2 group
+pub fn hash_to_g2<T: ark_serialize::CanonicalDeserialize>(message: &[u8]) -> T {
     let mut point_ser: Vec<u8> = Vec::new();
     let point = htp_bls12381_g2(message);
     point.serialize(&mut point_ser).unwrap();
@@ -96,10 +95,13 @@ pub fn setup_fast<E: PairingEngine>(
     // The dealer chooses a uniformly random polynomial f of degree t-1
     let threshold_poly = DensePolynomial::<E::Fr>::rand(threshold - 1, rng);
     // Domain, or omega Ω
+    // The domain may be larger than `shares_num` if it is not a power of two,
+    // in which case only the first `shares_num` points are used
     let fft_domain =
         ark_poly::Radix2EvaluationDomain::<E::Fr>::new(shares_num).unwrap();
     // `evals` are evaluations of the polynomial f over the domain, omega: f(ω_j) for ω_j in Ω
-    let evals = threshold_poly.evaluate_over_domain_by_ref(fft_domain);
+    let mut evals = threshold_poly.evaluate_over_domain_by_ref(fft_domain);
+    evals.evals.truncate(shares_num);
 
     let mut domain_points = Vec::with_capacity(shares_num);
     let mut point = E::Fr::one();
@@ -197,12 +199,15 @@ pub fn setup_simple<E: PairingEngine>(
     // The dealer chooses a uniformly random polynomial f of degree t-1
     let threshold_poly = DensePolynomial::<E::Fr>::rand(threshold - 1, rng);
     // Domain, or omega Ω
+    // The domain may be larger than `shares_num` if it is not a power of two,
+    // in which case only the first `shares_num` points are used
     let fft_domain =
         ark_poly::Radix2EvaluationDomain::<E::Fr>::new(shares_num).unwrap();
     // `evals` are evaluations of the polynomial f over the domain, omega: f(ω_j) for ω_j in Ω
-    let evals = threshold_poly.evaluate_over_domain_by_ref(fft_domain);
+    let mut evals = threshold_poly.evaluate_over_domain_by_ref(fft_domain);
+    evals.evals.truncate(shares_num);
 
-    let shares_x = fft_domain.elements().collect::<Vec<_>>();
+    let shares_x = fft_domain.elements().take(shares_num).collect::<Vec<_>>();
 
     // A - public key shares of participants
     let pubkey_shares =
@@ -373,14 +378,23 @@ mod tests {
 
     #[test]
     fn fast_threshold_encryption() {
+        fast_threshold_encryption_with_shares_num(16);
+    }
+
+    #[test]
+    fn fast_threshold_encryption_non_power_of_two() {
+        fast_threshold_encryption_with_shares_num(10);
+    }
+
+    fn fast_threshold_encryption_with_shares_num(shares_num: usize) {
         let mut rng = &mut test_rng();
-        let shares_num = 16;
         let threshold = shares_num * 2 / 3;
         let msg: &[u8] = "abc".as_bytes();
         let aad: &[u8] = "my-aad".as_bytes();
 
         let (pubkey, _, contexts) =
             setup_fast::<E>(threshold, shares_num, &mut rng);
+        assert_eq!(contexts.len(), shares_num);
         let ciphertext = encrypt::<_, E>(msg, aad, &pubkey, rng);
 
         let mut shares: Vec<DecryptionShareFast<E>> = vec![];
@@ -405,14 +419,23 @@ mod tests {
 
     #[test]
     fn simple_threshold_decryption() {
+        simple_threshold_decryption_with_shares_num(16);
+    }
+
+    #[test]
+    fn simple_threshold_decryption_non_power_of_two() {
+        simple_threshold_decryption_with_shares_num(10);
+    }
+
+    fn simple_threshold_decryption_with_shares_num(shares_num: usize) {
         let mut rng = &mut test_rng();
-        let shares_num = 16;
         let threshold = shares_num * 2 / 3;
         let msg: &[u8] = "abc".as_bytes();
         let aad: &[u8] = "my-aad".as_bytes();
 
         let (pubkey, _, contexts) =
             setup_simple::<E>(threshold, shares_num, &mut rng);
+        assert_eq!(contexts.len(), shares_num);
 
         // Ciphertext.commitment is already computed to match U
         let ciphertext = encrypt::<_, E>(msg, aad, &pubkey, rng);
@@ -6,7 +6,6 @@ use itertools::zip_eq;
 use rand::prelude::StdRng;
 use rand_core::RngCore;
 use std::collections::HashMap;
-use std::usize;
 
 pub fn recover_share_at_point<E: PairingEngine>(
     other_participants: &[PrivateDecryptionContextSimple<E>],